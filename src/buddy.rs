@@ -0,0 +1,276 @@
+use crate::region::RegionAllocator;
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    mem::size_of,
+    ptr::{self, null_mut},
+    sync::{
+        atomic::{AtomicPtr, Ordering},
+        Mutex,
+    },
+};
+
+/// Order of the smallest block: it must be able to hold a free list link.
+const MIN_ORDER: usize = size_of::<usize>().trailing_zeros() as usize;
+/// Alignment cap for the arena, blocks aligned further than a page can't be guaranteed.
+const MAX_ARENA_ALIGN: usize = 4096;
+/// Marker of an empty free list, or of the end of a free list.
+const NO_BLOCK: usize = usize::MAX;
+
+/// Heap allocator splitting its arena into power-of-two sized blocks.
+///
+/// An allocation is served by the smallest free block able to hold it, splitting larger blocks in two
+/// halves (buddies) as needed. On deallocation, a block is merged with its buddy if it is free too,
+/// repeating up to the whole arena. Internal fragmentation is bounded to half of a block.
+///
+/// * `ORDER`: the arena size is `2^ORDER` bytes
+///
+/// Free blocks of each order are kept in an intrusive linked list of arena offsets.
+/// Block sizes are recomputed from the layout on deallocation, so no metadata is stored along allocations.
+pub struct BuddyAllocator<const ORDER: usize> {
+    arena_ptr: AtomicPtr<u8>,
+    /// Free list head offset for each block order
+    free_lists: Mutex<[usize; usize::BITS as usize]>,
+}
+
+impl<const ORDER: usize> BuddyAllocator<ORDER> {
+    /// Size of the arena, in bytes.
+    pub const ARENA_SIZE: usize = 1 << ORDER;
+    /// Size of the smallest block, in bytes.
+    pub const MIN_BLOCK_SIZE: usize = 1 << MIN_ORDER;
+    /// Largest supported alignment.
+    pub const MAX_ALIGN: usize = if Self::ARENA_SIZE > MAX_ARENA_ALIGN {
+        MAX_ARENA_ALIGN
+    } else {
+        Self::ARENA_SIZE
+    };
+
+    /// Create a new instance of buddy allocator, initialize the heap memory region for future allocations.
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        const {
+            assert!(
+                ORDER >= MIN_ORDER && ORDER < usize::BITS as usize - 1,
+                "buddy allocator order is out of bounds"
+            )
+        };
+
+        let arena_ptr = unsafe { GlobalAlloc::alloc(&System, Self::arena_layout()) };
+        Self {
+            arena_ptr: AtomicPtr::new(arena_ptr),
+            free_lists: Mutex::new(unsafe { Self::initial_free_lists(arena_ptr) }),
+        }
+    }
+
+    /// Write the free lists of an arena without allocation: the whole arena is a single free block.
+    unsafe fn initial_free_lists(arena_ptr: *mut u8) -> [usize; usize::BITS as usize] {
+        let mut free_lists = [NO_BLOCK; usize::BITS as usize];
+        if !arena_ptr.is_null() {
+            ptr::write(arena_ptr as *mut usize, NO_BLOCK);
+            free_lists[ORDER] = 0;
+        }
+        free_lists
+    }
+
+    fn arena_layout() -> Layout {
+        Layout::from_size_align(Self::ARENA_SIZE, Self::MAX_ALIGN).unwrap()
+    }
+
+    /// Order of the block required to hold the given layout, None if it can't be allocated.
+    fn block_order(layout: Layout) -> Option<usize> {
+        if layout.align() > Self::MAX_ALIGN {
+            return None;
+        }
+
+        // Blocks are aligned to their size
+        let size = layout
+            .size()
+            .max(layout.align())
+            .max(Self::MIN_BLOCK_SIZE)
+            .checked_next_power_of_two()?;
+        let order = size.trailing_zeros() as usize;
+        if order > ORDER {
+            None
+        } else {
+            Some(order)
+        }
+    }
+
+    /// Read the next block link stored in the free block at the given offset.
+    unsafe fn next_block(&self, offset: usize) -> usize {
+        ptr::read(self.arena_ptr.load(Ordering::Acquire).add(offset) as *const usize)
+    }
+
+    /// Push the block at the given offset as the head of a free list.
+    unsafe fn push_block(&self, free_lists: &mut [usize], order: usize, offset: usize) {
+        let block_ptr = self.arena_ptr.load(Ordering::Acquire).add(offset);
+        ptr::write(block_ptr as *mut usize, free_lists[order]);
+        free_lists[order] = offset;
+    }
+
+    /// Remove the block at the given offset from a free list.
+    ///
+    /// **Returns**: true if the block was found in the free list
+    unsafe fn remove_block(&self, free_lists: &mut [usize], order: usize, offset: usize) -> bool {
+        if free_lists[order] == offset {
+            free_lists[order] = self.next_block(offset);
+            return true;
+        }
+
+        let mut previous = free_lists[order];
+        while previous != NO_BLOCK {
+            let current = self.next_block(previous);
+            if current == offset {
+                // Unlink the block
+                let block_ptr = self.arena_ptr.load(Ordering::Acquire).add(previous);
+                ptr::write(block_ptr as *mut usize, self.next_block(current));
+                return true;
+            }
+            previous = current;
+        }
+
+        false
+    }
+}
+
+unsafe impl<const ORDER: usize> GlobalAlloc for BuddyAllocator<ORDER> {
+    /// Allocate the smallest block able to hold the layout, splitting larger blocks as needed.
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let order = match Self::block_order(layout) {
+            Some(order) => order,
+            None => return null_mut(),
+        };
+
+        let mut free_lists = self.free_lists.lock().unwrap();
+
+        // Find the smallest free block large enough
+        let mut current_order = match (order..=ORDER).find(|&o| free_lists[o] != NO_BLOCK) {
+            Some(o) => o,
+            None => return null_mut(), // No memory available
+        };
+        let offset = free_lists[current_order];
+        free_lists[current_order] = self.next_block(offset);
+
+        // Split the block until it has the required size, freeing the upper halves
+        while current_order > order {
+            current_order -= 1;
+            self.push_block(
+                &mut free_lists[..],
+                current_order,
+                offset + (1 << current_order),
+            );
+        }
+
+        self.arena_ptr.load(Ordering::Acquire).add(offset)
+    }
+
+    /// Release a block, merging it with its buddy for as long as the buddy is free.
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let mut order = match Self::block_order(layout) {
+            Some(order) => order,
+            None => return, // Can't have been allocated
+        };
+        let mut offset = ptr as usize - self.arena_ptr.load(Ordering::Acquire) as usize;
+
+        let mut free_lists = self.free_lists.lock().unwrap();
+        while order < ORDER {
+            let buddy = offset ^ (1 << order);
+            if !self.remove_block(&mut free_lists[..], order, buddy) {
+                // Buddy is allocated (or split)
+                break;
+            }
+
+            // Merged block starts at the lowest buddy
+            offset = offset.min(buddy);
+            order += 1;
+        }
+
+        self.push_block(&mut free_lists[..], order, offset);
+    }
+}
+
+impl<const ORDER: usize> RegionAllocator for BuddyAllocator<ORDER> {
+    /// Release every block at once, merging the whole arena back into a single free block.
+    unsafe fn reset(&self) {
+        let mut free_lists = self.free_lists.lock().unwrap();
+        *free_lists = Self::initial_free_lists(self.arena_ptr.load(Ordering::Acquire));
+    }
+}
+
+impl<const ORDER: usize> Drop for BuddyAllocator<ORDER> {
+    fn drop(&mut self) {
+        let arena_ptr = self.arena_ptr.load(Ordering::Acquire);
+        if !arena_ptr.is_null() {
+            unsafe { GlobalAlloc::dealloc(&System, arena_ptr, Self::arena_layout()) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::buddy::*;
+
+    #[test]
+    fn reset_merges_whole_arena() {
+        let buddy = BuddyAllocator::<8>::new();
+        let layout = Layout::new::<u64>();
+        for _ in 0..4 {
+            assert!(!unsafe { buddy.alloc(layout) }.is_null());
+        }
+
+        unsafe { buddy.reset() };
+        let whole = Layout::from_size_align(BuddyAllocator::<8>::ARENA_SIZE, 1).unwrap();
+        assert!(!unsafe { buddy.alloc(whole) }.is_null());
+    }
+
+    #[test]
+    fn alloc_too_large_null() {
+        let buddy = BuddyAllocator::<6>::new();
+        let ptr = unsafe { buddy.alloc(Layout::new::<[u8; 65]>()) };
+        assert!(ptr.is_null());
+    }
+
+    #[test]
+    fn alloc_whole_arena() {
+        let buddy = BuddyAllocator::<6>::new();
+        let layout = Layout::new::<[u8; 64]>();
+
+        let ptr = unsafe { buddy.alloc(layout) };
+        assert!(!ptr.is_null());
+        assert!(unsafe { buddy.alloc(Layout::new::<u8>()) }.is_null());
+    }
+
+    #[test]
+    fn alloc_blocks_aligned_to_size() {
+        let buddy = BuddyAllocator::<8>::new();
+        let small = Layout::new::<u8>();
+        let large = Layout::new::<[u8; 64]>();
+
+        let a = unsafe { buddy.alloc(small) };
+        let b = unsafe { buddy.alloc(large) };
+        let c = unsafe { buddy.alloc(small) };
+
+        assert_eq!(0, b as usize % 64);
+        // Smallest blocks are buddies
+        assert_eq!(BuddyAllocator::<8>::MIN_BLOCK_SIZE, c as usize - a as usize);
+    }
+
+    #[test]
+    fn dealloc_merges_buddies() {
+        let buddy = BuddyAllocator::<7>::new();
+        let layout = Layout::new::<u8>();
+
+        // Split the whole arena into smallest blocks
+        let count = BuddyAllocator::<7>::ARENA_SIZE / BuddyAllocator::<7>::MIN_BLOCK_SIZE;
+        let ptrs: Vec<*mut u8> = (0..count).map(|_| unsafe { buddy.alloc(layout) }).collect();
+        assert!(ptrs.iter().all(|ptr| !ptr.is_null()));
+        assert!(unsafe { buddy.alloc(layout) }.is_null());
+
+        for ptr in ptrs.into_iter().rev() {
+            unsafe { buddy.dealloc(ptr, layout) };
+        }
+
+        // Everything merged back into the whole arena
+        let whole = unsafe { buddy.alloc(Layout::new::<[u8; 128]>()) };
+        assert!(!whole.is_null());
+    }
+}
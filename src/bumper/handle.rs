@@ -0,0 +1,49 @@
+use std::marker::PhantomData;
+
+/// Typed reference to a value allocated by a `BumpAllocator` (see `BumpAllocator::allocate_handle`).
+///
+/// The handle records the allocator generation at allocation time, which is incremented by each `dealloc_all` call:
+/// reading a handle created before a reset is detected instead of reading whatever was allocated in its place.
+pub struct BumpHandle<T> {
+    pub(super) offset: usize,
+    pub(super) generation: usize,
+    pub(super) _value: PhantomData<fn() -> T>,
+}
+
+impl<T> BumpHandle<T> {
+    /// Get the offset of the value from the arena start.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Get the generation of the allocator when the value was allocated.
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+}
+
+// Implemented manually, as derives would require `T` to implement the traits as well
+impl<T> Clone for BumpHandle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for BumpHandle<T> {}
+
+impl<T> std::fmt::Debug for BumpHandle<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BumpHandle")
+            .field("offset", &self.offset)
+            .field("generation", &self.generation)
+            .finish()
+    }
+}
+
+impl<T> PartialEq for BumpHandle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.offset == other.offset && self.generation == other.generation
+    }
+}
+
+impl<T> Eq for BumpHandle<T> {}
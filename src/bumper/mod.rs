@@ -0,0 +1,1591 @@
+use crate::{error::AllocError, layout::array_layout, region::RegionAllocator};
+
+pub use self::{
+    handle::BumpHandle, scope::BumpScope, thread_local::ThreadLocalBumpAllocator, typed::TypedArena,
+};
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    marker::PhantomData,
+    mem::{align_of, needs_drop, size_of, MaybeUninit},
+    pin::Pin,
+    ptr::{self, null_mut},
+    slice, str,
+    sync::{
+        atomic::{compiler_fence, AtomicPtr, AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+mod handle;
+mod scope;
+mod thread_local;
+mod typed;
+
+/// Heap allocator that simply places values after each other and isn't capable of single element deallocation.
+///
+/// This allocator is really fast and is able to deallocate all elements contained in it even faster.
+/// It supports memory wiping, writing 0 in each previously allocated byte.
+/// The arena is freed when the allocator is dropped, optionally wiping it first (see `new_zeroing`).
+///
+/// The arena is zeroed on creation, so bytes that were never allocated are 0. Once `dealloc_all(false)` is called,
+/// the previously allocated bytes keep their stale values: use `allocate_zeroed` to get zeroed memory whatever the arena history.
+///
+/// ## Thread safety
+/// The allocator is `Send` and `Sync`: its allocation state is only made of atomics, so it can be shared across threads,
+/// as a global allocator for instance. Allocations atomically advance the allocation cursor, so concurrent calls
+/// to `allocate` always hand out disjoint memory, and the returned `&mut T` never alias.
+/// Resetting or wiping the arena (`dealloc_all`, `secure_wipe`) takes `&mut self`, so it can't happen
+/// while an allocated reference is alive. Allocated values are never dropped, unless allocated with `allocate_owned`.
+pub struct BumpAllocator<const N: usize> {
+    arena_ptr: AtomicPtr<u8>,
+    allocated: AtomicUsize,
+    /// Highest value reached by the allocation cursor since the last wipe: the bytes past the cursor may hold
+    /// stale values, from scopes that ended for instance
+    high_water: AtomicUsize,
+    /// Count of values allocated since the last reset
+    alloc_count: AtomicUsize,
+    /// Count of resets, used to detect stale handles (see `BumpHandle`)
+    generation: AtomicUsize,
+    /// Count of alignment padding bytes inserted since the last reset
+    wasted_padding: AtomicUsize,
+    /// Wipe the whole arena before freeing it on drop
+    zero_on_drop: bool,
+    /// The arena was allocated from the `System` allocator, and must be freed on drop
+    owns_arena: bool,
+    /// Offsets and drop functions of the values allocated with `allocate_owned`, in allocation order
+    drops: Mutex<Vec<(usize, DropFn)>>,
+}
+
+impl<const N: usize> BumpAllocator<N> {
+    /// Create a new instance of bump allocator, initialize the heap memory region for future allocations.
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self::with_zero_on_drop(false)
+    }
+
+    /// Create a new instance of bump allocator which wipes its whole arena when dropped,
+    /// before giving the memory back to the system. This is meant for sensitive data, such as key material.
+    pub fn new_zeroing() -> Self {
+        Self::with_zero_on_drop(true)
+    }
+
+    fn with_zero_on_drop(zero_on_drop: bool) -> Self {
+        const { assert!(N > 0, "bump arena can't be empty") };
+
+        let layout = arena_layout(N);
+        // Zeroed so that the arena bytes can be inspected before being allocated (see `arena_bytes`)
+        let arena_ptr = unsafe { GlobalAlloc::alloc_zeroed(&System, layout) };
+        Self {
+            arena_ptr: AtomicPtr::new(arena_ptr),
+            allocated: AtomicUsize::new(0),
+            high_water: AtomicUsize::new(0),
+            alloc_count: AtomicUsize::new(0),
+            generation: AtomicUsize::new(0),
+            wasted_padding: AtomicUsize::new(0),
+            zero_on_drop,
+            owns_arena: true,
+            drops: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Create a new instance of bump allocator using the given buffer as its arena, without any `System` allocation.
+    /// This allows placing the arena in a static buffer or in a specific memory region.
+    ///
+    /// Only the first `N` bytes of the buffer are used. They are zeroed, as an allocated arena would be.
+    /// The buffer is borrowed for the whole program, so it is never freed.
+    ///
+    /// **Panics**: if the buffer is smaller than `N` bytes
+    pub fn from_slice(buf: &'static mut [u8]) -> Self {
+        const { assert!(N > 0, "bump arena can't be empty") };
+        assert!(
+            buf.len() >= N,
+            "bump arena buffer is too small: {} bytes, {N} expected",
+            buf.len()
+        );
+
+        buf[..N].fill(0);
+        Self {
+            arena_ptr: AtomicPtr::new(buf.as_mut_ptr()),
+            allocated: AtomicUsize::new(0),
+            high_water: AtomicUsize::new(0),
+            alloc_count: AtomicUsize::new(0),
+            generation: AtomicUsize::new(0),
+            wasted_padding: AtomicUsize::new(0),
+            zero_on_drop: false,
+            owns_arena: false,
+            drops: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Create a new allocator with a copy of this arena: allocated bytes are copied and the allocation cursor is kept.
+    ///
+    /// This is a shallow byte copy, suitable for plain data: pointers stored inside allocated values still point
+    /// to this arena. Values keep their offsets, so alignments larger than the arena base alignment aren't guaranteed
+    /// to be preserved in the copy. Values allocated with `allocate_owned` are only dropped by this allocator.
+    pub fn clone_arena(&self) -> Self {
+        let clone = Self::with_zero_on_drop(self.zero_on_drop);
+        let size = self.allocated.load(Ordering::Acquire);
+        unsafe {
+            ptr::copy_nonoverlapping(
+                self.arena_ptr.load(Ordering::Acquire),
+                clone.arena_ptr.load(Ordering::Acquire),
+                size,
+            )
+        };
+        clone.allocated.store(size, Ordering::SeqCst);
+        clone.high_water.store(size, Ordering::SeqCst);
+        clone
+            .alloc_count
+            .store(self.alloc_count.load(Ordering::Acquire), Ordering::SeqCst);
+        clone.wasted_padding.store(
+            self.wasted_padding.load(Ordering::Acquire),
+            Ordering::SeqCst,
+        );
+        // Handles to the copied values stay valid in the copy
+        clone
+            .generation
+            .store(self.generation.load(Ordering::Acquire), Ordering::SeqCst);
+        clone
+    }
+
+    /// Allocate the given value to the heap using bump allocation.
+    ///
+    /// Known issue: value parameter is first allocated to the stack, which is not optimal.
+    ///
+    /// **Panics**: if there isn't enough space left in the arena
+    #[allow(clippy::mut_from_ref)]
+    pub fn allocate<T>(&self, value: T) -> &mut T {
+        match self.try_allocate(value) {
+            Ok(value) => value,
+            Err(error) => panic!("bump allocation failed: {error}"),
+        }
+    }
+
+    /// Allocate the given value to the heap using bump allocation.
+    ///
+    /// **Returns**: An error if there isn't enough space left in the arena
+    #[allow(clippy::mut_from_ref)]
+    pub fn try_allocate<T>(&self, value: T) -> Result<&mut T, AllocError> {
+        let ptr = self.try_alloc_layout(Layout::new::<T>())? as *mut T;
+        unsafe {
+            ptr::write(ptr, value);
+            Ok(&mut *ptr) // Return value at new address
+        }
+    }
+
+    /// Allocate the given value to the heap using bump allocation, and drop it when the arena is reset.
+    ///
+    /// Owned values are dropped in reverse allocation order by `dealloc_all`, `secure_wipe`, the end of
+    /// the scope they were allocated in (see `BumpScope`) and the allocator drop. This makes the allocator suitable
+    /// for values owning resources, such as `String` or `Vec`, at the cost of recording the value offset
+    /// and drop function in a side vector. Nothing is recorded for types without drop glue.
+    ///
+    /// Values must be `Send`, since the allocator may be reset from another thread.
+    ///
+    /// **Panics**: if there isn't enough space left in the arena
+    #[allow(clippy::mut_from_ref)]
+    pub fn allocate_owned<T: Send>(&self, value: T) -> &mut T {
+        match self.try_allocate_owned(value) {
+            Ok(value) => value,
+            Err(error) => panic!("bump allocation failed: {error}"),
+        }
+    }
+
+    /// Allocate the given value to the heap using bump allocation, and drop it when the arena is reset
+    /// (see `allocate_owned`).
+    ///
+    /// **Returns**: An error if there isn't enough space left in the arena, the value is dropped then
+    #[allow(clippy::mut_from_ref)]
+    pub fn try_allocate_owned<T: Send>(&self, value: T) -> Result<&mut T, AllocError> {
+        let value = self.try_allocate(value)?;
+        if needs_drop::<T>() {
+            let offset = value as *mut T as usize - self.arena_ptr.load(Ordering::Acquire) as usize;
+            self.drops.lock().unwrap().push((offset, drop_value::<T>));
+        }
+        Ok(value)
+    }
+
+    /// Drop the values allocated with `allocate_owned`, in reverse allocation order, until only `count` of them are left.
+    ///
+    /// The lock isn't held while dropping, so that drop implementations may use the allocator.
+    fn drop_owned(&self, count: usize) {
+        let arena_ptr = self.arena_ptr.load(Ordering::Acquire);
+        loop {
+            let mut drops = self.drops.lock().unwrap();
+            if drops.len() <= count {
+                return;
+            }
+            let (offset, drop_fn) = drops.pop().unwrap();
+            drop(drops);
+
+            unsafe { drop_fn(arena_ptr.add(offset)) };
+        }
+    }
+
+    /// Get the count of values allocated with `allocate_owned` and not dropped yet.
+    fn owned_count(&mut self) -> usize {
+        self.drops.get_mut().unwrap().len()
+    }
+
+    /// Allocate space for a value, then build it in place with the given closure.
+    ///
+    /// The value is written straight into the arena, which avoids moving a large value from the stack.
+    /// The returned reference can be coerced to an unsized type, such as `&mut dyn Trait`.
+    /// If the closure panics, the reserved space stays allocated until the arena is reset.
+    ///
+    /// **Panics**: if there isn't enough space left in the arena
+    #[allow(clippy::mut_from_ref)]
+    pub fn allocate_with<T, F: FnOnce() -> T>(&self, f: F) -> &mut T {
+        match self.try_allocate_with(f) {
+            Ok(value) => value,
+            Err(error) => panic!("bump allocation failed: {error}"),
+        }
+    }
+
+    /// Allocate space for a value, then build it in place with the given closure (see `allocate_with`).
+    ///
+    /// **Returns**: An error if there isn't enough space left in the arena, the closure isn't called then
+    #[allow(clippy::mut_from_ref)]
+    pub fn try_allocate_with<T, F: FnOnce() -> T>(&self, f: F) -> Result<&mut T, AllocError> {
+        let ptr = self.try_alloc_layout(Layout::new::<T>())? as *mut T;
+        unsafe {
+            ptr::write(ptr, f());
+            Ok(&mut *ptr)
+        }
+    }
+
+    /// Allocate the given value to the heap using bump allocation, also returning its offset from the arena start.
+    ///
+    /// Unlike pointers, offsets can be serialized and sent across threads. Use `get_at` to get the value back.
+    ///
+    /// **Panics**: if there isn't enough space left in the arena
+    #[allow(clippy::mut_from_ref)]
+    pub fn allocate_indexed<T>(&self, value: T) -> (usize, &mut T) {
+        let value = self.allocate(value);
+        let offset = value as *mut T as usize - self.arena_ptr.load(Ordering::Acquire) as usize;
+        (offset, value)
+    }
+
+    /// Allocate the given value to the heap using bump allocation, returning both a raw pointer to it, to be handed
+    /// to foreign code, and a reference to it.
+    ///
+    /// The reference is derived from the raw pointer, and not the other way around: using the reference doesn't
+    /// invalidate the pointer. Once the value is accessed through the pointer, the reference must not be used anymore.
+    /// The pointer stays valid until the arena is reset: it must not be used after `dealloc_all`,
+    /// `RegionAllocator::reset` or the end of the scope the value was allocated in.
+    ///
+    /// **Panics**: if there isn't enough space left in the arena
+    #[allow(clippy::mut_from_ref)]
+    pub fn allocate_raw<T>(&self, value: T) -> (*mut T, &mut T) {
+        let ptr = match self.try_alloc_layout(Layout::new::<T>()) {
+            Ok(ptr) => ptr as *mut T,
+            Err(error) => panic!("bump allocation failed: {error}"),
+        };
+        unsafe {
+            ptr::write(ptr, value);
+            (ptr, &mut *ptr)
+        }
+    }
+
+    /// Get the value stored at the given offset from the arena start (see `allocate_indexed`).
+    ///
+    /// **Panics**: if the value isn't entirely within the allocated bytes, or if the offset isn't aligned for `T`
+    ///
+    /// # Safety
+    /// A value of type `T` must have been allocated at this offset since the last `dealloc_all` call,
+    /// and it must not be mutably borrowed.
+    pub unsafe fn get_at<T>(&self, offset: usize) -> &T {
+        let allocated = self.allocated.load(Ordering::Acquire);
+        assert!(
+            offset <= allocated && size_of::<T>() <= allocated - offset,
+            "offset is out of the allocated bytes"
+        );
+
+        let ptr = self.arena_ptr.load(Ordering::Acquire).add(offset) as *const T;
+        assert!(ptr.is_aligned(), "offset isn't aligned for the value type");
+        &*ptr
+    }
+
+    /// Allocate the given value to the heap using bump allocation, returning a handle to it instead of a reference.
+    ///
+    /// Unlike references and offsets, handles are checked against `dealloc_all` calls when reading them back with `get`.
+    ///
+    /// **Panics**: if there isn't enough space left in the arena
+    pub fn allocate_handle<T>(&self, value: T) -> BumpHandle<T> {
+        // Read first: a concurrent reset makes the handle stale rather than wrongly current
+        let generation = self.generation.load(Ordering::Acquire);
+        let (offset, _) = self.allocate_indexed(value);
+        BumpHandle {
+            offset,
+            generation,
+            _value: PhantomData,
+        }
+    }
+
+    /// Get the value referenced by the given handle (see `allocate_handle`).
+    ///
+    /// **Panics**: if the allocator was reset with `dealloc_all` since the value was allocated
+    ///
+    /// # Safety
+    /// The handle must have been created by this allocator (or by the allocator this one was cloned from
+    /// with `clone_arena`), and the value must not be mutably borrowed.
+    pub unsafe fn get<T>(&self, handle: BumpHandle<T>) -> &T {
+        assert!(
+            self.is_current(handle),
+            "stale handle: the allocator was reset since the value was allocated"
+        );
+        self.get_at(handle.offset)
+    }
+
+    /// Check if the given handle was created since the last `dealloc_all` call.
+    pub fn is_current<T>(&self, handle: BumpHandle<T>) -> bool {
+        handle.generation == self.generation.load(Ordering::Acquire)
+    }
+
+    /// Get the count of `dealloc_all` calls since creation.
+    pub fn generation(&self) -> usize {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    /// Allocate the given value to the heap using bump allocation, aligned to at least `align` bytes.
+    /// This allows placing values on their own cache line or page.
+    ///
+    /// **Panics**: if `align` isn't a power of two, or if there isn't enough space left in the arena
+    #[allow(clippy::mut_from_ref)]
+    pub fn allocate_aligned<T>(&self, value: T, align: usize) -> &mut T {
+        assert!(align.is_power_of_two(), "alignment must be a power of two");
+        let layout = Layout::from_size_align(size_of::<T>(), align.max(align_of::<T>()))
+            .expect("bump allocation layout overflow");
+        let ptr = unsafe { self.alloc(layout) };
+        if ptr.is_null() {
+            panic!("bump allocation failed");
+        }
+
+        unsafe {
+            ptr::write(ptr as *mut T, value);
+            &mut *(ptr as *mut T)
+        }
+    }
+
+    /// Allocate the given value to the heap using bump allocation, pinned to its arena location.
+    /// This allows building self-referential structures, which rely on a stable address.
+    ///
+    /// Allocations never move: the address stays valid until the arena is reset with `dealloc_all`,
+    /// or the allocator is dropped. The memory is reused afterwards.
+    ///
+    /// **Panics**: if there isn't enough space left in the arena
+    ///
+    /// # Safety
+    /// Pinning promises that the value memory isn't reused before the value is dropped, but the allocator never drops values.
+    /// If `T` relies on its destructor (an intrusive list node unlinking itself for instance), the caller must drop it
+    /// in place before calling `dealloc_all` or dropping the allocator.
+    #[allow(clippy::mut_from_ref)]
+    pub unsafe fn allocate_pinned<T>(&self, value: T) -> Pin<&mut T> {
+        Pin::new_unchecked(self.allocate(value))
+    }
+
+    /// Allocate the given fixed-length array to the heap using bump allocation.
+    ///
+    /// **Panics**: if there isn't enough space left in the arena
+    #[allow(clippy::mut_from_ref)]
+    pub fn allocate_array<T, const L: usize>(&self, values: [T; L]) -> &mut [T; L] {
+        self.allocate(values)
+    }
+
+    /// Allocate a value of type `T` with all its bytes set to 0, without building it on the stack.
+    ///
+    /// The bytes are written to 0 on each call, so the value is zeroed even if the arena memory was used before
+    /// a `dealloc_all(false)` call.
+    ///
+    /// **Panics**: if there isn't enough space left in the arena
+    ///
+    /// # Safety
+    /// All-zero bytes must be a valid value of `T` (integers, floats, arrays of them...), which isn't the case
+    /// for references or `NonZero` integers for instance.
+    #[allow(clippy::mut_from_ref)]
+    pub unsafe fn allocate_zeroed<T>(&self) -> &mut T {
+        let ptr = self.alloc(Layout::new::<T>()) as *mut T;
+        if ptr.is_null() {
+            panic!("bump allocation failed");
+        }
+
+        ptr::write_bytes(ptr, 0, 1);
+        &mut *ptr
+    }
+
+    /// Allocate the default value of `T` to the heap using bump allocation.
+    ///
+    /// **Panics**: if there isn't enough space left in the arena
+    #[allow(clippy::mut_from_ref)]
+    pub fn allocate_default<T: Default>(&self) -> &mut T {
+        self.allocate(T::default())
+    }
+
+    /// Allocate the default value of `T` to the heap using bump allocation.
+    ///
+    /// **Returns**: An error if there isn't enough space left in the arena
+    #[allow(clippy::mut_from_ref)]
+    pub fn try_allocate_default<T: Default>(&self) -> Result<&mut T, AllocError> {
+        self.try_allocate(T::default())
+    }
+
+    /// Copy the given string into the arena.
+    ///
+    /// Empty strings don't consume any arena space.
+    ///
+    /// **Panics**: if there isn't enough space left in the arena
+    pub fn allocate_str(&self, value: &str) -> &str {
+        let bytes = self.allocate_bytes(value.as_bytes());
+        unsafe { str::from_utf8_unchecked(bytes) } // Bytes were copied from a valid str
+    }
+
+    /// Copy the given bytes into the arena.
+    ///
+    /// Empty slices don't consume any arena space.
+    ///
+    /// **Panics**: if there isn't enough space left in the arena
+    #[allow(clippy::mut_from_ref)]
+    pub fn allocate_bytes(&self, bytes: &[u8]) -> &mut [u8] {
+        if bytes.is_empty() {
+            return &mut [];
+        }
+
+        let layout = Layout::for_value(bytes);
+        let ptr = unsafe { self.alloc(layout) };
+        if ptr.is_null() {
+            panic!("bump allocation failed");
+        }
+
+        unsafe {
+            ptr::copy_nonoverlapping(bytes.as_ptr(), ptr, bytes.len());
+            slice::from_raw_parts_mut(ptr, bytes.len())
+        }
+    }
+
+    /// Copy the given slice into the arena.
+    ///
+    /// Empty slices don't consume any arena space.
+    ///
+    /// **Returns**: An error if the slice layout overflows or if there isn't enough space left in the arena
+    #[allow(clippy::mut_from_ref)]
+    pub fn try_allocate_slice<T: Copy>(&self, src: &[T]) -> Result<&mut [T], AllocError> {
+        if src.is_empty() {
+            return Ok(&mut []);
+        }
+
+        let ptr = self.try_alloc_layout(array_layout::<T>(src.len())?)? as *mut T;
+        unsafe {
+            ptr::copy_nonoverlapping(src.as_ptr(), ptr, src.len());
+            Ok(slice::from_raw_parts_mut(ptr, src.len()))
+        }
+    }
+
+    /// Copy the given slice into the arena.
+    ///
+    /// Empty slices don't consume any arena space.
+    ///
+    /// **Panics**: if the slice layout overflows or if there isn't enough space left in the arena
+    #[allow(clippy::mut_from_ref)]
+    pub fn allocate_slice<T: Copy>(&self, src: &[T]) -> &mut [T] {
+        match self.try_allocate_slice(src) {
+            Ok(values) => values,
+            Err(error) => panic!("bump allocation failed: {error}"),
+        }
+    }
+
+    /// Allocate an uninitialized slice of `len` values, to be filled in place (by an I/O call for instance)
+    /// without building the values on the stack first.
+    ///
+    /// Empty slices don't consume any arena space.
+    ///
+    /// **Panics**: if the slice layout overflows or if there isn't enough space left in the arena
+    #[allow(clippy::mut_from_ref)]
+    pub fn allocate_uninit_slice<T>(&self, len: usize) -> &mut [MaybeUninit<T>] {
+        match self.try_allocate_uninit_slice(len) {
+            Ok(values) => values,
+            Err(error) => panic!("bump allocation failed: {error}"),
+        }
+    }
+
+    /// Allocate an uninitialized slice of `len` values (see `allocate_uninit_slice`).
+    ///
+    /// **Returns**: An error if the slice layout overflows or if there isn't enough space left in the arena
+    #[allow(clippy::mut_from_ref)]
+    pub fn try_allocate_uninit_slice<T>(
+        &self,
+        len: usize,
+    ) -> Result<&mut [MaybeUninit<T>], AllocError> {
+        if len == 0 {
+            return Ok(&mut []);
+        }
+
+        let ptr = self.try_alloc_layout(array_layout::<T>(len)?)? as *mut MaybeUninit<T>;
+        // MaybeUninit values don't need to be initialized
+        Ok(unsafe { slice::from_raw_parts_mut(ptr, len) })
+    }
+
+    /// Allocate the given layout, telling why the allocation failed instead of returning null.
+    fn try_alloc_layout(&self, layout: Layout) -> Result<*mut u8, AllocError> {
+        let ptr = unsafe { self.alloc(layout) };
+        if !ptr.is_null() {
+            Ok(ptr)
+        } else if layout.size() > N {
+            Err(AllocError::ArenaTooSmall)
+        } else {
+            Err(AllocError::OutOfMemory)
+        }
+    }
+
+    /// Reset the bump allocator, freeing all its space.
+    /// This is really fast because it just implies setting the allocation cursor to 0,
+    /// after dropping the values allocated with `allocate_owned`, in reverse allocation order.
+    ///
+    /// Taking `&mut self` ensures no allocated reference is still alive: the freed bytes are handed out again
+    /// by the next allocations.
+    ///
+    /// * `wipe_memory`: Set to true to write 0 bytes where memory was allocated (see `secure_wipe`), false to leave the memory intact.
+    pub fn dealloc_all(&mut self, wipe_memory: bool) {
+        // Handles created so far become stale, even if they point to zero-sized values
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        // Zero-sized values may need dropping too
+        self.drop_owned(0);
+
+        let size = self.allocated.load(Ordering::Acquire);
+        let high_water = *self.high_water.get_mut();
+        if size == 0 && (!wipe_memory || high_water == 0) {
+            // Nothing is currently allocated nor left to wipe, can fast return
+            return;
+        }
+
+        if wipe_memory {
+            self.secure_wipe();
+        }
+
+        self.reset_cursor();
+        if wipe_memory {
+            // The whole arena is zeroed again
+            *self.high_water.get_mut() = 0;
+        }
+    }
+
+    /// Set the allocation cursor back to the arena start, along with the allocation tracking.
+    fn reset_cursor(&self) {
+        self.allocated.store(0, Ordering::SeqCst);
+        self.alloc_count.store(0, Ordering::SeqCst);
+        self.wasted_padding.store(0, Ordering::SeqCst);
+    }
+
+    /// Open a temporary allocation scope: the values allocated through it are freed when it is dropped,
+    /// by resetting the allocation cursor to its current position (see `BumpScope`).
+    pub fn scope(&mut self) -> BumpScope<'_, N> {
+        BumpScope::new(self)
+    }
+
+    /// Get the count of bytes skipped to align allocations since creation or the last `dealloc_all` call.
+    ///
+    /// Allocating the most aligned values first reduces this waste.
+    pub fn wasted_padding(&self) -> usize {
+        self.wasted_padding.load(Ordering::Acquire)
+    }
+
+    /// Get the count of successful allocations since creation or the last `dealloc_all` call.
+    pub fn allocation_count(&self) -> usize {
+        self.alloc_count.load(Ordering::Acquire)
+    }
+
+    /// Get the allocated bytes of the arena, from its start up to the allocation cursor.
+    ///
+    /// This is meant for debugging and persisting the arena contents. Taking `&mut self` ensures
+    /// no allocation is running or still borrowed while reading. Padding bytes inside allocated values
+    /// hold unspecified values.
+    pub fn as_bytes(&mut self) -> &[u8] {
+        let size = *self.allocated.get_mut();
+        &self.arena_bytes()[..size]
+    }
+
+    /// Get all the bytes of the arena, including the ones that aren't allocated yet (see `as_bytes`).
+    ///
+    /// Bytes that were never allocated are 0.
+    pub fn arena_bytes(&mut self) -> &[u8; N] {
+        let arena_ptr = *self.arena_ptr.get_mut();
+        assert!(!arena_ptr.is_null(), "bump arena isn't allocated");
+        unsafe { &*(arena_ptr as *const [u8; N]) }
+    }
+
+    /// Check if the given layout can currently be allocated, without allocating it.
+    pub fn can_fit(&self, layout: Layout) -> bool {
+        self.fit(self.allocated.load(Ordering::Acquire), layout)
+            .is_some()
+    }
+
+    /// Check if a value of type `T` can currently be allocated, without allocating it.
+    pub fn can_fit_value<T>(&self) -> bool {
+        self.can_fit(Layout::new::<T>())
+    }
+
+    /// Check if the given allocation was served by this allocator: it lies inside the arena, or right at its end
+    /// for a zero-sized value allocated once the arena was full.
+    pub fn owns(&self, ptr: *const u8, layout: Layout) -> bool {
+        let arena_ptr = self.arena_ptr.load(Ordering::Acquire);
+        let offset = (ptr as usize).wrapping_sub(arena_ptr as usize);
+        !arena_ptr.is_null() && (offset < N || (offset == N && layout.size() == 0))
+    }
+
+    /// Compute the placement of the given layout after `allocated` bytes.
+    ///
+    /// **Returns**: the allocation offset and end, or None if there isn't enough space left in the arena
+    fn fit(&self, allocated: usize, layout: Layout) -> Option<(usize, usize)> {
+        let size = layout.size();
+        let align = layout.align();
+        if size > N - allocated {
+            // Not enough bytes available
+            return None;
+        }
+
+        // Align the address rather than the offset, since the arena itself may be less aligned than the layout
+        let cursor = self.arena_ptr.load(Ordering::Acquire) as usize + allocated;
+        let alloc_padding = (align - (cursor % align)) % align;
+        let alloc_offset = allocated + alloc_padding;
+
+        let alloc_end = alloc_offset + size;
+        if alloc_end <= N {
+            Some((alloc_offset, alloc_end))
+        } else {
+            // Padding causes the allocation to fail: not enough bytes available
+            None
+        }
+    }
+
+    /// Touch every page of the arena, so that the system commits them up front instead of on first use.
+    /// This removes page fault latency spikes from later allocations, typically at startup.
+    ///
+    /// Each page is read and written back with the same value: the arena contents are unchanged.
+    /// Taking `&mut self` ensures no allocated value is being written meanwhile.
+    pub fn prefault(&mut self) {
+        let arena_ptr = *self.arena_ptr.get_mut();
+        unsafe { touch_pages(arena_ptr, N) };
+    }
+
+    /// Write 0 in all allocated bytes, without resetting the allocation cursor.
+    ///
+    /// Bytes which were allocated since the last wipe but were freed since, by the end of a `BumpScope` for instance,
+    /// are wiped as well. The wipe is guaranteed not to be optimized out, even if the memory isn't read afterwards.
+    /// Allocated values are still reachable after this call, but hold zeroed bytes. Values allocated with
+    /// `allocate_owned` are dropped first, since zeroed bytes may not be valid values to drop.
+    /// Taking `&mut self` ensures no allocated reference is alive while its bytes are overwritten.
+    pub fn secure_wipe(&mut self) {
+        self.drop_owned(0);
+        let size = *self.high_water.get_mut();
+        let ptr = self.arena_ptr.load(Ordering::Acquire);
+        let len_bytes = size * size_of::<u8>();
+        unsafe { secure_zero(ptr, len_bytes) };
+        *self.high_water.get_mut() = *self.allocated.get_mut();
+    }
+}
+
+unsafe impl<const N: usize> GlobalAlloc for BumpAllocator<N> {
+    /// Allocate memory for a layout.
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let mut alloc_offset = 0;
+
+        // Try to update allocated cursor
+        let allocated =
+            match self
+                .allocated
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |allocated| {
+                    let (offset, alloc_end) = self.fit(allocated, layout)?;
+                    alloc_offset = offset;
+                    Some(alloc_end)
+                }) {
+                Ok(allocated) => allocated,
+                Err(_) => return null_mut(),
+            };
+        self.alloc_count.fetch_add(1, Ordering::SeqCst);
+        self.wasted_padding
+            .fetch_add(alloc_offset - allocated, Ordering::SeqCst);
+        self.high_water
+            .fetch_max(alloc_offset + layout.size(), Ordering::SeqCst);
+
+        // Point to the start of the free bytes
+        self.arena_ptr.load(Ordering::Acquire).add(alloc_offset)
+    }
+
+    /// Deallocation of a single element.
+    ///
+    /// No per-value deallocation, only full deallocation is available.
+    unsafe fn dealloc(&self, _ptr: *mut u8, _layout: Layout) {}
+
+    /// Resize an allocation.
+    ///
+    /// If it is the most recent allocation, it is resized in place by moving the cursor: shrinking it makes
+    /// the freed bytes immediately reusable. Other allocations are shrunk in place without freeing anything,
+    /// or grown by allocating a new block and copying the value, the old block stays used until `dealloc_all`.
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let offset = (ptr as usize).wrapping_sub(self.arena_ptr.load(Ordering::Acquire) as usize);
+        if offset <= N && new_size <= N - offset {
+            let old_end = offset + layout.size();
+            let new_end = offset + new_size;
+            if self
+                .allocated
+                .compare_exchange(old_end, new_end, Ordering::SeqCst, Ordering::SeqCst)
+                .is_ok()
+            {
+                // Tail allocation: resized in place, shrinking gives the freed bytes back to the arena.
+                // They still have to be wiped (see `secure_wipe`)
+                self.high_water.fetch_max(new_end, Ordering::SeqCst);
+                return ptr;
+            }
+        }
+        if new_size <= layout.size() {
+            // Not the tail: the value already fits, the freed bytes stay used until `dealloc_all`
+            return ptr;
+        }
+
+        let new_layout = Layout::from_size_align_unchecked(new_size, layout.align());
+        let new_ptr = self.alloc(new_layout);
+        if !new_ptr.is_null() {
+            ptr::copy_nonoverlapping(ptr, new_ptr, layout.size().min(new_size));
+        }
+        new_ptr
+    }
+}
+
+impl<const N: usize> RegionAllocator for BumpAllocator<N> {
+    /// Reset the allocation cursor, like `dealloc_all(false)`.
+    unsafe fn reset(&self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        self.drop_owned(0);
+        self.reset_cursor();
+    }
+}
+
+impl<const N: usize> Drop for BumpAllocator<N> {
+    fn drop(&mut self) {
+        let arena_ptr = self.arena_ptr.load(Ordering::Acquire);
+        if arena_ptr.is_null() {
+            return;
+        }
+
+        self.drop_owned(0);
+
+        unsafe {
+            if self.zero_on_drop {
+                secure_zero(arena_ptr, N);
+            }
+            if self.owns_arena {
+                GlobalAlloc::dealloc(&System, arena_ptr, arena_layout(N));
+            }
+        }
+    }
+}
+
+/// Type-erased drop function of an owned value, called with the value location.
+type DropFn = unsafe fn(*mut u8);
+
+/// Drop the value of type `T` at the given location, type-erased to be recorded by `allocate_owned`.
+unsafe fn drop_value<T>(ptr: *mut u8) {
+    ptr::drop_in_place(ptr as *mut T);
+}
+
+// The allocator is meant to be shared across threads, this must not regress
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<BumpAllocator<1>>();
+};
+
+/// Alignment of the arenas, so that common allocations don't need padding at the arena start.
+const ARENA_ALIGN: usize = 16;
+
+/// Layout of an arena of `size` bytes. The same layout must be used to allocate and free it.
+fn arena_layout(size: usize) -> Layout {
+    Layout::from_size_align(size, ARENA_ALIGN).expect("bump arena size overflow")
+}
+
+/// Stride used to touch memory pages. Touching more often than the actual page size is harmless.
+const PAGE_SIZE: usize = 4096;
+
+/// Read then write back one byte per page in the `len` bytes starting at `ptr`, forcing the pages to be committed.
+///
+/// Volatile accesses ensure the touches aren't optimized out, as they don't change the memory contents.
+unsafe fn touch_pages(ptr: *mut u8, len: usize) {
+    for offset in (0..len).step_by(PAGE_SIZE) {
+        let byte = ptr.add(offset);
+        ptr::write_volatile(byte, ptr::read_volatile(byte));
+    }
+}
+
+/// Write 0 in `len` bytes starting at `ptr`.
+///
+/// Volatile writes followed by a compiler fence ensure the wipe isn't optimized out,
+/// even though the memory isn't read afterwards.
+unsafe fn secure_zero(ptr: *mut u8, len: usize) {
+    for i in 0..len {
+        ptr::write_volatile(ptr.add(i), 0);
+    }
+    compiler_fence(Ordering::SeqCst);
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bumper::*;
+
+    #[test]
+    #[should_panic]
+    fn allocate_not_enough_space_panic() {
+        let bumper = BumpAllocator::<2>::new();
+        bumper.allocate(123); // i32 has layout size of 4 bytes, which is more than the available space (2 bytes)
+    }
+
+    #[test]
+    fn allocate_enough_space() {
+        let bumper = BumpAllocator::<8>::new();
+
+        let i32_var = bumper.allocate(123);
+        assert_eq!(*i32_var, 123);
+        let mut allocated = bumper.allocated.load(Ordering::Acquire);
+        assert_eq!(Layout::new::<i32>().size(), allocated);
+
+        let bool_var = bumper.allocate(true);
+        assert!(*bool_var);
+        allocated = bumper.allocated.load(Ordering::Acquire);
+        assert_eq!(
+            Layout::new::<i32>().size() + Layout::new::<bool>().size(),
+            allocated
+        );
+    }
+
+    #[test]
+    fn allocate_with_builds_in_place() {
+        let bumper = BumpAllocator::<64>::new();
+        let values: &mut [u64; 4] = bumper.allocate_with(|| [7; 4]);
+        assert_eq!([7; 4], *values);
+        assert_eq!(1, bumper.allocation_count());
+    }
+
+    #[test]
+    fn allocate_with_coerces_to_trait_object() {
+        let bumper = BumpAllocator::<64>::new();
+        let values: [&mut dyn std::fmt::Display; 2] = [
+            bumper.allocate_with(|| 12u8),
+            bumper.allocate_with(|| String::from("ab")),
+        ];
+        let formatted: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+        assert_eq!(vec!["12", "ab"], formatted);
+    }
+
+    #[test]
+    fn try_allocate_with_not_enough_space_skips_closure() {
+        let bumper = BumpAllocator::<4>::new();
+        let mut called = false;
+        assert!(bumper
+            .try_allocate_with(|| {
+                called = true;
+                0u64
+            })
+            .is_err());
+        assert!(!called);
+    }
+
+    #[test]
+    fn prefault_keeps_arena_contents() {
+        let mut bumper = BumpAllocator::<10000>::new();
+        bumper.allocate(0xABu8);
+        bumper.prefault();
+
+        assert_eq!(&[0xAB], bumper.as_bytes());
+        assert!(bumper.arena_bytes()[1..].iter().all(|byte| *byte == 0));
+    }
+
+    #[test]
+    fn from_slice_allocates_in_buffer() {
+        let buf = Box::leak(vec![0xFFu8; 32].into_boxed_slice());
+        let range = buf.as_ptr_range();
+        let mut bumper = BumpAllocator::<24>::from_slice(buf);
+
+        let value = bumper.allocate(7u64) as *mut u64 as *const u8;
+        assert!(range.contains(&value));
+        assert!(bumper.try_allocate([0u8; 24]).is_err());
+        // The buffer is zeroed on creation
+        assert!(bumper.arena_bytes()[8..].iter().all(|byte| *byte == 0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn from_slice_too_small_panic() {
+        let buf = Box::leak(vec![0u8; 8].into_boxed_slice());
+        BumpAllocator::<16>::from_slice(buf);
+    }
+
+    #[test]
+    fn region_reset_frees_arena() {
+        let bumper = BumpAllocator::<8>::new();
+        let handle = bumper.allocate_handle(1u64);
+        unsafe { RegionAllocator::reset(&bumper) };
+
+        assert!(!bumper.is_current(handle));
+        assert!(bumper.try_allocate(2u64).is_ok());
+    }
+
+    #[test]
+    fn dealloc_all_empty_no_panic() {
+        let mut bumper = BumpAllocator::<8>::new();
+        bumper.dealloc_all(false);
+    }
+
+    #[test]
+    fn dealloc_all_wipe_memory_empty_no_panic() {
+        let mut bumper = BumpAllocator::<8>::new();
+        bumper.dealloc_all(true);
+    }
+
+    #[test]
+    fn dealloc_all() {
+        let mut bumper = BumpAllocator::<8>::new();
+        bumper.allocate(123);
+
+        bumper.dealloc_all(false);
+        let allocated = bumper.allocated.load(Ordering::Acquire);
+        assert_eq!(0, allocated); // Reset
+
+        // Bytes should still be set to the correct value, no wipe
+        let start_ptr = bumper.arena_ptr.load(Ordering::Acquire);
+        let stored_i32 = unsafe { ptr::read(start_ptr as *const i32) };
+        assert_eq!(123, stored_i32);
+    }
+
+    #[test]
+    fn dealloc_all_wipe_memory() {
+        let mut bumper = BumpAllocator::<8>::new();
+        bumper.allocate(123);
+
+        bumper.dealloc_all(true);
+        let allocated = bumper.allocated.load(Ordering::Acquire);
+        assert_eq!(0, allocated); // Reset
+
+        // Bytes should be set to 0
+        let start_ptr = bumper.arena_ptr.load(Ordering::Acquire);
+        let stored_i32 = unsafe { ptr::read(start_ptr as *const i32) };
+        assert_eq!(0, stored_i32);
+    }
+
+    /// Value recording its id in a shared log when dropped
+    struct DropLog<'a>(u32, &'a Mutex<Vec<u32>>);
+
+    impl Drop for DropLog<'_> {
+        fn drop(&mut self) {
+            self.1.lock().unwrap().push(self.0);
+        }
+    }
+
+    #[test]
+    fn dealloc_all_drops_owned_values_in_reverse_order() {
+        let log = Mutex::new(Vec::new());
+        let mut bumper = BumpAllocator::<64>::new();
+        bumper.allocate_owned(DropLog(1, &log));
+        bumper.allocate(DropLog(2, &log)); // Not owned: never dropped
+        bumper.allocate_owned(DropLog(3, &log));
+
+        bumper.dealloc_all(false);
+        assert_eq!(vec![3, 1], *log.lock().unwrap());
+
+        // Already dropped values aren't dropped again
+        bumper.dealloc_all(false);
+        assert_eq!(2, log.lock().unwrap().len());
+    }
+
+    #[test]
+    fn drop_drops_owned_values() {
+        let value = std::sync::Arc::new(());
+        let bumper = BumpAllocator::<64>::new();
+        bumper.allocate_owned(vec![std::sync::Arc::clone(&value)]);
+        bumper.allocate_owned(String::from("owned"));
+
+        drop(bumper);
+        assert_eq!(1, std::sync::Arc::strong_count(&value));
+    }
+
+    #[test]
+    fn secure_wipe_drops_owned_values() {
+        let log = Mutex::new(Vec::new());
+        let mut bumper = BumpAllocator::<64>::new();
+        bumper.allocate_owned(DropLog(1, &log));
+
+        bumper.secure_wipe();
+        assert_eq!(vec![1], *log.lock().unwrap());
+    }
+
+    #[test]
+    fn allocate_owned_without_drop_glue_not_recorded() {
+        let mut bumper = BumpAllocator::<64>::new();
+        assert_eq!(7, *bumper.allocate_owned(7u64));
+        assert_eq!(0, bumper.owned_count());
+    }
+
+    #[test]
+    fn drop_zeroing_no_panic() {
+        let bumper = BumpAllocator::<8>::new_zeroing();
+        bumper.allocate(123);
+        drop(bumper);
+    }
+
+    #[test]
+    fn secure_zero_wipes_bytes() {
+        let mut buffer = [0xFFu8; 16];
+        unsafe { secure_zero(buffer.as_mut_ptr(), 8) };
+
+        assert_eq!([0; 8], buffer[..8]);
+        assert_eq!([0xFF; 8], buffer[8..]);
+    }
+
+    #[test]
+    fn secure_wipe_keeps_cursor() {
+        let mut bumper = BumpAllocator::<8>::new();
+        let value = bumper.allocate(123) as *mut i32;
+
+        bumper.secure_wipe();
+        assert_eq!(
+            Layout::new::<i32>().size(),
+            bumper.allocated.load(Ordering::Acquire)
+        );
+        assert_eq!(0, unsafe { ptr::read(value) });
+    }
+
+    #[test]
+    fn try_allocate_larger_than_arena_err() {
+        let bumper = BumpAllocator::<2>::new();
+        assert_eq!(
+            Err(AllocError::ArenaTooSmall),
+            bumper.try_allocate(123).map(|_| ())
+        );
+        assert_eq!(0, bumper.allocated.load(Ordering::Acquire));
+    }
+
+    #[test]
+    fn try_allocate_not_enough_space_left_err() {
+        let bumper = BumpAllocator::<8>::new();
+        bumper.allocate(1u32);
+        assert_eq!(
+            Err(AllocError::OutOfMemory),
+            bumper.try_allocate(2u64).map(|_| ())
+        );
+    }
+
+    #[test]
+    fn allocate_zeroed_after_reset() {
+        let mut bumper = BumpAllocator::<32>::new();
+        bumper.allocate([0xFFu8; 32]);
+        bumper.dealloc_all(false);
+
+        let value = unsafe { bumper.allocate_zeroed::<[u64; 4]>() };
+        assert_eq!([0; 4], *value);
+    }
+
+    #[test]
+    #[should_panic(expected = "bump allocation failed")]
+    fn allocate_zeroed_not_enough_space_panic() {
+        let bumper = BumpAllocator::<4>::new();
+        unsafe { bumper.allocate_zeroed::<u64>() };
+    }
+
+    #[test]
+    fn allocate_default() {
+        let bumper = BumpAllocator::<32>::new();
+
+        let value = bumper.allocate_default::<(u64, bool)>();
+        assert_eq!((0, false), *value);
+    }
+
+    #[test]
+    fn try_allocate_default_not_enough_space_none() {
+        let bumper = BumpAllocator::<4>::new();
+        assert!(bumper.try_allocate_default::<u64>().is_err());
+    }
+
+    #[test]
+    fn allocate_str() {
+        let bumper = BumpAllocator::<16>::new();
+
+        let value = bumper.allocate_str("arena");
+        assert_eq!("arena", value);
+        assert_eq!(5, bumper.allocated.load(Ordering::Acquire));
+    }
+
+    #[test]
+    fn allocate_str_empty_no_space_consumed() {
+        let bumper = BumpAllocator::<16>::new();
+
+        let value = bumper.allocate_str("");
+        assert_eq!("", value);
+        assert_eq!(0, bumper.allocated.load(Ordering::Acquire));
+    }
+
+    #[test]
+    #[should_panic]
+    fn allocate_str_not_enough_space_panic() {
+        let bumper = BumpAllocator::<4>::new();
+        bumper.allocate_str("arena");
+    }
+
+    #[test]
+    fn allocate_concurrently_disjoint() {
+        let bumper = BumpAllocator::<1024>::new();
+
+        let mut addresses: Vec<usize> = std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..4)
+                .map(|_| {
+                    scope.spawn(|| {
+                        (0..16u64)
+                            .map(|i| bumper.allocate(i) as *mut u64 as usize)
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().unwrap())
+                .collect()
+        });
+
+        // Every value got its own slot
+        addresses.sort_unstable();
+        assert!(addresses
+            .windows(2)
+            .all(|pair| pair[1] - pair[0] >= size_of::<u64>()));
+        assert_eq!(64, bumper.allocation_count());
+    }
+
+    #[test]
+    fn alloc_concurrently_mixed_layouts_stress() {
+        const THREADS: usize = 8;
+        const ALLOCATIONS: usize = 256;
+        let bumper = BumpAllocator::<{ THREADS * ALLOCATIONS * 16 }>::new();
+        let layouts = [
+            Layout::new::<u8>(),
+            Layout::new::<u64>(),
+            Layout::new::<[u8; 3]>(),
+            Layout::from_size_align(4, 16).unwrap(),
+        ];
+
+        let mut ranges: Vec<(usize, usize, usize)> = std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..THREADS)
+                .map(|thread| {
+                    let bumper = &bumper;
+                    scope.spawn(move || {
+                        (0..ALLOCATIONS)
+                            .map(|i| {
+                                let layout = layouts[(thread + i) % layouts.len()];
+                                let ptr = unsafe { bumper.alloc(layout) };
+                                assert!(!ptr.is_null());
+                                (ptr as usize, layout.size(), layout.align())
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().unwrap())
+                .collect()
+        });
+
+        let arena_start = bumper.arena_ptr.load(Ordering::Acquire) as usize;
+        let arena_end = arena_start + bumper.allocated.load(Ordering::Acquire);
+        ranges.sort_unstable();
+        for (start, size, align) in &ranges {
+            assert_eq!(0, start % align);
+            assert!(*start >= arena_start && start + size <= arena_end);
+        }
+        // No two allocations overlap
+        assert!(ranges
+            .windows(2)
+            .all(|pair| pair[0].0 + pair[0].1 <= pair[1].0));
+        assert_eq!(THREADS * ALLOCATIONS, bumper.allocation_count());
+    }
+
+    #[test]
+    fn wasted_padding() {
+        let mut bumper = BumpAllocator::<16>::new();
+        bumper.allocate(1u8);
+        bumper.allocate(2u32);
+        assert_eq!(3, bumper.wasted_padding());
+        bumper.allocate(3u8);
+        assert_eq!(3, bumper.wasted_padding());
+
+        bumper.dealloc_all(false);
+        assert_eq!(0, bumper.wasted_padding());
+    }
+
+    #[test]
+    fn allocation_count() {
+        let mut bumper = BumpAllocator::<8>::new();
+        bumper.allocate(1u8);
+        bumper.allocate(2u32);
+        assert!(bumper.try_allocate(3u64).is_err());
+        assert_eq!(2, bumper.allocation_count());
+
+        bumper.dealloc_all(false);
+        assert_eq!(0, bumper.allocation_count());
+    }
+
+    #[test]
+    fn can_fit() {
+        let bumper = BumpAllocator::<8>::new();
+        bumper.allocate(1u8);
+
+        assert!(bumper.can_fit_value::<u32>());
+        assert!(!bumper.can_fit_value::<u64>()); // Padding leaves no room
+        assert!(bumper.can_fit(Layout::new::<[u8; 7]>()));
+        assert!(!bumper.can_fit(Layout::new::<[u8; 8]>()));
+        assert_eq!(1, bumper.allocated.load(Ordering::Acquire));
+    }
+
+    #[test]
+    fn realloc_tail_in_place() {
+        let bumper = BumpAllocator::<16>::new();
+        let layout = Layout::new::<[u8; 4]>();
+        let ptr = unsafe { bumper.alloc(layout) };
+
+        let grown = unsafe { bumper.realloc(ptr, layout, 12) };
+        assert_eq!(ptr, grown);
+        assert_eq!(12, bumper.allocated.load(Ordering::Acquire));
+
+        let shrunk = unsafe { bumper.realloc(grown, Layout::new::<[u8; 12]>(), 2) };
+        assert_eq!(ptr, shrunk);
+        assert_eq!(2, bumper.allocated.load(Ordering::Acquire));
+    }
+
+    #[test]
+    fn realloc_not_tail_copies() {
+        let bumper = BumpAllocator::<16>::new();
+        let layout = Layout::new::<[u8; 4]>();
+        let a = unsafe { bumper.alloc(layout) };
+        unsafe { ptr::write(a as *mut [u8; 4], [1, 2, 3, 4]) };
+        unsafe { bumper.alloc(layout) };
+
+        let grown = unsafe { bumper.realloc(a, layout, 6) };
+        assert_ne!(a, grown);
+        assert_eq!([1, 2, 3, 4], unsafe { ptr::read(grown as *const [u8; 4]) });
+        assert_eq!(14, bumper.allocated.load(Ordering::Acquire));
+    }
+
+    #[test]
+    fn realloc_tail_shrink_space_is_reused() {
+        let bumper = BumpAllocator::<16>::new();
+        let layout = Layout::new::<[u8; 12]>();
+        let ptr = unsafe { bumper.alloc(layout) };
+
+        unsafe { bumper.realloc(ptr, layout, 4) };
+        let next = unsafe { bumper.alloc(Layout::new::<[u8; 12]>()) };
+        assert_eq!(unsafe { ptr.add(4) }, next);
+    }
+
+    #[test]
+    fn realloc_not_tail_shrink_in_place() {
+        let bumper = BumpAllocator::<16>::new();
+        let layout = Layout::new::<[u8; 8]>();
+        let a = unsafe { bumper.alloc(layout) };
+        unsafe { bumper.alloc(Layout::new::<[u8; 4]>()) };
+
+        let shrunk = unsafe { bumper.realloc(a, layout, 2) };
+        assert_eq!(a, shrunk);
+        assert_eq!(12, bumper.allocated.load(Ordering::Acquire));
+    }
+
+    #[test]
+    fn realloc_tail_not_enough_space_null() {
+        let bumper = BumpAllocator::<8>::new();
+        let layout = Layout::new::<[u8; 4]>();
+        let ptr = unsafe { bumper.alloc(layout) };
+
+        assert!(unsafe { bumper.realloc(ptr, layout, 9) }.is_null());
+        assert_eq!(4, bumper.allocated.load(Ordering::Acquire));
+    }
+
+    #[test]
+    fn realloc_tail_shrink_released_bytes_are_wiped() {
+        let mut bumper = BumpAllocator::<64>::new();
+        let layout = Layout::new::<[u8; 32]>();
+        let ptr = unsafe { bumper.alloc(layout) };
+        unsafe { ptr.write_bytes(0xBB, layout.size()) };
+        assert_eq!(ptr, unsafe { bumper.realloc(ptr, layout, 4) });
+        assert_eq!(4, bumper.allocated.load(Ordering::Acquire));
+
+        bumper.dealloc_all(true);
+        assert!(bumper.arena_bytes().iter().all(|byte| *byte == 0));
+    }
+
+    #[test]
+    fn realloc_tail_grow_bytes_are_wiped() {
+        let mut bumper = BumpAllocator::<64>::new();
+        let layout = Layout::new::<[u8; 4]>();
+        let ptr = unsafe { bumper.alloc(layout) };
+        let ptr = unsafe { bumper.realloc(ptr, layout, 32) };
+        unsafe { ptr.write_bytes(0xBB, 32) };
+
+        bumper.secure_wipe();
+        assert!(bumper.arena_bytes().iter().all(|byte| *byte == 0));
+    }
+
+    #[test]
+    fn try_allocate_slice() {
+        let bumper = BumpAllocator::<16>::new();
+        bumper.allocate(1u8);
+
+        let value = bumper.try_allocate_slice(&[1u32, 2, 3]).unwrap();
+        assert_eq!(&[1, 2, 3], value);
+        assert_eq!(0, value.as_ptr() as usize % align_of::<u32>());
+        assert_eq!(16, bumper.allocated.load(Ordering::Acquire));
+    }
+
+    #[test]
+    fn try_allocate_slice_not_enough_space_none() {
+        let bumper = BumpAllocator::<8>::new();
+        assert_eq!(
+            Err(AllocError::ArenaTooSmall),
+            bumper.try_allocate_slice(&[1u32, 2, 3])
+        );
+        assert_eq!(0, bumper.allocated.load(Ordering::Acquire));
+    }
+
+    #[test]
+    fn try_allocate_slice_empty() {
+        let bumper = BumpAllocator::<8>::new();
+        assert!(bumper.try_allocate_slice::<u32>(&[]).unwrap().is_empty());
+        assert_eq!(0, bumper.allocation_count());
+    }
+
+    #[test]
+    fn allocate_slice() {
+        let bumper = BumpAllocator::<16>::new();
+
+        assert_eq!(&[1u16, 2], bumper.allocate_slice(&[1u16, 2]));
+        assert!(bumper.allocate_slice::<u16>(&[]).is_empty());
+        assert_eq!(4, bumper.allocated.load(Ordering::Acquire));
+    }
+
+    #[test]
+    #[should_panic(expected = "bump allocation failed")]
+    fn allocate_slice_not_enough_space_panic() {
+        let bumper = BumpAllocator::<8>::new();
+        bumper.allocate_slice(&[1u32, 2, 3]);
+    }
+
+    #[test]
+    fn allocate_uninit_slice() {
+        let bumper = BumpAllocator::<16>::new();
+        bumper.allocate(1u8);
+
+        let values = bumper.allocate_uninit_slice::<u32>(3);
+        assert_eq!(3, values.len());
+        assert_eq!(0, values.as_ptr() as usize % align_of::<u32>());
+        for (value, i) in values.iter_mut().zip(1..) {
+            value.write(i);
+        }
+        let values = unsafe { &*(values as *const [MaybeUninit<u32>] as *const [u32]) };
+        assert_eq!(&[1, 2, 3], values);
+        assert_eq!(16, bumper.allocated.load(Ordering::Acquire));
+    }
+
+    #[test]
+    fn try_allocate_uninit_slice_empty() {
+        let bumper = BumpAllocator::<8>::new();
+        assert!(bumper
+            .try_allocate_uninit_slice::<u32>(0)
+            .unwrap()
+            .is_empty());
+        assert_eq!(0, bumper.allocation_count());
+    }
+
+    #[test]
+    fn try_allocate_uninit_slice_failures() {
+        let bumper = BumpAllocator::<8>::new();
+        assert_eq!(
+            Err(AllocError::ArenaTooSmall),
+            bumper.try_allocate_uninit_slice::<u32>(3).map(|_| ())
+        );
+        assert_eq!(
+            Err(AllocError::LayoutOverflow),
+            bumper
+                .try_allocate_uninit_slice::<u32>(usize::MAX)
+                .map(|_| ())
+        );
+        assert_eq!(0, bumper.allocated.load(Ordering::Acquire));
+    }
+
+    #[test]
+    #[should_panic(expected = "bump allocation failed: requested size overflows")]
+    fn allocate_uninit_slice_overflow_panics() {
+        let bumper = BumpAllocator::<8>::new();
+        bumper.allocate_uninit_slice::<u64>(usize::MAX);
+    }
+
+    #[test]
+    fn as_bytes() {
+        let mut bumper = BumpAllocator::<8>::new();
+        bumper.allocate(0x0102u16.to_be());
+        bumper.allocate(3u8);
+
+        assert_eq!(&[1, 2, 3], bumper.as_bytes());
+        assert_eq!(&[1, 2, 3, 0, 0, 0, 0, 0], bumper.arena_bytes());
+    }
+
+    #[test]
+    fn allocate_aligned() {
+        let bumper = BumpAllocator::<256>::new();
+        bumper.allocate(1u8);
+
+        let value = bumper.allocate_aligned(2u32, 64);
+        assert_eq!(2, *value);
+        assert_eq!(0, value as *mut u32 as usize % 64);
+    }
+
+    #[test]
+    #[should_panic(expected = "power of two")]
+    fn allocate_aligned_invalid_align_panic() {
+        let bumper = BumpAllocator::<256>::new();
+        bumper.allocate_aligned(2u32, 48);
+    }
+
+    #[test]
+    fn allocate_handle() {
+        let bumper = BumpAllocator::<16>::new();
+        bumper.allocate(1u8);
+
+        let handle = bumper.allocate_handle(2u32);
+        assert_eq!(4, handle.offset());
+        assert!(bumper.is_current(handle));
+        assert_eq!(2, unsafe { *bumper.get(handle) });
+    }
+
+    #[test]
+    #[should_panic(expected = "stale handle")]
+    fn get_stale_handle_panic() {
+        let mut bumper = BumpAllocator::<16>::new();
+        let handle = bumper.allocate_handle(1u32);
+
+        bumper.dealloc_all(false);
+        assert_eq!(1, bumper.generation());
+        assert!(!bumper.is_current(handle));
+
+        // The value was replaced after the reset
+        bumper.allocate(2u32);
+        unsafe { bumper.get(handle) };
+    }
+
+    #[test]
+    fn clone_arena_keeps_handles() {
+        let mut bumper = BumpAllocator::<16>::new();
+        bumper.dealloc_all(false);
+        let handle = bumper.allocate_handle(7u64);
+
+        let clone = bumper.clone_arena();
+        assert_eq!(7, unsafe { *clone.get(handle) });
+    }
+
+    #[test]
+    fn allocate_pinned_self_referential() {
+        struct SelfRef {
+            value: u32,
+            value_ptr: *const u32,
+            _pin: std::marker::PhantomPinned,
+        }
+
+        let bumper = BumpAllocator::<64>::new();
+        let mut pinned = unsafe {
+            bumper.allocate_pinned(SelfRef {
+                value: 7,
+                value_ptr: ptr::null(),
+                _pin: std::marker::PhantomPinned,
+            })
+        };
+        let self_ref = unsafe { pinned.as_mut().get_unchecked_mut() };
+        self_ref.value_ptr = &self_ref.value;
+
+        // Later allocations don't move the value
+        bumper.allocate([0u8; 32]);
+        assert_eq!(7, unsafe { *pinned.value_ptr });
+    }
+
+    #[test]
+    fn allocate_indexed() {
+        let bumper = BumpAllocator::<16>::new();
+        let (first, _) = bumper.allocate_indexed(1u8);
+        let (second, value) = bumper.allocate_indexed(2u32);
+        *value += 1;
+
+        assert_eq!(0, first);
+        assert_eq!(1, unsafe { *bumper.get_at::<u8>(first) });
+        assert_eq!(3, unsafe { *bumper.get_at::<u32>(second) });
+    }
+
+    #[test]
+    fn allocate_raw() {
+        let bumper = BumpAllocator::<16>::new();
+        let (ptr, value) = bumper.allocate_raw(2u32);
+        *value += 1;
+
+        assert_eq!(bumper.arena_ptr.load(Ordering::Acquire) as *mut u32, ptr);
+        assert_eq!(3, unsafe { ptr.read() });
+        unsafe { ptr.write(4) };
+        assert_eq!(4, unsafe { *bumper.get_at::<u32>(0) });
+    }
+
+    #[test]
+    #[should_panic(expected = "out of the allocated bytes")]
+    fn get_at_out_of_bounds_panic() {
+        let bumper = BumpAllocator::<16>::new();
+        let (offset, _) = bumper.allocate_indexed(1u32);
+        unsafe { bumper.get_at::<u64>(offset) };
+    }
+
+    #[test]
+    fn allocate_array() {
+        let bumper = BumpAllocator::<16>::new();
+
+        let values = bumper.allocate_array([1u16, 2, 3]);
+        values[2] = 4;
+        assert_eq!(&[1, 2, 4], values);
+        assert_eq!(6, bumper.allocated.load(Ordering::Acquire));
+    }
+
+    #[test]
+    #[should_panic]
+    fn allocate_array_not_enough_space_panic() {
+        let bumper = BumpAllocator::<4>::new();
+        bumper.allocate_array([1u16, 2, 3]);
+    }
+
+    #[test]
+    fn clone_arena() {
+        let bumper = BumpAllocator::<16>::new();
+        let (offset, _) = bumper.allocate_indexed(123u32);
+
+        let clone = bumper.clone_arena();
+        assert_eq!(4, clone.allocated.load(Ordering::Acquire));
+        assert_eq!(1, clone.allocation_count());
+        assert_eq!(123, unsafe { *clone.get_at::<u32>(offset) });
+        assert_ne!(
+            bumper.arena_ptr.load(Ordering::Acquire),
+            clone.arena_ptr.load(Ordering::Acquire)
+        );
+    }
+
+    #[test]
+    fn arena_aligned() {
+        let bumper = BumpAllocator::<16>::new();
+        assert_eq!(
+            0,
+            bumper.arena_ptr.load(Ordering::Acquire) as usize % ARENA_ALIGN
+        );
+    }
+
+    #[test]
+    fn allocate_bytes() {
+        let bumper = BumpAllocator::<16>::new();
+
+        let value = bumper.allocate_bytes(&[1, 2, 3]);
+        value[0] = 4;
+        assert_eq!(&[4, 2, 3], value);
+    }
+}
@@ -0,0 +1,187 @@
+use super::BumpAllocator;
+use std::{ops::Deref, sync::atomic::Ordering};
+
+/// Temporary allocation scope of a bump allocator, created by `BumpAllocator::scope`.
+///
+/// The scope records the allocation cursor on creation and resets the allocator to it when dropped:
+/// the values allocated through the scope are freed on scope exit, even when unwinding, while the values
+/// allocated before it are kept. Values aren't dropped, as with any bump allocation, except the ones allocated
+/// with `allocate_owned` in the scope, which are dropped in reverse allocation order.
+///
+/// The scope exclusively borrows the allocator, and allocated references borrow the scope,
+/// so none of them can outlive the reset. Scopes can be nested with `BumpScope::scope`.
+///
+/// Handles can't tell values allocated before the scope from the ones allocated in it: all the handles
+/// created so far become stale when the scope ends (see `BumpAllocator::allocate_handle`).
+///
+/// ## Usage
+/// ```
+/// use allocator::bumper::BumpAllocator;
+///
+/// let mut bump = BumpAllocator::<1024>::new();
+/// let kept = *bump.allocate(1u64);
+/// {
+///     let scope = bump.scope();
+///     let temporary = scope.allocate([0u8; 256]);
+///     temporary[0] = 1;
+/// } // The array space is reusable again
+/// assert_eq!(8, bump.as_bytes().len());
+/// # assert_eq!(1, kept);
+/// ```
+pub struct BumpScope<'a, const N: usize> {
+    bump: &'a mut BumpAllocator<N>,
+    /// Allocation cursor on scope creation
+    allocated: usize,
+    /// Allocation count on scope creation
+    alloc_count: usize,
+    /// Wasted padding on scope creation
+    wasted_padding: usize,
+    /// Count of owned values on scope creation
+    owned_count: usize,
+}
+
+impl<'a, const N: usize> BumpScope<'a, N> {
+    pub(super) fn new(bump: &'a mut BumpAllocator<N>) -> Self {
+        Self {
+            allocated: *bump.allocated.get_mut(),
+            alloc_count: *bump.alloc_count.get_mut(),
+            wasted_padding: *bump.wasted_padding.get_mut(),
+            owned_count: bump.owned_count(),
+            bump,
+        }
+    }
+
+    /// Open a nested scope: values allocated through it are freed when it ends, while this scope's values are kept.
+    pub fn scope(&mut self) -> BumpScope<'_, N> {
+        BumpScope::new(self.bump)
+    }
+}
+
+impl<const N: usize> Deref for BumpScope<'_, N> {
+    type Target = BumpAllocator<N>;
+
+    fn deref(&self) -> &Self::Target {
+        self.bump
+    }
+}
+
+impl<const N: usize> Drop for BumpScope<'_, N> {
+    fn drop(&mut self) {
+        // Handles to the values allocated in the scope must not be read
+        self.bump.generation.fetch_add(1, Ordering::SeqCst);
+        self.bump.drop_owned(self.owned_count);
+
+        self.bump.allocated.store(self.allocated, Ordering::SeqCst);
+        self.bump
+            .alloc_count
+            .store(self.alloc_count, Ordering::SeqCst);
+        self.bump
+            .wasted_padding
+            .store(self.wasted_padding, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bumper::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn scope_end_frees_scope_values() {
+        let mut bump = BumpAllocator::<64>::new();
+        bump.allocate(1u32);
+        {
+            let scope = bump.scope();
+            scope.allocate(2u64);
+            scope.allocate(3u64);
+            assert_eq!(3, scope.allocation_count());
+        }
+
+        assert_eq!(1, bump.allocation_count());
+        assert_eq!(&1u32.to_ne_bytes(), bump.as_bytes());
+    }
+
+    #[test]
+    fn scope_space_is_reused() {
+        let mut bump = BumpAllocator::<16>::new();
+        for _ in 0..3 {
+            let scope = bump.scope();
+            assert!(scope.try_allocate([0u8; 16]).is_ok());
+            assert!(scope.try_allocate(0u8).is_err());
+        }
+    }
+
+    #[test]
+    fn nested_scope_keeps_outer_values() {
+        let mut bump = BumpAllocator::<64>::new();
+        let mut outer = bump.scope();
+        outer.allocate(1u8);
+        {
+            let inner = outer.scope();
+            inner.allocate(2u64);
+            assert_eq!(2, inner.allocation_count());
+        }
+
+        assert_eq!(1, outer.allocation_count());
+        assert!(outer.can_fit(Layout::new::<[u8; 63]>()));
+    }
+
+    #[test]
+    fn scope_end_on_unwind() {
+        let mut bump = BumpAllocator::<64>::new();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let scope = bump.scope();
+            scope.allocate(1u64);
+            panic!("scope body failed");
+        }));
+
+        assert!(result.is_err());
+        assert_eq!(0, bump.allocation_count());
+    }
+
+    #[test]
+    fn scope_end_drops_scope_owned_values() {
+        let mut bump = BumpAllocator::<64>::new();
+        let kept = Arc::new(());
+        bump.allocate_owned(Arc::clone(&kept));
+        {
+            let scope = bump.scope();
+            scope.allocate_owned(Arc::clone(&kept));
+            assert_eq!(3, Arc::strong_count(&kept));
+        }
+
+        assert_eq!(2, Arc::strong_count(&kept));
+    }
+
+    #[test]
+    fn scope_end_makes_handles_stale() {
+        let mut bump = BumpAllocator::<64>::new();
+        let handle = {
+            let scope = bump.scope();
+            scope.allocate_handle(1u64)
+        };
+
+        assert!(!bump.is_current(handle));
+    }
+
+    #[test]
+    fn scope_values_are_wiped_after_scope_end() {
+        let mut bump = BumpAllocator::<64>::new();
+        bump.allocate(1u8);
+        {
+            let scope = bump.scope();
+            scope.allocate([0xAAu8; 32]);
+        }
+
+        bump.secure_wipe();
+        assert_eq!(1, bump.allocation_count());
+        assert!(bump.arena_bytes().iter().all(|byte| *byte == 0));
+
+        {
+            let scope = bump.scope();
+            scope.allocate([0xAAu8; 32]);
+        }
+        bump.dealloc_all(true);
+        assert!(bump.arena_bytes().iter().all(|byte| *byte == 0));
+    }
+}
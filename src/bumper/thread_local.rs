@@ -0,0 +1,223 @@
+use super::{arena_layout, secure_zero};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::RefCell,
+    ptr,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Source of unique allocator identifiers, used to find the arenas of an allocator.
+static NEXT_ALLOCATOR_ID: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    /// Arenas of the current thread, one per allocator instance which allocated from this thread.
+    static ARENAS: RefCell<Vec<LocalArena>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Bump allocator giving each thread its own arena, so allocations never contend across threads.
+///
+/// An arena of `N` bytes is lazily created the first time a thread allocates, and freed when the thread exits.
+///
+/// ## Thread safety
+/// The allocator can be shared across threads, but references returned by `allocate` point into the arena
+/// of the calling thread, which is freed when the thread exits: this is why `allocate` is unsafe.
+/// `dealloc_all` only resets the arena of the calling thread.
+pub struct ThreadLocalBumpAllocator<const N: usize> {
+    id: usize,
+}
+
+/// Arena owned by a single thread.
+struct LocalArena {
+    allocator_id: usize,
+    ptr: *mut u8,
+    size: usize,
+    allocated: usize,
+}
+
+impl Drop for LocalArena {
+    fn drop(&mut self) {
+        if !self.ptr.is_null() {
+            unsafe { GlobalAlloc::dealloc(&System, self.ptr, arena_layout(self.size)) };
+        }
+    }
+}
+
+impl<const N: usize> ThreadLocalBumpAllocator<N> {
+    /// Create a new instance of thread local bump allocator. Arenas are created on first use in each thread.
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        const { assert!(N > 0, "bump arena can't be empty") };
+
+        Self {
+            id: NEXT_ALLOCATOR_ID.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+
+    /// Allocate the given value in the arena of the calling thread.
+    ///
+    /// Known issue: value parameter is first allocated to the stack, which is not optimal.
+    ///
+    /// **Panics**: if there isn't enough space left in the arena
+    ///
+    /// # Safety
+    /// The returned reference must not be used once the calling thread exits, since its arena is freed then:
+    /// it must not be returned from a scoped thread borrowing the allocator for instance.
+    #[allow(clippy::mut_from_ref)]
+    pub unsafe fn allocate<T>(&self, value: T) -> &mut T {
+        let ptr = self.alloc(Layout::new::<T>());
+        if ptr.is_null() {
+            panic!("bump allocation failed");
+        }
+
+        ptr::write(ptr as *mut T, value);
+        (ptr as *mut T).as_mut().unwrap() // Return value at new address
+    }
+
+    /// Reset the arena of the calling thread, freeing all its space.
+    /// Arenas of other threads are left untouched.
+    ///
+    /// * `wipe_memory`: Set to true to write 0 bytes where memory was allocated, false to leave the memory intact.
+    ///
+    /// # Safety
+    /// No reference allocated by the calling thread may be used afterwards: the freed bytes are handed out again
+    /// by the next allocations.
+    pub unsafe fn dealloc_all(&self, wipe_memory: bool) {
+        self.with_arena(|arena| {
+            if wipe_memory && arena.allocated != 0 {
+                secure_zero(arena.ptr, arena.allocated);
+            }
+            arena.allocated = 0;
+        });
+    }
+
+    /// Get the count of bytes allocated in the arena of the calling thread.
+    pub fn allocated(&self) -> usize {
+        self.with_arena(|arena| arena.allocated)
+    }
+
+    /// Allocate memory for a layout in the arena of the calling thread.
+    fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.with_arena(|arena| {
+            if arena.ptr.is_null() {
+                return ptr::null_mut();
+            }
+
+            // Align the address rather than the offset, since the arena itself may be less aligned than the layout
+            let align = layout.align();
+            let cursor = arena.ptr as usize + arena.allocated;
+            let alloc_padding = (align - (cursor % align)) % align;
+            let alloc_offset = arena.allocated + alloc_padding;
+            match alloc_offset.checked_add(layout.size()) {
+                Some(alloc_end) if alloc_end <= N => {
+                    arena.allocated = alloc_end;
+                    unsafe { arena.ptr.add(alloc_offset) }
+                }
+                _ => ptr::null_mut(), // Not enough bytes available
+            }
+        })
+    }
+
+    /// Run the given function on the arena of the calling thread, creating it if needed.
+    fn with_arena<R>(&self, f: impl FnOnce(&mut LocalArena) -> R) -> R {
+        ARENAS.with(|arenas| {
+            let mut arenas = arenas.borrow_mut();
+            let index = match arenas.iter().position(|a| a.allocator_id == self.id) {
+                Some(index) => index,
+                None => {
+                    arenas.push(LocalArena {
+                        allocator_id: self.id,
+                        ptr: unsafe { GlobalAlloc::alloc(&System, arena_layout(N)) },
+                        size: N,
+                        allocated: 0,
+                    });
+                    arenas.len() - 1
+                }
+            };
+            f(&mut arenas[index])
+        })
+    }
+}
+
+impl<const N: usize> Drop for ThreadLocalBumpAllocator<N> {
+    fn drop(&mut self) {
+        // Free the arena of the current thread right away, others are freed on thread exit
+        let _ = ARENAS.try_with(|arenas| {
+            arenas
+                .borrow_mut()
+                .retain(|arena| arena.allocator_id != self.id)
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bumper::thread_local::*;
+    use std::{sync::Arc, thread};
+
+    #[test]
+    #[should_panic]
+    fn allocate_not_enough_space_panic() {
+        let bumper = ThreadLocalBumpAllocator::<2>::new();
+        unsafe { bumper.allocate(123) };
+    }
+
+    #[test]
+    fn allocate_enough_space() {
+        let bumper = ThreadLocalBumpAllocator::<8>::new();
+
+        let i32_var = unsafe { bumper.allocate(123) };
+        assert_eq!(123, *i32_var);
+        let bool_var = unsafe { bumper.allocate(true) };
+        assert!(*bool_var);
+        assert_eq!(5, bumper.allocated());
+    }
+
+    #[test]
+    fn threads_use_separate_arenas() {
+        let bumper = Arc::new(ThreadLocalBumpAllocator::<8>::new());
+        unsafe { bumper.allocate(1u64) }; // Main thread arena is full
+
+        let thread_bumper = bumper.clone();
+        let allocated = thread::spawn(move || {
+            let value = unsafe { thread_bumper.allocate(2u64) };
+            assert_eq!(2, *value);
+            thread_bumper.allocated()
+        })
+        .join()
+        .unwrap();
+
+        assert_eq!(8, allocated);
+        assert_eq!(8, bumper.allocated());
+    }
+
+    #[test]
+    fn dealloc_all_resets_current_thread_only() {
+        let bumper = Arc::new(ThreadLocalBumpAllocator::<8>::new());
+        unsafe { bumper.allocate(1u32) };
+
+        let thread_bumper = bumper.clone();
+        thread::spawn(move || {
+            unsafe {
+                thread_bumper.allocate(2u32);
+                thread_bumper.dealloc_all(true);
+            }
+            assert_eq!(0, thread_bumper.allocated());
+        })
+        .join()
+        .unwrap();
+
+        assert_eq!(4, bumper.allocated());
+    }
+
+    #[test]
+    fn allocate_over_aligned_value() {
+        #[repr(align(64))]
+        struct Aligned(u8);
+
+        let bumper = ThreadLocalBumpAllocator::<256>::new();
+        unsafe { bumper.allocate(1u8) };
+        let value = unsafe { bumper.allocate(Aligned(2)) };
+        assert_eq!(0, value as *const Aligned as usize % 64);
+        assert_eq!(2, value.0);
+    }
+}
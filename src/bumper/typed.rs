@@ -0,0 +1,173 @@
+use super::BumpAllocator;
+use crate::error::AllocError;
+use std::{cell::Cell, marker::PhantomData, ptr, sync::atomic::Ordering};
+
+/// Arena of values of a single type `T`, stored in a bump allocator of `N` bytes.
+///
+/// Unlike `BumpAllocator`, the allocated values are dropped: when the arena is dropped or cleared,
+/// the destructor of each value is run before its memory is reused. This allows storing values owning
+/// other resources, such as `String` or `Vec`, without leaking them.
+///
+/// Values are placed one after another, so they can be found back without any per-value bookkeeping.
+///
+/// ## Usage
+/// ```
+/// use allocator::bumper::TypedArena;
+///
+/// let arena = TypedArena::<String, 1024>::new();
+/// let name = arena.alloc(String::from("arena"));
+/// name.push_str("-allocated");
+/// // Strings are dropped along with the arena
+/// ```
+pub struct TypedArena<T, const N: usize> {
+    bump: BumpAllocator<N>,
+    /// Count of values placed since creation or the last `clear` call
+    len: Cell<usize>,
+    /// The arena owns values of type `T`
+    _values: PhantomData<T>,
+}
+
+impl<T, const N: usize> TypedArena<T, N> {
+    /// Create a new typed arena, allocating its memory region.
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self {
+            bump: BumpAllocator::new(),
+            len: Cell::new(0),
+            _values: PhantomData,
+        }
+    }
+
+    /// Move the given value into the arena.
+    ///
+    /// **Panics**: if there isn't enough space left in the arena
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc(&self, value: T) -> &mut T {
+        match self.try_alloc(value) {
+            Ok(value) => value,
+            Err(error) => panic!("bump allocation failed: {error}"),
+        }
+    }
+
+    /// Move the given value into the arena.
+    ///
+    /// **Returns**: An error if there isn't enough space left in the arena, the value is dropped then
+    #[allow(clippy::mut_from_ref)]
+    pub fn try_alloc(&self, value: T) -> Result<&mut T, AllocError> {
+        let value = self.bump.try_allocate(value)?;
+        self.len.set(self.len.get() + 1);
+        Ok(value)
+    }
+
+    /// Get the count of values in the arena.
+    pub fn len(&self) -> usize {
+        self.len.get()
+    }
+
+    /// Check if the arena holds no value.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drop every value of the arena, then reset it so that its space can be reused.
+    pub fn clear(&mut self) {
+        self.drop_values();
+        self.bump.dealloc_all(false);
+    }
+
+    /// Run the destructor of every value of the arena.
+    fn drop_values(&mut self) {
+        // Reset first: if a destructor panics, values are leaked rather than dropped twice
+        let len = self.len.replace(0);
+        if len == 0 {
+            return;
+        }
+
+        // Values only are allocated: they are contiguous, starting at the first `T` aligned address of the arena
+        let arena_ptr = self.bump.arena_ptr.load(Ordering::Acquire);
+        unsafe {
+            let first = arena_ptr.add(arena_ptr.align_offset(align_of::<T>())) as *mut T;
+            ptr::drop_in_place(ptr::slice_from_raw_parts_mut(first, len));
+        }
+    }
+}
+
+impl<T, const N: usize> Drop for TypedArena<T, N> {
+    fn drop(&mut self) {
+        self.drop_values();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bumper::typed::*;
+    use std::rc::Rc;
+
+    #[test]
+    fn alloc_values() {
+        let arena = TypedArena::<u64, 64>::new();
+        let a = arena.alloc(1);
+        let b = arena.alloc(2);
+        *a += 10;
+
+        assert_eq!((11, 2), (*a, *b));
+        assert_eq!(2, arena.len());
+    }
+
+    #[test]
+    fn try_alloc_not_enough_space_none() {
+        let arena = TypedArena::<u64, 8>::new();
+        assert!(arena.try_alloc(1).is_ok());
+        assert!(arena.try_alloc(2).is_err());
+        assert_eq!(1, arena.len());
+    }
+
+    #[test]
+    fn drop_runs_destructors() {
+        let counter = Rc::new(());
+        let arena = TypedArena::<Rc<()>, 256>::new();
+        for _ in 0..4 {
+            arena.alloc(Rc::clone(&counter));
+        }
+        assert_eq!(5, Rc::strong_count(&counter));
+
+        drop(arena);
+        assert_eq!(1, Rc::strong_count(&counter));
+    }
+
+    #[test]
+    fn clear_runs_destructors_and_reuses_space() {
+        let counter = Rc::new(());
+        let mut arena = TypedArena::<Rc<()>, 16>::new();
+        arena.alloc(Rc::clone(&counter));
+        arena.alloc(Rc::clone(&counter));
+        assert!(arena.try_alloc(Rc::clone(&counter)).is_err());
+
+        arena.clear();
+        assert!(arena.is_empty());
+        assert_eq!(1, Rc::strong_count(&counter));
+
+        arena.alloc(Rc::clone(&counter));
+        assert_eq!(2, Rc::strong_count(&counter));
+    }
+
+    #[test]
+    fn over_aligned_values() {
+        #[repr(align(64))]
+        struct Aligned {
+            _counter: Rc<()>,
+        }
+
+        let counter = Rc::new(());
+        let arena = TypedArena::<Aligned, 512>::new();
+        for _ in 0..3 {
+            let value = arena.alloc(Aligned {
+                _counter: Rc::clone(&counter),
+            });
+            assert_eq!(0, value as *mut Aligned as usize % 64);
+        }
+
+        drop(arena);
+        assert_eq!(1, Rc::strong_count(&counter));
+    }
+}
@@ -0,0 +1,33 @@
+use std::{alloc::LayoutError, error::Error, fmt};
+
+/// Reason why an allocation failed, returned by the fallible methods of the typed allocation APIs.
+///
+/// `GlobalAlloc` implementations still return null pointers, as required by the trait.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AllocError {
+    /// Not enough space is left in the arena. The allocation may succeed once memory is released.
+    OutOfMemory,
+    /// The requested size overflows, for a slice too long for its element size for instance.
+    LayoutOverflow,
+    /// The requested layout is larger than the whole arena: the allocation can never succeed.
+    ArenaTooSmall,
+}
+
+impl fmt::Display for AllocError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            AllocError::OutOfMemory => "not enough space left in the arena",
+            AllocError::LayoutOverflow => "requested size overflows",
+            AllocError::ArenaTooSmall => "requested size exceeds the arena size",
+        };
+        f.write_str(reason)
+    }
+}
+
+impl Error for AllocError {}
+
+impl From<LayoutError> for AllocError {
+    fn from(_: LayoutError) -> Self {
+        AllocError::LayoutOverflow
+    }
+}
@@ -1,18 +1,553 @@
-use super::node::{AllocationMetadata, AllocationSpecs, Node, ALLOCATION_METADATA_LAYOUT_SIZE};
+#[cfg(feature = "alloc_age")]
+use super::inspect::AgedAllocInfo;
+use super::{
+    arena_layout,
+    config::{FitStrategy, FreeListConfig, MetadataPlacement},
+    inspect::{AllocInfo, FreeBlock, FreeListCheckpoint, RepairReport},
+    node::{
+        AllocationMetadata, AllocationSpecs, LiveHeader, Node, ALLOCATION_METADATA_LAYOUT_SIZE,
+        MAX_METADATA_PADDING, NODE_LAYOUT_ALIGN, NODE_LAYOUT_SIZE,
+    },
+    stats::FreeListStats,
+    ARENA_ALIGN,
+};
 use std::{
-    ptr,
+    alloc::{GlobalAlloc, Layout, System},
+    mem::size_of,
+    ptr::{self, null_mut},
+    slice,
     sync::atomic::{AtomicPtr, Ordering},
 };
 
+/// Stride used to touch memory pages (see `AllocatorRoot::prefault`). Touching more often than the actual page size is harmless.
+const PAGE_SIZE: usize = 4096;
+
+/// Size of the data following the arena bytes in a snapshot: the free root offset and the live bytes count.
+pub(crate) const SNAPSHOT_TRAILER_SIZE: usize = 2 * size_of::<usize>();
+
 pub(crate) struct AllocatorRoot {
     pub(crate) free_root: Option<AtomicPtr<u8>>,
+    /// Start of the arena managed by this root
+    pub(crate) arena_ptr: AtomicPtr<u8>,
+    /// Size of the arena, in bytes. It is kept while the arena is released by `trim`.
+    pub(crate) arena_size: usize,
+    /// Cumulative size of the values currently allocated in the arena, paddings and metadata excluded
+    pub(crate) live_bytes: usize,
+    /// Arena bytes held by allocations, paddings and metadata included
+    pub(crate) allocated_bytes: usize,
+    /// Highest value reached by `allocated_bytes`
+    pub(crate) peak_allocated_bytes: usize,
+    /// Free Nodes are sorted by address and merged with their neighbors.
+    /// This is false after deallocations made without coalescing (see `FreeListConfig::coalesce_on_free`).
+    pub(crate) sorted: bool,
+    /// Most recently freed Node, checked first by allocations when `FreeListConfig::recent_free_cache` is enabled
+    pub(crate) recent_free: Option<RecentFree>,
+    /// Size of the arena given back to the `System` allocator by `trim`, allocated again by the next allocation
+    pub(crate) released_size: Option<usize>,
+    /// Sequence number of the next tracked allocation (see `LiveHeader::sequence`)
+    #[cfg(feature = "alloc_age")]
+    pub(crate) next_sequence: usize,
+}
+
+/// Location of the most recently freed Node, as offsets from the arena start.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct RecentFree {
+    pub(crate) node_offset: usize,
+    /// Offset of the Node preceding it in the free list, `None` if it is the free root
+    pub(crate) previous_offset: Option<usize>,
 }
 
 impl AllocatorRoot {
+    /// Create an empty root, without any arena: it can't allocate.
+    pub(crate) const fn empty() -> Self {
+        AllocatorRoot {
+            free_root: None,
+            arena_ptr: AtomicPtr::new(null_mut()),
+            arena_size: 0,
+            live_bytes: 0,
+            allocated_bytes: 0,
+            peak_allocated_bytes: 0,
+            sorted: true,
+            recent_free: None,
+            released_size: None,
+            #[cfg(feature = "alloc_age")]
+            next_sequence: 0,
+        }
+    }
+
+    /// Create a root managing the given arena, writing a single free Node spanning all of it.
+    ///
+    /// A null arena pointer (failed arena allocation) results in an empty root, which can't allocate.
+    pub(crate) unsafe fn new(arena_ptr: *mut u8, arena_size: usize) -> Self {
+        if arena_ptr.is_null() {
+            return Self::empty();
+        }
+
+        // Write root node at the start of the arena
+        let root_node = Node {
+            size: arena_size,
+            next_ptr: None,
+        };
+        ptr::write(arena_ptr as *mut Node, root_node);
+
+        AllocatorRoot {
+            free_root: Some(AtomicPtr::new(arena_ptr)),
+            arena_ptr: AtomicPtr::new(arena_ptr),
+            arena_size,
+            live_bytes: 0,
+            allocated_bytes: 0,
+            peak_allocated_bytes: 0,
+            sorted: true,
+            recent_free: None,
+            released_size: None,
+            #[cfg(feature = "alloc_age")]
+            next_sequence: 0,
+        }
+    }
+
+    /// Release every allocation, writing a single free Node spanning the whole arena.
+    /// Nothing is done if the arena isn't allocated: nothing is allocated in it.
+    pub(crate) unsafe fn reset(&mut self) {
+        let arena_ptr = self.arena_ptr.load(Ordering::Acquire);
+        if arena_ptr.is_null() {
+            return;
+        }
+        self.replace(Self::new(arena_ptr, self.arena_size));
+    }
+
+    /// Replace this root by the given one, keeping the peak of allocated bytes, the released arena size
+    /// and the allocation sequence.
+    fn replace(&mut self, root: Self) {
+        let peak_allocated_bytes = self.peak_allocated_bytes;
+        let released_size = self.released_size;
+        #[cfg(feature = "alloc_age")]
+        let next_sequence = self.next_sequence;
+        *self = root;
+        self.peak_allocated_bytes = peak_allocated_bytes;
+        self.released_size = released_size;
+        #[cfg(feature = "alloc_age")]
+        {
+            self.next_sequence = next_sequence;
+        }
+    }
+
+    /// Give the arena back to the `System` allocator if it is idle, a single free Node spanning all of it.
+    /// The next allocation allocates a new arena of the same size.
+    ///
+    /// **Returns**: true if the arena was released
+    pub(crate) unsafe fn trim(&mut self) -> bool {
+        let arena_ptr = self.arena_ptr.load(Ordering::Acquire);
+        if arena_ptr.is_null() {
+            return false;
+        }
+
+        // Deallocations without coalescing may have left contiguous Nodes
+        self.sort();
+        let idle = matches!(
+            self.free_nodes().next(),
+            Some((node_ptr, node)) if ptr::eq(node_ptr, arena_ptr) && node.size == self.arena_size
+        );
+        if !idle {
+            return false;
+        }
+
+        let arena_size = self.arena_size;
+        System.dealloc(arena_ptr, arena_layout(arena_size).unwrap());
+        self.replace(Self::empty());
+        self.arena_size = arena_size;
+        self.released_size = Some(arena_size);
+        true
+    }
+
+    /// Allocate a new arena in place of the one released by `trim`, if any.
+    /// The arena stays released if it can't be allocated.
+    pub(crate) unsafe fn reallocate_released(&mut self) {
+        let Some(arena_size) = self.released_size else {
+            return;
+        };
+        let arena_ptr = System.alloc(arena_layout(arena_size).unwrap());
+        if arena_ptr.is_null() {
+            return;
+        }
+        self.replace(Self::new(arena_ptr, arena_size));
+        self.released_size = None;
+    }
+
+    /// Move the arena start to `arena_ptr`, the arena bytes having been moved there (along with an inline arena).
+    /// Free list links are shifted by the move distance.
+    pub(crate) unsafe fn rebase(&mut self, arena_ptr: *mut u8) {
+        let previous_arena_ptr = self.arena_ptr.load(Ordering::Acquire);
+        if previous_arena_ptr == arena_ptr || previous_arena_ptr.is_null() {
+            return;
+        }
+
+        let rebase_ptr = |ptr: *const u8| arena_ptr.add(ptr as usize - previous_arena_ptr as usize);
+        self.free_root = self
+            .free_root
+            .take()
+            .map(|root| AtomicPtr::new(rebase_ptr(root.load(Ordering::Acquire))));
+        self.arena_ptr.store(arena_ptr, Ordering::Release);
+
+        let mut cursor = self
+            .free_root
+            .as_ref()
+            .map(|root| root.load(Ordering::Acquire) as *const u8);
+        while let Some(node_ptr) = cursor {
+            let mut node = ptr::read(node_ptr as *const Node);
+            node.next_ptr = node.next_ptr.map(|ptr| rebase_ptr(ptr) as *const u8);
+            cursor = node.next_ptr;
+            ptr::write(node_ptr as *mut Node, node);
+        }
+    }
+
+    /// Search the free list for a suitable Node and allocate the given layout in place of it.
+    /// If no Node is large enough, contiguous Nodes are merged and the search is run once more. There are only
+    /// contiguous Nodes after deallocations without coalescing (see `FreeListConfig::coalesce_on_free`):
+    /// otherwise, nothing is merged and the search isn't run again.
+    ///
+    /// **Returns**: Pointer to the newly allocated block, null if no free Node is large enough
+    pub(crate) unsafe fn alloc(&mut self, layout: Layout, config: &FreeListConfig) -> *mut u8 {
+        self.reallocate_released();
+        if config.recent_free_cache {
+            let ptr = self.recent_free_alloc(layout, config);
+            if !ptr.is_null() {
+                return ptr;
+            }
+        }
+
+        let ptr = self.fit_alloc(layout, config);
+        if ptr.is_null() && self.defragment() != 0 {
+            // Free space may have been split across unmerged Nodes, retry
+            return self.fit_alloc(layout, config);
+        }
+        ptr
+    }
+
+    /// Allocate the given layout in place of the most recently freed Node, without scanning the free list.
+    ///
+    /// The cached location is checked against the free list links before being used, so a stale entry is simply a miss.
+    ///
+    /// **Returns**: Pointer to the newly allocated block, null if the Node isn't cached or isn't large enough
+    unsafe fn recent_free_alloc(&mut self, layout: Layout, config: &FreeListConfig) -> *mut u8 {
+        let Some(recent) = self.recent_free else {
+            return null_mut();
+        };
+        let arena_ptr = self.arena_ptr.load(Ordering::Acquire) as *const u8;
+        let node_ptr = arena_ptr.add(recent.node_offset);
+        let previous_ptr = recent.previous_offset.map(|offset| arena_ptr.add(offset));
+
+        // The Node must still be linked from its cached predecessor
+        let linked_ptr = match previous_ptr {
+            Some(ptr) => ptr::read(ptr as *const Node).next_ptr,
+            None => self
+                .free_root
+                .as_ref()
+                .map(|root| root.load(Ordering::Acquire) as *const u8),
+        };
+        if linked_ptr != Some(node_ptr) {
+            self.recent_free = None;
+            return null_mut();
+        }
+
+        let node = ptr::read(node_ptr as *const Node);
+        match node.try_get_alloc_specs(
+            layout.size(),
+            layout.align(),
+            node_ptr,
+            config.min_block_size,
+            config.header_size(),
+            config.metadata_placement,
+        ) {
+            Ok(alloc_specs) => self.split_alloc(previous_ptr, node, alloc_specs),
+            Err(_) => null_mut(),
+        }
+    }
+
+    /// Allocate the given layout in place of a free Node large enough to contain it, chosen according to the fit strategy.
+    ///
+    /// **Returns**: Pointer to the newly allocated block, null if no free Node is large enough
+    unsafe fn fit_alloc(&mut self, layout: Layout, config: &FreeListConfig) -> *mut u8 {
+        let size = layout.size();
+        let align = layout.align();
+        let min_block_size = config.min_block_size;
+        // Large alignments are placed where they need the least padding, whatever the fit strategy
+        let least_padding = align >= config.least_padding_align;
+
+        // Best candidate so far: previous Node pointer, Node and allocation specs
+        let mut candidate: Option<(Option<*const u8>, Node, AllocationSpecs)> = None;
+        let mut previous_node_ptr = None;
+        let mut cursor = self
+            .free_root
+            .as_ref()
+            .map(|root| root.load(Ordering::Acquire) as *const u8);
+
+        // Iterate over free nodes, sorted by address
+        while let Some(node_ptr) = cursor {
+            let node = ptr::read(node_ptr as *const Node);
+            cursor = node.next_ptr;
+
+            if let Ok(alloc_specs) = node.try_get_alloc_specs(
+                size,
+                align,
+                node_ptr,
+                min_block_size,
+                config.header_size(),
+                config.metadata_placement,
+            ) {
+                if least_padding {
+                    // Ties are resolved in favor of the lowest address
+                    if candidate
+                        .as_ref()
+                        .is_none_or(|(_, _, best_specs)| alloc_specs.padding < best_specs.padding)
+                    {
+                        let no_padding = alloc_specs.padding
+                            == alloc_specs.header_size + alloc_specs.placement.leading_size();
+                        candidate = Some((previous_node_ptr, node, alloc_specs));
+                        if no_padding {
+                            // Already aligned: can't do better
+                            break;
+                        }
+                    }
+                    previous_node_ptr = Some(node_ptr);
+                    continue;
+                }
+
+                match config.fit_strategy {
+                    FitStrategy::FirstFit => {
+                        // Allocate in place of the current free node
+                        return self.split_alloc(previous_node_ptr, node, alloc_specs);
+                    }
+                    FitStrategy::BestFit => {
+                        // Ties are resolved in favor of the lowest address
+                        if candidate
+                            .as_ref()
+                            .is_none_or(|(_, best_node, _)| node.size < best_node.size)
+                        {
+                            candidate = Some((previous_node_ptr, node, alloc_specs));
+                        }
+                    }
+                    FitStrategy::WorstFit => {
+                        // Ties are resolved in favor of the lowest address
+                        if candidate.as_ref().is_none_or(|(_, _, best_specs)| {
+                            alloc_specs.remaining_size > best_specs.remaining_size
+                        }) {
+                            candidate = Some((previous_node_ptr, node, alloc_specs));
+                        }
+                    }
+                }
+            }
+
+            previous_node_ptr = Some(node_ptr);
+        }
+
+        match candidate {
+            Some((previous_ptr, node, alloc_specs)) => {
+                self.split_alloc(previous_ptr, node, alloc_specs)
+            }
+            // Failed to find a suitable space
+            None => null_mut(),
+        }
+    }
+
+    /// Check if a free Node is large enough to allocate the given layout, without allocating it.
+    /// An arena released by `trim` is checked as a single free Node, since the allocation would allocate it again.
+    pub(crate) unsafe fn can_allocate(&self, layout: Layout, config: &FreeListConfig) -> bool {
+        // The new arena address is unknown: the lowest address aligned like it is padded the most
+        let released = self
+            .released_node()
+            .map(|node| (ARENA_ALIGN as *const u8, node));
+        self.free_nodes().chain(released).any(|(ptr, node)| {
+            node.try_get_alloc_specs(
+                layout.size(),
+                layout.align(),
+                ptr,
+                config.min_block_size,
+                config.header_size(),
+                config.metadata_placement,
+            )
+            .is_ok()
+        })
+    }
+
+    /// Release the allocated block pointed by `ptr` back to the free list.
+    pub(crate) unsafe fn dealloc(&mut self, ptr: *mut u8, layout: Layout, config: &FreeListConfig) {
+        let (block_ptr, block_size) = self.release_block(ptr, layout, config.metadata_placement);
+        self.free_block(block_ptr, block_size, config);
+    }
+
+    /// Add a free block to the free list, merging it with its neighbors if `FreeListConfig::coalesce_on_free` is enabled.
+    unsafe fn free_block(
+        &mut self,
+        block_ptr: *mut u8,
+        block_size: usize,
+        config: &FreeListConfig,
+    ) {
+        if config.coalesce_on_free {
+            self.create_free_node(block_ptr, block_size);
+        } else {
+            self.push_free_node(block_ptr, block_size);
+        }
+    }
+
+    /// Prepend a free Node to the free list, in constant time: it isn't sorted nor merged with its neighbors
+    /// until the next call to `sort`.
+    pub(crate) unsafe fn push_free_node(&mut self, block_ptr: *mut u8, block_size: usize) {
+        let next_ptr = self
+            .free_root
+            .as_ref()
+            .map(|root| root.load(Ordering::Acquire) as *const u8);
+        let node = Node {
+            size: block_size,
+            next_ptr,
+        };
+        ptr::write(block_ptr as *mut Node, node);
+
+        self.free_root = Some(AtomicPtr::new(block_ptr));
+        self.sorted &= next_ptr.is_none();
+        self.recent_free = Some(RecentFree {
+            node_offset: self.offset_of(block_ptr),
+            previous_offset: None,
+        });
+    }
+
+    /// Sort the free list by address and merge contiguous Nodes, if deallocations left it unsorted.
+    pub(crate) unsafe fn sort(&mut self) {
+        if !self.sorted {
+            self.repair();
+        }
+    }
+
+    /// Release the given allocations back to the free list, walking it a single time.
+    ///
+    /// Allocations are sorted by address in place, so that each one is inserted after the previous one.
+    /// Null pointers, zero-sized allocations and pointers outside of the arena are skipped.
+    pub(crate) unsafe fn dealloc_batch(
+        &mut self,
+        allocations: &mut [(*mut u8, Layout)],
+        config: &FreeListConfig,
+    ) {
+        allocations.sort_unstable_by_key(|(ptr, _)| *ptr as usize);
+        self.sort();
+
+        let mut previous_ptr = None;
+        let mut next_ptr = self
+            .free_root
+            .as_ref()
+            .map(|root| root.load(Ordering::Acquire) as *const u8);
+        for &(ptr, layout) in allocations.iter() {
+            if ptr.is_null() || layout.size() == 0 || !self.contains(ptr) {
+                continue;
+            }
+
+            let (block_ptr, block_size) =
+                self.release_block(ptr, layout, config.metadata_placement);
+            // Resume the free list walk where the previous block was inserted
+            while let Some(node_ptr) = next_ptr.filter(|node_ptr| *node_ptr < block_ptr) {
+                previous_ptr = Some(node_ptr);
+                next_ptr = ptr::read(node_ptr as *const Node).next_ptr;
+            }
+
+            let dest_ptr = self.insert_free_node(block_ptr, block_size, previous_ptr, next_ptr);
+            previous_ptr = Some(dest_ptr);
+            next_ptr = ptr::read(dest_ptr as *const Node).next_ptr;
+        }
+        self.recent_free = None;
+    }
+
+    /// Read the metadata of the allocation pointed by `ptr`, and remove the allocation from the live bytes.
+    ///
+    /// **Returns**: Start and size of the allocated block
+    unsafe fn release_block(
+        &mut self,
+        ptr: *mut u8,
+        layout: Layout,
+        placement: MetadataPlacement,
+    ) -> (*mut u8, usize) {
+        // Get allocation metadata
+        let metadata = AllocationMetadata::read(placement.metadata_ptr(ptr, layout.size()));
+        metadata.check_magic();
+        let align_padding = metadata.align_padding as usize;
+        let fill_padding = metadata.fill_padding as usize;
+        #[cfg(debug_assertions)]
+        {
+            assert_eq!(
+                layout.align().trailing_zeros(),
+                metadata.align_shift,
+                "Deallocation layout alignment doesn't match the allocation alignment"
+            );
+            assert!(
+                (ptr as usize).is_multiple_of(layout.align()),
+                "Deallocated pointer isn't aligned to the layout alignment"
+            );
+        }
+        // Get start of block
+        let block_ptr = ptr.sub(align_padding + placement.leading_size());
+        self.live_bytes = self.live_bytes.saturating_sub(layout.size());
+
+        let block_size =
+            align_padding + layout.size() + ALLOCATION_METADATA_LAYOUT_SIZE + fill_padding;
+        self.allocated_bytes = self.allocated_bytes.saturating_sub(block_size);
+        (block_ptr, block_size)
+    }
+
+    /// Shrink the allocated block pointed by `ptr` in place, from `layout.size()` to `new_size` bytes.
+    /// If the freed tail can hold a free Node, it is released to the free list, otherwise it is added to the fill padding.
+    ///
+    /// **Returns**: `false` if the block couldn't be shrunk, in which case it is left untouched
+    pub(crate) unsafe fn shrink(
+        &mut self,
+        ptr: *mut u8,
+        layout: Layout,
+        new_size: usize,
+        config: &FreeListConfig,
+    ) -> bool {
+        let placement = config.metadata_placement;
+        let metadata = AllocationMetadata::read(placement.metadata_ptr(ptr, layout.size()));
+        metadata.check_magic();
+        let align_padding = metadata.align_padding as usize;
+        let block_ptr = ptr.sub(align_padding + placement.leading_size());
+        let block_end =
+            ptr.add(layout.size() + placement.trailing_size() + metadata.fill_padding as usize);
+
+        // The shrunk block still needs to be able to fit a Node once it's deallocated
+        let alloc_end = ptr.add(new_size + placement.trailing_size());
+        let block_size = (alloc_end as usize - block_ptr as usize).max(NODE_LAYOUT_SIZE);
+        // A Node placed after the block must be aligned
+        let tail_ptr = block_ptr
+            .add(block_size)
+            .add(block_ptr.add(block_size).align_offset(NODE_LAYOUT_ALIGN));
+
+        let fill_padding;
+        let tail_size = (block_end as usize).saturating_sub(tail_ptr as usize);
+        if tail_size >= config.min_block_size.max(NODE_LAYOUT_SIZE) {
+            // Release the tail
+            fill_padding = tail_ptr as usize - alloc_end as usize;
+            self.free_block(tail_ptr, tail_size, config);
+            self.allocated_bytes = self.allocated_bytes.saturating_sub(tail_size);
+        } else {
+            // Keep the whole block, the freed bytes are given to the fill padding
+            fill_padding = block_end as usize - alloc_end as usize;
+            if fill_padding > MAX_METADATA_PADDING {
+                return false;
+            }
+        }
+
+        AllocationMetadata {
+            fill_padding: fill_padding as u32,
+            ..metadata
+        }
+        .write(placement.metadata_ptr(ptr, new_size));
+        if config.live_tracking {
+            (*(block_ptr as *mut LiveHeader)).value_size = new_size;
+        }
+        self.live_bytes = self.live_bytes.saturating_sub(layout.size() - new_size);
+        true
+    }
+
     /// Allocate memory for the given size and alignment parameters, in place of an existing free Node.
     /// If there is enough space left, add a new free Node with the remaining size.
     ///
-    /// **Returns**: Pointer to the newly allocated block
+    /// **Returns**: Pointer to the newly allocated block, null if the block would extend past the arena end
     ///
     /// **Allocation possibilities**:
     /// - | PAD . ALLOC . ALLOC_METADATA . FILL_PAD |
@@ -25,27 +560,45 @@ impl AllocatorRoot {
     ///     - Added padding count (PAD size), may be 0
     ///     - Additional padding count (FILL_PAD size), may be 0
     /// - FILL_PAD: additional padding after the allocated block to fill size up to a Node space
-    /// (this is mandatory for deallocation process: must have enough space to allocate a free Node in place of this)
+    ///   (this is mandatory for deallocation process: must have enough space to allocate a free Node in place of this),
+    ///   and to align the following FREE_NODE
     /// - FREE_NODE: optional free Node instance if there is enough size to place it
+    ///
+    /// When the metadata is placed before the value, the block is laid out as | PAD . ALLOC_METADATA . ALLOC . FILL_PAD |,
+    /// and PAD is part of the allocation padding along with ALLOC_METADATA.
     pub(crate) unsafe fn split_alloc(
         &mut self,
-        previous: Option<Node>,
+        previous_ptr: Option<*const u8>,
         current: Node,
         alloc_specs: AllocationSpecs,
     ) -> *mut u8 {
-        let is_root: bool;
-        let mut prev_node = if let Some(prev) = previous {
-            is_root = false;
-            prev
+        let mut prev_node = if let Some(ptr) = previous_ptr {
+            ptr::read(ptr as *const Node)
         } else {
             // Dummy node
-            is_root = true;
             Node {
                 next_ptr: Some(self.free_root.as_mut().unwrap().load(Ordering::Acquire)),
                 size: 0,
             }
         };
 
+        // Bound the whole block against the arena end before writing anything,
+        // so that a corrupted Node or an extreme layout can't lead to writes outside of the arena
+        let block_ptr = prev_node.next_ptr.unwrap();
+        let arena_end = self.arena_ptr.load(Ordering::Acquire) as usize + self.arena_size;
+        let block_end = alloc_specs
+            .padding
+            .checked_add(alloc_specs.size)
+            .and_then(|size| size.checked_add(alloc_specs.placement.trailing_size()))
+            .and_then(|size| size.checked_add(alloc_specs.fill_padding))
+            .and_then(|size| size.checked_add(alloc_specs.remaining_size))
+            .and_then(|size| (block_ptr as usize).checked_add(size));
+        if block_end.is_none_or(|block_end| block_end > arena_end) {
+            return null_mut();
+        }
+        // The free list is about to change, the cached Node may not exist anymore
+        self.recent_free = None;
+
         let new_node = if alloc_specs.remaining_size != 0 {
             Some(Node {
                 next_ptr: None, // Will be set later in the function
@@ -56,24 +609,36 @@ impl AllocatorRoot {
         };
 
         // calculate allocation ptr (current block start + padding)
-        let alloc_ptr = prev_node
-            .next_ptr
-            .unwrap()
-            .cast_mut()
-            .add(alloc_specs.padding);
-
-        // Write allocation metadata after value
-        let mut ptr_cursor = alloc_ptr.add(alloc_specs.size);
-        let metadata = AllocationMetadata {
-            align_padding: alloc_specs.padding,
-            fill_padding: alloc_specs.fill_padding,
-        };
-        ptr::write(ptr_cursor as *mut AllocationMetadata, metadata);
+        let alloc_ptr = block_ptr.cast_mut().add(alloc_specs.padding);
+
+        if alloc_specs.header_size != 0 {
+            // Live allocations are tracked
+            let header = LiveHeader {
+                value_offset: alloc_specs.padding,
+                value_size: alloc_specs.size,
+                value_align: alloc_specs.align,
+                #[cfg(feature = "alloc_age")]
+                sequence: self.next_sequence,
+            };
+            ptr::write(block_ptr as *mut LiveHeader, header);
+            #[cfg(feature = "alloc_age")]
+            {
+                self.next_sequence += 1;
+            }
+        }
+
+        // Write allocation metadata after or before value
+        let metadata_ptr = alloc_specs
+            .placement
+            .metadata_ptr(alloc_ptr, alloc_specs.size);
+        AllocationMetadata::from_specs(&alloc_specs).write(metadata_ptr);
 
         // Add free node
         if let Some(mut node) = new_node {
             // Split the area into allocated and free
-            ptr_cursor = ptr_cursor.add(ALLOCATION_METADATA_LAYOUT_SIZE + alloc_specs.fill_padding);
+            let ptr_cursor = alloc_ptr.add(
+                alloc_specs.size + alloc_specs.placement.trailing_size() + alloc_specs.fill_padding,
+            );
             node.next_ptr = current.next_ptr;
             ptr::write(ptr_cursor as *mut Node, node); // Write Node
 
@@ -83,18 +648,37 @@ impl AllocatorRoot {
             prev_node.next_ptr = current.next_ptr;
         }
 
-        // Additional work if root node
-        if is_root {
+        if let Some(ptr) = previous_ptr {
+            // Persist the previous node link
+            ptr::write(ptr as *mut Node, prev_node);
+        } else {
+            // Additional work if root node
             self.free_root = prev_node
                 .next_ptr
                 .map(|next_ptr| AtomicPtr::new(next_ptr as *mut u8))
         }
 
+        self.live_bytes += alloc_specs.size;
+        self.allocated_bytes += alloc_specs.padding
+            + alloc_specs.size
+            + alloc_specs.placement.trailing_size()
+            + alloc_specs.fill_padding;
+        self.peak_allocated_bytes = self.peak_allocated_bytes.max(self.allocated_bytes);
         alloc_ptr
     }
 
     /// Create a new free block Node, trying to merge it with its adjacent Nodes.
+    ///
+    /// The block start must be aligned for a Node: this holds for every block carved from the free list,
+    /// as the arena start and every split Node are Node-aligned.
     pub(crate) unsafe fn create_free_node(&mut self, block_ptr: *mut u8, initial_size: usize) {
+        debug_assert!(
+            (block_ptr as usize).is_multiple_of(NODE_LAYOUT_ALIGN),
+            "freed block {block_ptr:?} isn't aligned for a Node"
+        );
+        // The insertion point can only be found in a sorted list
+        self.sort();
+
         let root_ptr = if let Some(ptr) = &self.free_root {
             ptr.load(Ordering::Acquire)
         } else {
@@ -107,6 +691,10 @@ impl AllocatorRoot {
             ptr::write(block_ptr as *mut Node, node);
 
             self.free_root = Some(AtomicPtr::new(block_ptr));
+            self.recent_free = Some(RecentFree {
+                node_offset: self.offset_of(block_ptr),
+                previous_offset: None,
+            });
 
             return;
         };
@@ -114,15 +702,51 @@ impl AllocatorRoot {
         // Iterate over nodes linked list, searching for correct location to write node (sorted by pointer adress).
         let (previous_ptr, next_ptr) = self.find_insertion_point(block_ptr, root_ptr);
 
-        // Once this place is found, try to merge adjacent blocks.
-        let (node, dest_ptr) =
-            self.try_merge_nodes(block_ptr, initial_size, previous_ptr, next_ptr);
+        // Once this place is found, try to merge adjacent blocks, and link the resulting Node
+        // from the previous Node, or from the free root
+        let dest_ptr = self.insert_free_node(block_ptr, initial_size, previous_ptr, next_ptr);
+
+        // A Node merged into its predecessor isn't cached, as the predecessor of that one is unknown
+        self.recent_free = if ptr::eq(dest_ptr, block_ptr) {
+            Some(RecentFree {
+                node_offset: self.offset_of(dest_ptr),
+                previous_offset: previous_ptr.map(|ptr| self.offset_of(ptr)),
+            })
+        } else {
+            None
+        };
+    }
+
+    /// Offset of the given arena pointer from the arena start.
+    pub(crate) fn offset_of(&self, ptr: *const u8) -> usize {
+        ptr as usize - self.arena_ptr.load(Ordering::Acquire) as usize
+    }
+
+    /// Write a free Node for the given block, between two consecutive Nodes of the free list.
+    /// The block is merged with them if they are adjacent, and the resulting Node is linked from the previous Node or the free root.
+    ///
+    /// **Returns**: Location of the written Node
+    unsafe fn insert_free_node(
+        &mut self,
+        block_ptr: *mut u8,
+        block_size: usize,
+        previous_ptr: Option<*const u8>,
+        next_ptr: Option<*const u8>,
+    ) -> *const u8 {
+        let (node, dest_ptr) = self.try_merge_nodes(block_ptr, block_size, previous_ptr, next_ptr);
         ptr::write(dest_ptr as *mut Node, node);
 
-        if previous_ptr.is_none() {
-            // Replace root
-            self.free_root = Some(AtomicPtr::new(dest_ptr));
+        match previous_ptr {
+            // Merged into the previous Node, which is already linked
+            Some(previous_ptr) if ptr::eq(previous_ptr, dest_ptr) => {}
+            Some(previous_ptr) => {
+                let mut previous = ptr::read(previous_ptr as *const Node);
+                previous.next_ptr = Some(dest_ptr);
+                ptr::write(previous_ptr as *mut Node, previous);
+            }
+            None => self.free_root = Some(AtomicPtr::new(dest_ptr)),
         }
+        dest_ptr
     }
 
     /// Find the new Node location, which is adjacent to one or two Nodes, sorted by memory adress.
@@ -132,6 +756,8 @@ impl AllocatorRoot {
     /// - Optional next Node pointer
     ///
     /// **Note**: returned pointer options can't be both None.
+    ///
+    /// **Panics**: if the free list contains a cycle
     pub(crate) unsafe fn find_insertion_point(
         &self,
         block_ptr: *const u8,
@@ -141,9 +767,18 @@ impl AllocatorRoot {
             return (None, Some(root_ptr));
         }
 
+        // The arena can't hold more Nodes than this: visiting more means the list loops
+        let max_nodes = self.arena_size / NODE_LAYOUT_SIZE;
+        let mut visited_nodes = 0;
+
         let mut previous_node_ptr = root_ptr;
         let mut previous_node: Node;
         loop {
+            visited_nodes += 1;
+            if visited_nodes > max_nodes {
+                panic!("free list cycle detected, the free list is corrupted (double free?)");
+            }
+
             previous_node = ptr::read(previous_node_ptr as *const Node);
             previous_node_ptr = match previous_node.next_ptr {
                 Some(ptr) if block_ptr < ptr => {
@@ -204,4 +839,586 @@ impl AllocatorRoot {
 
         (node, new_ptr as *mut u8)
     }
+
+    /// Walk the free list and panic if one of its invariants is broken:
+    /// - Nodes are strictly sorted by memory address, as expected by `find_insertion_point`
+    /// - Nodes don't overlap and aren't physically contiguous (`try_merge_nodes` should have merged them)
+    /// - Nodes are large enough to hold a Node and don't extend past the arena end
+    ///
+    /// Order and contiguity aren't checked while deallocations without coalescing left the list unsorted.
+    pub(crate) unsafe fn assert_valid(&self) {
+        let arena_start = self.arena_ptr.load(Ordering::Acquire) as *const u8;
+        let arena_end = arena_start.add(self.arena_size);
+        // The arena can't hold more Nodes than this: visiting more means the list loops
+        let max_nodes = self.arena_size / NODE_LAYOUT_SIZE;
+        let mut visited_nodes = 0;
+
+        let mut node_ptr = match &self.free_root {
+            Some(ptr) => ptr.load(Ordering::Acquire) as *const u8,
+            None => return, // Empty free list
+        };
+        loop {
+            visited_nodes += 1;
+            assert!(
+                visited_nodes <= max_nodes,
+                "free list cycle detected at {node_ptr:?}"
+            );
+            assert!(
+                node_ptr >= arena_start && node_ptr < arena_end,
+                "free node {node_ptr:?} is outside of the arena"
+            );
+            assert!(
+                (node_ptr as usize).is_multiple_of(NODE_LAYOUT_ALIGN),
+                "free node {node_ptr:?} is misaligned"
+            );
+            let node = ptr::read(node_ptr as *const Node);
+            assert!(
+                node.size >= NODE_LAYOUT_SIZE,
+                "free node {node_ptr:?} is too small to hold a Node ({} bytes)",
+                node.size
+            );
+            assert!(
+                node.size <= arena_end as usize - node_ptr as usize,
+                "free node {node_ptr:?} extends past the arena end"
+            );
+
+            let next_ptr = match node.next_ptr {
+                Some(ptr) => ptr,
+                None => return, // Reached the end of the list
+            };
+            if !self.sorted {
+                node_ptr = next_ptr;
+                continue;
+            }
+            let node_end = node_ptr.add(node.size);
+            assert!(
+                next_ptr > node_ptr,
+                "free nodes {node_ptr:?} and {next_ptr:?} aren't sorted by address"
+            );
+            assert!(
+                next_ptr >= node_end,
+                "free nodes {node_ptr:?} and {next_ptr:?} overlap"
+            );
+            assert!(
+                next_ptr != node_end,
+                "free nodes {node_ptr:?} and {next_ptr:?} are contiguous but weren't merged"
+            );
+
+            node_ptr = next_ptr;
+        }
+    }
+
+    /// Rebuild the free list from its valid Nodes, sorted by address, then merge contiguous or overlapping Nodes.
+    ///
+    /// Nothing is allocated, so that this can run inside a global allocator: Nodes are inserted one by one in the rebuilt list.
+    pub(crate) unsafe fn repair(&mut self) -> RepairReport {
+        let arena_start = self.arena_ptr.load(Ordering::Acquire) as *const u8;
+        let arena_end = arena_start.wrapping_add(self.arena_size);
+        let mut report = RepairReport::default();
+        self.recent_free = None;
+
+        let mut cursor = self
+            .free_root
+            .take()
+            .map(|root| root.load(Ordering::Acquire) as *const u8);
+        let mut last_inserted_ptr = arena_start;
+        while let Some(node_ptr) = cursor {
+            if node_ptr < arena_start
+                || node_ptr >= arena_end
+                || !(node_ptr as usize).is_multiple_of(NODE_LAYOUT_ALIGN)
+            {
+                // Can't be read: the rest of the list is lost
+                report.dropped_nodes += 1;
+                break;
+            }
+
+            let mut node = ptr::read(node_ptr as *const Node);
+            cursor = node.next_ptr;
+            let available = arena_end as usize - node_ptr as usize;
+            if node.size > available {
+                node.size = available;
+                report.truncated_nodes += 1;
+            }
+            if node.size < NODE_LAYOUT_SIZE {
+                report.dropped_nodes += 1;
+                continue;
+            }
+
+            // Find the insertion point in the rebuilt list, which is sorted
+            let mut previous_ptr = None;
+            let mut next_ptr = self
+                .free_root
+                .as_ref()
+                .map(|root| root.load(Ordering::Acquire) as *const u8);
+            while let Some(ptr) = next_ptr.filter(|ptr| *ptr < node_ptr) {
+                previous_ptr = Some(ptr);
+                next_ptr = ptr::read(ptr as *const Node).next_ptr;
+            }
+            if next_ptr == Some(node_ptr) {
+                // Already inserted: the list loops
+                report.dropped_nodes += 1;
+                break;
+            }
+            if node_ptr < last_inserted_ptr {
+                report.reordered_nodes += 1;
+            }
+            last_inserted_ptr = last_inserted_ptr.max(node_ptr);
+
+            node.next_ptr = next_ptr;
+            ptr::write(node_ptr as *mut Node, node);
+            match previous_ptr {
+                Some(previous_ptr) => {
+                    let mut previous = ptr::read(previous_ptr as *const Node);
+                    previous.next_ptr = Some(node_ptr);
+                    ptr::write(previous_ptr as *mut Node, previous);
+                }
+                None => self.free_root = Some(AtomicPtr::new(node_ptr.cast_mut())),
+            }
+        }
+
+        // Merge contiguous and overlapping Nodes
+        let mut node_ptr = self
+            .free_root
+            .as_ref()
+            .map(|root| root.load(Ordering::Acquire) as *const u8);
+        while let Some(ptr) = node_ptr {
+            let mut node = ptr::read(ptr as *const Node);
+            match node.next_ptr {
+                Some(next_ptr) if ptr.add(node.size) >= next_ptr => {
+                    let next = ptr::read(next_ptr as *const Node);
+                    let end = ptr.add(node.size).max(next_ptr.add(next.size));
+                    node.size = end as usize - ptr as usize;
+                    node.next_ptr = next.next_ptr;
+                    ptr::write(ptr as *mut Node, node);
+                    report.merged_nodes += 1;
+                }
+                next_ptr => node_ptr = next_ptr,
+            }
+        }
+        self.sync_allocated_bytes();
+        self.sorted = true;
+        report
+    }
+
+    /// Merge physically contiguous free Nodes.
+    ///
+    /// **Returns**: Count of merged Nodes
+    pub(crate) unsafe fn defragment(&mut self) -> usize {
+        if !self.sorted {
+            // Nodes left unsorted by deallocations without coalescing are merged while sorting them
+            return self.repair().merged_nodes;
+        }
+
+        let mut node_ptr = match &self.free_root {
+            Some(ptr) => ptr.load(Ordering::Acquire) as *const u8,
+            None => return 0,
+        };
+
+        // Nodes may be absorbed by their predecessor
+        self.recent_free = None;
+
+        let mut merged = 0;
+        loop {
+            let mut node = ptr::read(node_ptr as *const Node);
+            let next_ptr = match node.next_ptr {
+                Some(ptr) => ptr,
+                None => return merged, // Reached the end of the list
+            };
+
+            if node_ptr.add(node.size) == next_ptr {
+                // Absorb the next node, then try to merge the following one too
+                let next = ptr::read(next_ptr as *const Node);
+                node.size += next.size;
+                node.next_ptr = next.next_ptr;
+                ptr::write(node_ptr as *mut Node, node);
+                merged += 1;
+            } else {
+                node_ptr = next_ptr;
+            }
+        }
+    }
+
+    /// Touch every page holding free Nodes, so that the system commits them up front.
+    ///
+    /// Pages are read and written back with the same value. Allocated blocks aren't touched, as they may be
+    /// written concurrently by their owner: their pages were already committed by their allocation anyway.
+    pub(crate) unsafe fn prefault(&self) {
+        for (node_ptr, node) in self.free_nodes() {
+            let mut page = node_ptr.cast_mut();
+            let node_end = node_ptr.add(node.size);
+            while page < node_end.cast_mut() {
+                ptr::write_volatile(page, ptr::read_volatile(page));
+                // Next page start
+                page = page.add(PAGE_SIZE - page as usize % PAGE_SIZE);
+            }
+        }
+    }
+
+    /// Check if the given pointer lies inside the arena.
+    pub(crate) fn contains(&self, ptr: *const u8) -> bool {
+        let arena_start = self.arena_ptr.load(Ordering::Acquire) as usize;
+        arena_start != 0 && (arena_start..arena_start + self.arena_size).contains(&(ptr as usize))
+    }
+
+    /// Check if the given address lies inside the arena, outside of every free Node.
+    pub(crate) unsafe fn is_allocated(&self, address: usize) -> bool {
+        self.contains(address as *const u8)
+            && self.free_nodes().all(|(node_ptr, node)| {
+                !(node_ptr as usize..node_ptr as usize + node.size).contains(&address)
+            })
+    }
+
+    /// Get a free Node spanning the arena released by `trim`, if it is released: the next allocation allocates
+    /// a new arena, with such a Node. Queries count it along with the free list, which is empty meanwhile.
+    fn released_node(&self) -> Option<Node> {
+        self.released_size.map(|size| Node {
+            size,
+            next_ptr: None,
+        })
+    }
+
+    /// Count the free Nodes, including the one standing for a released arena (see `released_node`).
+    pub(crate) unsafe fn free_node_count(&self) -> usize {
+        self.free_nodes().count() + self.released_node().iter().count()
+    }
+
+    /// Iterate over the free list Nodes, starting from the root.
+    pub(crate) unsafe fn free_nodes(&self) -> FreeNodes {
+        FreeNodes {
+            cursor: self
+                .free_root
+                .as_ref()
+                .map(|ptr| ptr.load(Ordering::Acquire) as *const u8),
+        }
+    }
+
+    /// Copy the whole arena into `snapshot`, followed by the free root offset from the arena start
+    /// (`usize::MAX` if there is no free root) and the live bytes count. Nothing is allocated.
+    ///
+    /// An arena released by `trim` is allocated again first, so that the snapshot can be restored.
+    ///
+    /// **Panics**: if the snapshot size doesn't match the arena size
+    pub(crate) unsafe fn snapshot(&mut self, snapshot: &mut [u8]) {
+        self.reallocate_released();
+        assert_eq!(
+            self.arena_size + SNAPSHOT_TRAILER_SIZE,
+            snapshot.len(),
+            "Snapshot size doesn't match the arena size"
+        );
+
+        let arena_ptr = self.arena_ptr.load(Ordering::Acquire);
+        let root_offset = match &self.free_root {
+            Some(ptr) => ptr.load(Ordering::Acquire) as usize - arena_ptr as usize,
+            None => usize::MAX,
+        };
+
+        let (arena, trailer) = snapshot.split_at_mut(self.arena_size);
+        let (root_offset_bytes, live_bytes) = trailer.split_at_mut(size_of::<usize>());
+        if !arena_ptr.is_null() {
+            arena.copy_from_slice(slice::from_raw_parts(arena_ptr, self.arena_size));
+        }
+        root_offset_bytes.copy_from_slice(&root_offset.to_ne_bytes());
+        live_bytes.copy_from_slice(&self.live_bytes.to_ne_bytes());
+    }
+
+    /// Copy a snapshot taken by `snapshot` back into the arena and rebuild the free root.
+    /// An arena released by `trim` is allocated again first.
+    ///
+    /// **Panics**: if the snapshot size doesn't match the arena size
+    pub(crate) unsafe fn restore(&mut self, snapshot: &[u8]) {
+        self.reallocate_released();
+        assert_eq!(
+            self.arena_size + SNAPSHOT_TRAILER_SIZE,
+            snapshot.len(),
+            "Snapshot size doesn't match the arena size"
+        );
+
+        let arena_ptr = self.arena_ptr.load(Ordering::Acquire);
+        let (arena, trailer) = snapshot.split_at(self.arena_size);
+        let (root_offset, live_bytes) = trailer.split_at(size_of::<usize>());
+        if !arena_ptr.is_null() {
+            ptr::copy_nonoverlapping(arena.as_ptr(), arena_ptr, self.arena_size);
+        }
+
+        let root_offset = usize::from_ne_bytes(root_offset.try_into().unwrap());
+        self.free_root = if root_offset == usize::MAX {
+            None
+        } else {
+            Some(AtomicPtr::new(arena_ptr.add(root_offset)))
+        };
+        self.live_bytes = usize::from_ne_bytes(live_bytes.try_into().unwrap());
+        self.recent_free = None;
+        // The snapshot may have been taken while deallocations without coalescing left the free list unsorted
+        self.sorted = false;
+        self.sync_allocated_bytes();
+    }
+
+    /// Capture the free blocks into `free_blocks`, so that the allocations made afterwards can be released by `rewind`.
+    /// Nothing is allocated if `free_blocks` has room for every free Node.
+    /// An arena released by `trim` is allocated again first, so that the checkpoint can be rewound to.
+    ///
+    /// **Returns**: the arena start address, and the live bytes count
+    pub(crate) unsafe fn checkpoint(&mut self, free_blocks: &mut Vec<FreeBlock>) -> (usize, usize) {
+        self.reallocate_released();
+        self.free_blocks(free_blocks);
+        (
+            self.arena_ptr.load(Ordering::Acquire) as usize,
+            self.live_bytes,
+        )
+    }
+
+    /// Rebuild the free list from the blocks free at the checkpoint and the blocks currently free, merging the
+    /// overlapping and contiguous ones: allocations made since the checkpoint are released, while allocations
+    /// older than it stay released if they were deallocated in between.
+    ///
+    /// The live bytes count can't be split between both kinds of allocations: it is restored from the checkpoint,
+    /// and only exact if no older allocation was deallocated in between.
+    ///
+    /// Nothing is allocated: a scratch buffer allocated from this arena would be an allocation made since
+    /// the checkpoint, released while still in use. Instead, the free list is merged in place with the checkpoint blocks.
+    ///
+    /// **Panics**: if the checkpoint was taken from another arena
+    pub(crate) unsafe fn rewind(&mut self, checkpoint: &FreeListCheckpoint) {
+        self.reallocate_released();
+        let arena_ptr = self.arena_ptr.load(Ordering::Acquire);
+        assert_eq!(
+            arena_ptr as usize, checkpoint.arena_address,
+            "Checkpoint taken from another arena"
+        );
+
+        // Both block sequences are merged in offset order
+        self.sort();
+        let mut current = self
+            .free_nodes()
+            .map(|(node_ptr, node)| FreeBlock {
+                offset: node_ptr as usize - arena_ptr as usize,
+                size: node.size,
+            })
+            .peekable();
+        let mut previous = checkpoint.free_blocks.iter().copied().peekable();
+        self.free_root = None;
+
+        // A merged block is written once the next block starts after its end: the Nodes still to be read
+        // lie after it, so they aren't overwritten
+        let mut merged: Option<FreeBlock> = None;
+        let mut last_ptr: Option<*mut u8> = None;
+        loop {
+            let block = match (current.peek(), previous.peek()) {
+                (Some(a), Some(b)) if b.offset < a.offset => previous.next(),
+                (Some(_), _) => current.next(),
+                (None, _) => previous.next(),
+            };
+            if let (Some(merged), Some(block)) = (merged.as_mut(), block) {
+                let merged_end = merged.offset + merged.size;
+                if block.offset <= merged_end {
+                    merged.size = merged_end.max(block.offset + block.size) - merged.offset;
+                    continue;
+                }
+            }
+
+            if let Some(merged) = merged {
+                let node_ptr = arena_ptr.add(merged.offset);
+                ptr::write(
+                    node_ptr as *mut Node,
+                    Node {
+                        size: merged.size,
+                        next_ptr: None,
+                    },
+                );
+                match last_ptr {
+                    Some(last_ptr) => {
+                        let mut last = ptr::read(last_ptr as *const Node);
+                        last.next_ptr = Some(node_ptr as *const u8);
+                        ptr::write(last_ptr as *mut Node, last);
+                    }
+                    None => self.free_root = Some(AtomicPtr::new(node_ptr)),
+                }
+                last_ptr = Some(node_ptr);
+            }
+            match block {
+                Some(block) => merged = Some(block),
+                None => break,
+            }
+        }
+        self.sorted = true;
+        self.recent_free = None;
+        self.sync_allocated_bytes();
+        self.live_bytes = self
+            .live_bytes
+            .min(checkpoint.live_bytes)
+            .min(self.allocated_bytes);
+    }
+
+    /// Count the arena bytes used by allocations, including their paddings and metadata.
+    pub(crate) unsafe fn used_bytes(&self) -> usize {
+        self.arena_size - self.free_bytes()
+    }
+
+    /// Recompute the allocated bytes count from the free list, after it was rebuilt or replaced.
+    unsafe fn sync_allocated_bytes(&mut self) {
+        self.allocated_bytes = self.used_bytes();
+        self.peak_allocated_bytes = self.peak_allocated_bytes.max(self.allocated_bytes);
+    }
+
+    /// Fill the arena usage fields of the given stats, in a single free list walk.
+    pub(crate) unsafe fn arena_stats(&self, stats: &mut FreeListStats) {
+        let (free_bytes, free_node_count, largest_free_block) = self
+            .free_nodes()
+            .map(|(_, node)| node)
+            .chain(self.released_node())
+            .fold((0, 0, 0), |(free_bytes, count, largest), node| {
+                (free_bytes + node.size, count + 1, largest.max(node.size))
+            });
+        stats.free_bytes = free_bytes;
+        stats.used_bytes = self.arena_size - free_bytes;
+        stats.largest_free_block = largest_free_block;
+        stats.free_node_count = free_node_count;
+        stats.peak_used = self.peak_allocated_bytes;
+    }
+
+    /// Count the arena bytes held by free Nodes, including the whole arena if it is released by `trim`.
+    pub(crate) unsafe fn free_bytes(&self) -> usize {
+        self.free_nodes()
+            .map(|(_, node)| node)
+            .chain(self.released_node())
+            .map(|node| node.size)
+            .sum()
+    }
+
+    /// Count the arena bytes used by allocation paddings and metadata.
+    ///
+    /// Blocks tile the arena, so every byte outside of free Nodes belongs to an allocation: its value, its paddings or its metadata.
+    pub(crate) unsafe fn internal_fragmentation_bytes(&self) -> usize {
+        self.used_bytes() - self.live_bytes
+    }
+
+    /// Count the free bytes that aren't part of the largest free Node.
+    pub(crate) unsafe fn external_fragmentation_bytes(&self) -> usize {
+        let (free_bytes, largest) = self
+            .free_nodes()
+            .fold((0, 0), |(free_bytes, largest), (_, node)| {
+                (free_bytes + node.size, largest.max(node.size))
+            });
+        free_bytes - largest
+    }
+
+    /// Walk the arena blocks, pushing the offset, size and alignment of each allocated value into `live`.
+    ///
+    /// Blocks tile the arena: each block either is a free Node, or starts with a `LiveHeader`.
+    /// This is only valid if every allocation was made with live tracking enabled.
+    /// Nothing is allocated if `live` has room for every allocation (see `count_live_allocations`).
+    pub(crate) unsafe fn live_allocations(
+        &self,
+        placement: MetadataPlacement,
+        live: &mut Vec<AllocInfo>,
+    ) {
+        self.walk_live_allocations(placement, |alloc, _| live.push(alloc));
+    }
+
+    /// Count the allocated values, walking the arena blocks (see `live_allocations`).
+    pub(crate) unsafe fn count_live_allocations(&self, placement: MetadataPlacement) -> usize {
+        let mut count = 0;
+        self.walk_live_allocations(placement, |_, _| count += 1);
+        count
+    }
+
+    /// Walk the arena blocks, pushing each allocated value along with its sequence number and age into `aged`
+    /// (see `live_allocations`).
+    #[cfg(feature = "alloc_age")]
+    pub(crate) unsafe fn aged_allocations(
+        &self,
+        placement: MetadataPlacement,
+        aged: &mut Vec<AgedAllocInfo>,
+    ) {
+        self.walk_live_allocations(placement, |alloc, header| {
+            aged.push(AgedAllocInfo {
+                alloc,
+                sequence: header.sequence,
+                age: self.next_sequence - header.sequence - 1,
+            })
+        });
+    }
+
+    /// Call `visit` with each allocated value and its header, in offset order (see `live_allocations`).
+    ///
+    /// Nothing is allocated, so that this can run with the lock held.
+    unsafe fn walk_live_allocations(
+        &self,
+        placement: MetadataPlacement,
+        mut visit: impl FnMut(AllocInfo, &LiveHeader),
+    ) {
+        let arena_start = self.arena_ptr.load(Ordering::Acquire) as *const u8;
+        if arena_start.is_null() {
+            return; // Nothing is allocated without an arena
+        }
+        let arena_end = arena_start.add(self.arena_size);
+        let mut free_nodes = self.free_nodes().peekable();
+
+        let mut cursor = arena_start;
+        while cursor < arena_end {
+            let free_node = if self.sorted {
+                free_nodes.next_if(|(node_ptr, _)| *node_ptr == cursor)
+            } else {
+                // Deallocations without coalescing left the free list unsorted: search it for each block
+                self.free_nodes().find(|(node_ptr, _)| *node_ptr == cursor)
+            };
+            if let Some((_, node)) = free_node {
+                cursor = cursor.add(node.size);
+                continue;
+            }
+
+            let header = ptr::read(cursor as *const LiveHeader);
+            let value_ptr = cursor.add(header.value_offset);
+            let metadata = AllocationMetadata::read(
+                placement.metadata_ptr(value_ptr.cast_mut(), header.value_size),
+            );
+            visit(
+                AllocInfo {
+                    offset: value_ptr as usize - arena_start as usize,
+                    size: header.value_size,
+                    align: header.value_align,
+                },
+                &header,
+            );
+            cursor = value_ptr.add(
+                header.value_size + placement.trailing_size() + metadata.fill_padding as usize,
+            );
+        }
+    }
+
+    /// Push the free blocks of the arena into `blocks`, sorted by offset. A released arena is a single free block.
+    ///
+    /// Nothing is allocated if `blocks` has room for every free Node (see `free_node_count`),
+    /// so that this can run with the lock held.
+    pub(crate) unsafe fn free_blocks(&self, blocks: &mut Vec<FreeBlock>) {
+        let arena_start = self.arena_ptr.load(Ordering::Acquire) as usize;
+        let first = blocks.len();
+        blocks.extend(self.free_nodes().map(|(ptr, node)| FreeBlock {
+            offset: ptr as usize - arena_start,
+            size: node.size,
+        }));
+        blocks.extend(self.released_node().map(|node| FreeBlock {
+            offset: 0,
+            size: node.size,
+        }));
+        if !self.sorted {
+            blocks[first..].sort_unstable_by_key(|block| block.offset);
+        }
+    }
+}
+
+/// Iterator over the free list Nodes, yielding each Node along with its location.
+pub(crate) struct FreeNodes {
+    cursor: Option<*const u8>,
+}
+
+impl Iterator for FreeNodes {
+    type Item = (*const u8, Node);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let ptr = self.cursor?;
+        let node = unsafe { ptr::read(ptr as *const Node) };
+        self.cursor = node.next_ptr;
+        Some((ptr, node))
+    }
 }
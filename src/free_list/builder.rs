@@ -0,0 +1,130 @@
+use std::alloc::Layout;
+
+use super::{
+    config::{FailurePolicy, FitStrategy, FreeListConfig, MetadataPlacement},
+    dynamic::DynFreeListAllocator,
+    event::AllocEvent,
+    FreeListAllocator,
+};
+
+/// Builder collecting the options of a free list allocator.
+///
+/// Every method is `const`, so it can be used to initialize a global allocator.
+///
+/// ## Usage
+/// ```
+/// use allocator::free_list::{FitStrategy, FreeListAllocator, FreeListAllocatorBuilder};
+///
+/// #[global_allocator]
+/// static ALLOCATOR: FreeListAllocator<4096> = FreeListAllocatorBuilder::new()
+///     .fit_strategy(FitStrategy::BestFit)
+///     .min_block_size(64)
+///     .build();
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct FreeListAllocatorBuilder {
+    config: FreeListConfig,
+}
+
+impl FreeListAllocatorBuilder {
+    /// Create a builder using the default configuration.
+    pub const fn new() -> Self {
+        FreeListAllocatorBuilder {
+            config: FreeListConfig::new(),
+        }
+    }
+
+    /// Set the minimum size of a free Node split off after an allocation (see `FreeListConfig::min_block_size`).
+    pub const fn min_block_size(mut self, min_block_size: usize) -> Self {
+        self.config.min_block_size = min_block_size;
+        self
+    }
+
+    /// Set the strategy used to choose the free Node of an allocation (see `FreeListConfig::fit_strategy`).
+    pub const fn fit_strategy(mut self, fit_strategy: FitStrategy) -> Self {
+        self.config.fit_strategy = fit_strategy;
+        self
+    }
+
+    /// Serve allocations from the `System` allocator when the arena is exhausted (see `FreeListConfig::system_fallback`).
+    pub const fn system_fallback(mut self, system_fallback: bool) -> Self {
+        self.config.system_fallback = system_fallback;
+        self
+    }
+
+    /// Count allocations by size class (see `FreeListConfig::size_histogram`).
+    pub const fn size_histogram(mut self, size_histogram: bool) -> Self {
+        self.config.size_histogram = size_histogram;
+        self
+    }
+
+    /// Set the function called on each allocation and deallocation (see `FreeListConfig::event_hook`).
+    pub const fn event_hook(mut self, event_hook: fn(AllocEvent)) -> Self {
+        self.config.event_hook = Some(event_hook);
+        self
+    }
+
+    /// Keep track of live allocations (see `FreeListConfig::live_tracking`).
+    pub const fn live_tracking(mut self, live_tracking: bool) -> Self {
+        self.config.live_tracking = live_tracking;
+        self
+    }
+
+    /// Try the most recently freed Node before scanning the free list (see `FreeListConfig::recent_free_cache`).
+    pub const fn recent_free_cache(mut self, recent_free_cache: bool) -> Self {
+        self.config.recent_free_cache = recent_free_cache;
+        self
+    }
+
+    /// Merge deallocated blocks with their neighbors right away (see `FreeListConfig::coalesce_on_free`).
+    pub const fn coalesce_on_free(mut self, coalesce_on_free: bool) -> Self {
+        self.config.coalesce_on_free = coalesce_on_free;
+        self
+    }
+
+    /// Place allocations aligned to at least `align` where they need the least padding
+    /// (see `FreeListConfig::least_padding_align`).
+    pub const fn least_padding_align(mut self, align: usize) -> Self {
+        self.config.least_padding_align = align;
+        self
+    }
+
+    /// Set the location of the allocation metadata (see `FreeListConfig::metadata_placement`).
+    pub const fn metadata_placement(mut self, placement: MetadataPlacement) -> Self {
+        self.config.metadata_placement = placement;
+        self
+    }
+
+    /// Set the outcome of failed allocations made through `allocate` (see `FreeListConfig::failure_policy`).
+    pub const fn failure_policy(mut self, policy: FailurePolicy) -> Self {
+        self.config.failure_policy = policy;
+        self
+    }
+
+    /// Set the function called when the arena can't hold an allocation (see `FreeListConfig::oom_handler`).
+    pub const fn oom_handler(mut self, oom_handler: fn(Layout) -> bool) -> Self {
+        self.config.oom_handler = Some(oom_handler);
+        self
+    }
+
+    /// Get the collected configuration.
+    pub const fn config(&self) -> FreeListConfig {
+        self.config
+    }
+
+    /// Create an allocator with an arena of `S` bytes.
+    pub const fn build<const S: usize>(self) -> FreeListAllocator<S> {
+        FreeListAllocator::with_config(self.config)
+    }
+
+    /// Create an allocator with an arena of `size` bytes, chosen at runtime.
+    pub fn build_dyn(self, size: usize) -> DynFreeListAllocator {
+        DynFreeListAllocator::with_config(size, self.config)
+    }
+}
+
+impl Default for FreeListAllocatorBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
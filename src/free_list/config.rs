@@ -0,0 +1,222 @@
+use std::alloc::Layout;
+
+use super::{
+    event::AllocEvent,
+    node::{
+        ALLOCATION_METADATA_LAYOUT_SIZE, LIVE_HEADER_LAYOUT_SIZE, NODE_LAYOUT_ALIGN,
+        NODE_LAYOUT_SIZE,
+    },
+};
+
+/// Tunable behavior of a free list allocator.
+///
+/// ## Usage
+/// ```
+/// use allocator::free_list::{FreeListAllocator, FreeListConfig};
+///
+/// #[global_allocator]
+/// static ALLOCATOR: FreeListAllocator<4096> = FreeListAllocator::with_config(FreeListConfig {
+///     min_block_size: 64,
+///     ..FreeListConfig::new()
+/// });
+/// ```
+// Event hooks and OOM handlers are compared by address, which is good enough to tell configurations apart
+#[allow(unpredictable_function_pointer_comparisons)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FreeListConfig {
+    /// Minimum size of a free Node split off after an allocation.
+    /// If fewer bytes would remain, they are given to the allocation as fill padding instead.
+    ///
+    /// Values lower than the size of a Node are raised to it.
+    pub min_block_size: usize,
+    /// When the arena is exhausted, serve allocations from the `System` allocator instead of returning null.
+    ///
+    /// Deallocated pointers lying outside of the arena are given back to the `System` allocator.
+    pub system_fallback: bool,
+    /// Strategy used to choose the free Node an allocation is placed in.
+    pub fit_strategy: FitStrategy,
+    /// Count allocations by power of two size class (see `FreeListAllocator::size_histogram`).
+    /// Nothing is recorded when disabled.
+    pub size_histogram: bool,
+    /// Function called after each allocation and deallocation, once the free list is updated.
+    /// Nothing is called when unset.
+    ///
+    /// The hook runs inside the allocator: it must not allocate through the same allocator,
+    /// as it would be called again recursively. This includes formatting strings, boxing values, etc.
+    pub event_hook: Option<fn(AllocEvent)>,
+    /// Keep track of live allocations, so that they can be listed (see `FreeListAllocator::report_live`).
+    ///
+    /// This adds a header of three `usize` at the start of each allocated block,
+    /// four with the `alloc_age` feature which records the allocation sequence number.
+    pub live_tracking: bool,
+    /// Remember the most recently freed Node and try it first on the next allocation, before scanning the free list.
+    /// This speeds up repeated allocations and deallocations of the same size.
+    ///
+    /// A cache hit bypasses the fit strategy: the allocation isn't necessarily placed at the lowest address or in the smallest Node.
+    pub recent_free_cache: bool,
+    /// Function called with the requested layout when no free Node can hold it, before giving up.
+    /// It returns `true` if memory was released (by evicting cached values for instance),
+    /// in which case the allocation is tried once more. Nothing is called when unset.
+    ///
+    /// The handler runs inside `alloc`, but without holding the allocator lock: it may deallocate through the same allocator.
+    /// It must not allocate through it, as a failed allocation would call the handler again recursively.
+    pub oom_handler: Option<fn(Layout) -> bool>,
+    /// Merge each deallocated block with its free neighbors, keeping the free list sorted by address.
+    /// This walks the free list, so the deallocation time depends on the count of free blocks.
+    ///
+    /// When disabled, deallocated blocks are prepended to the free list in constant time, without merging them:
+    /// the arena fragments until `FreeListAllocator::defragment` is called, which sorts and merges the free list.
+    /// An allocation finding no large enough free block defragments as well, then searches the free list once more.
+    /// First-fit allocations aren't placed at the lowest address anymore, as the list isn't sorted.
+    pub coalesce_on_free: bool,
+    /// Alignment from which allocations are placed in the free Node needing the least alignment padding,
+    /// instead of following `fit_strategy`. The whole free list is scanned, unless a Node is already aligned.
+    ///
+    /// Padding is lost until the value is deallocated: for page-aligned buffers (`4096`), a poorly aligned Node
+    /// may waste almost a page. Disabled by default (`usize::MAX`).
+    pub least_padding_align: usize,
+    /// Location of the allocation metadata, relative to the value.
+    ///
+    /// All the allocations of an arena must use the same placement: changing it requires the arena to be empty.
+    pub metadata_placement: MetadataPlacement,
+    /// Outcome of a failed allocation made through `FreeListAllocator::allocate`, once the OOM handler and the system
+    /// fallback couldn't serve it.
+    ///
+    /// `GlobalAlloc::alloc` ignores it and always returns null, as required by the trait.
+    pub failure_policy: FailurePolicy,
+}
+
+/// Strategy used to choose the free Node an allocation is placed in.
+///
+/// The free list is always sorted by address, whatever the strategy: this is what allows deallocated
+/// blocks to be merged with their neighbors in a single pass. Strategies only differ in how the list is scanned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FitStrategy {
+    /// Use the lowest-address free Node large enough. The scan stops at the first match.
+    FirstFit,
+    /// Use the smallest free Node large enough, keeping large Nodes available for large allocations.
+    /// The whole free list is scanned on each allocation. Among Nodes of the same size, the lowest-address one is used.
+    BestFit,
+    /// Use the free Node leaving the largest free Node after the allocation, which is the opposite of `BestFit`.
+    /// This deliberately fragments the arena, and is meant to exercise the merge paths of data structures in tests.
+    /// The whole free list is scanned on each allocation. Among equivalent Nodes, the lowest-address one is used.
+    WorstFit,
+}
+
+/// Outcome of an allocation the arena can't serve, see `FreeListConfig::failure_policy`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailurePolicy {
+    /// Return a null pointer, left to the caller to handle.
+    Null,
+    /// Panic with the requested layout and the arena usage, so that the failure can be diagnosed.
+    Panic,
+    /// Abort the process through `std::alloc::handle_alloc_error`, like a failed global allocation.
+    Abort,
+}
+
+/// Location of the metadata stored along each allocated value, needed to deallocate it.
+///
+/// Both placements take the same space. They only differ in which overrun reaches the metadata first:
+/// writes past the end of a value corrupt the metadata placed after it, and deallocating the value
+/// then corrupts the free list. Debug builds detect such corruption on deallocation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetadataPlacement {
+    /// Store the metadata right after the value, in the bytes a forward overrun writes first.
+    /// Allocations may need less alignment padding, as the value can start at the block start.
+    AfterValue,
+    /// Store the metadata right before the value, out of the way of forward overruns,
+    /// which hit the fill padding or the next block instead.
+    BeforeValue,
+}
+
+impl MetadataPlacement {
+    /// Size of the metadata placed before the value, 0 if it is placed after it.
+    pub(crate) const fn leading_size(self) -> usize {
+        match self {
+            MetadataPlacement::AfterValue => 0,
+            MetadataPlacement::BeforeValue => ALLOCATION_METADATA_LAYOUT_SIZE,
+        }
+    }
+
+    /// Size of the metadata placed after the value, 0 if it is placed before it.
+    pub(crate) const fn trailing_size(self) -> usize {
+        ALLOCATION_METADATA_LAYOUT_SIZE - self.leading_size()
+    }
+
+    /// Get the location of the metadata of the value of `size` bytes pointed by `ptr`.
+    pub(crate) unsafe fn metadata_ptr(self, ptr: *mut u8, size: usize) -> *mut u8 {
+        match self {
+            MetadataPlacement::AfterValue => ptr.add(size),
+            MetadataPlacement::BeforeValue => ptr.sub(ALLOCATION_METADATA_LAYOUT_SIZE),
+        }
+    }
+}
+
+impl FreeListConfig {
+    /// Create the default configuration.
+    pub const fn new() -> Self {
+        FreeListConfig {
+            min_block_size: NODE_LAYOUT_SIZE,
+            system_fallback: false,
+            fit_strategy: FitStrategy::FirstFit,
+            size_histogram: false,
+            event_hook: None,
+            live_tracking: false,
+            recent_free_cache: false,
+            oom_handler: None,
+            coalesce_on_free: true,
+            least_padding_align: usize::MAX,
+            metadata_placement: MetadataPlacement::AfterValue,
+            failure_policy: FailurePolicy::Null,
+        }
+    }
+
+    /// Get the count of bytes added to each allocated value with this configuration: its metadata,
+    /// and its header if live allocations are tracked.
+    ///
+    /// An allocated block takes at least `size + allocation_overhead()` bytes (plus alignment padding),
+    /// raised to `NODE_SIZE` and rounded up to a multiple of `NODE_ALIGN`.
+    pub const fn allocation_overhead(&self) -> usize {
+        ALLOCATION_METADATA_LAYOUT_SIZE + self.header_size()
+    }
+
+    /// Get an arena size able to hold `max_allocations` values of `data_bytes` bytes in total with this configuration,
+    /// whatever the sizes of the individual values.
+    ///
+    /// Each allocation is counted with its overhead (see `allocation_overhead`), plus the bytes an allocated block
+    /// may take on top of it: raising it to the minimum block size and rounding it up to a multiple of `NODE_ALIGN`.
+    /// This is a worst case: the arena is usually larger than needed.
+    ///
+    /// Values aligned to more than `NODE_ALIGN` need additional padding, and fragmentation may prevent allocations
+    /// from fitting once values are deallocated: neither is accounted for.
+    pub const fn arena_size_for(&self, data_bytes: usize, max_allocations: usize) -> usize {
+        let block_size = if self.min_block_size > NODE_LAYOUT_SIZE {
+            self.min_block_size
+        } else {
+            NODE_LAYOUT_SIZE
+        };
+        let per_allocation = self.allocation_overhead() + block_size + NODE_LAYOUT_ALIGN - 1;
+        // The arena must at least hold a free Node
+        let size = data_bytes.saturating_add(max_allocations.saturating_mul(per_allocation));
+        if size > NODE_LAYOUT_SIZE {
+            size
+        } else {
+            NODE_LAYOUT_SIZE
+        }
+    }
+
+    /// Size of the header placed at the start of each allocated block.
+    pub(crate) const fn header_size(&self) -> usize {
+        if self.live_tracking {
+            LIVE_HEADER_LAYOUT_SIZE
+        } else {
+            0
+        }
+    }
+}
+
+impl Default for FreeListConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,308 @@
+#[cfg(feature = "alloc_age")]
+use super::inspect::AgedAllocInfo;
+use super::{
+    alloc_root::AllocatorRoot,
+    arena_layout,
+    heap::FreeListHeap,
+    inspect::{AllocInfo, FreeBlock, FreeListCheckpoint, RepairReport},
+    node::NODE_LAYOUT_SIZE,
+    stats::{AllocationCounters, FreeListStats, SIZE_CLASSES},
+    FreeListConfig,
+};
+use crate::region::RegionAllocator;
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    ptr::null_mut,
+    sync::{atomic::Ordering, Mutex},
+};
+
+/// Free list allocator whose arena size is chosen at runtime.
+///
+/// It behaves like `FreeListAllocator`, but the arena is allocated when creating the allocator,
+/// and given back to the system when it is dropped.
+///
+/// ## Usage
+/// ```
+/// use allocator::free_list::DynFreeListAllocator;
+/// use std::alloc::{GlobalAlloc, Layout};
+///
+/// let allocator = DynFreeListAllocator::new(4096);
+/// let layout = Layout::new::<u64>();
+///
+/// let ptr = unsafe { allocator.alloc(layout) };
+/// unsafe { allocator.dealloc(ptr, layout) };
+/// ```
+pub struct DynFreeListAllocator {
+    allocator: Mutex<AllocatorRoot>,
+    config: FreeListConfig,
+    counters: AllocationCounters,
+}
+
+impl DynFreeListAllocator {
+    /// Create an allocator with an arena of `size` bytes.
+    ///
+    /// If the arena can't be allocated, or is too small to hold a free Node, every allocation returns null.
+    pub fn new(size: usize) -> Self {
+        Self::with_config(size, FreeListConfig::new())
+    }
+
+    /// Create an allocator with an arena of `size` bytes, using the given configuration.
+    pub fn with_config(size: usize, config: FreeListConfig) -> Self {
+        let arena_ptr = match arena_layout(size) {
+            Some(layout) if size >= NODE_LAYOUT_SIZE => unsafe {
+                GlobalAlloc::alloc(&System, layout)
+            },
+            _ => null_mut(),
+        };
+
+        DynFreeListAllocator {
+            allocator: Mutex::new(unsafe { AllocatorRoot::new(arena_ptr, size) }),
+            config,
+            counters: AllocationCounters::new(),
+        }
+    }
+
+    /// Create an allocator with an arena able to hold `max_allocations` values of `data_bytes` bytes in total,
+    /// their metadata included (see `FreeListConfig::arena_size_for`).
+    ///
+    /// The chosen arena size is returned by `size`, so that it can be logged.
+    pub fn sized_for(data_bytes: usize, max_allocations: usize) -> Self {
+        let config = FreeListConfig::new();
+        Self::with_config(config.arena_size_for(data_bytes, max_allocations), config)
+    }
+
+    /// Get the arena size, in bytes. It is 0 if the arena couldn't be allocated.
+    pub fn size(&self) -> usize {
+        self.allocator.lock().unwrap().arena_size
+    }
+
+    /// Get the count of arena bytes currently in use (see `FreeListAllocator::len`).
+    pub fn len(&self) -> usize {
+        unsafe { self.allocator.lock().unwrap().used_bytes() }
+    }
+
+    /// Check if no arena byte is currently in use (see `len`).
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get the count of free arena bytes (see `FreeListAllocator::free_bytes`).
+    pub fn free_bytes(&self) -> usize {
+        unsafe { self.allocator.lock().unwrap().free_bytes() }
+    }
+
+    /// Get the count of free blocks (see `FreeListAllocator::free_node_count`).
+    pub fn free_node_count(&self) -> usize {
+        unsafe { self.allocator.lock().unwrap().free_node_count() }
+    }
+
+    /// Get a snapshot of the allocation activity counters and of the arena usage (see `FreeListAllocator::stats`).
+    pub fn stats(&self) -> FreeListStats {
+        self.heap().stats()
+    }
+
+    /// Get the count of allocation requests per size class (see `FreeListAllocator::size_histogram`).
+    pub fn size_histogram(&self) -> [usize; SIZE_CLASSES] {
+        self.counters.size_histogram()
+    }
+
+    /// Get the count of allocator operations which had to wait for the allocator lock
+    /// (see `FreeListAllocator::lock_contention_count`).
+    #[cfg(feature = "lock_contention")]
+    pub fn lock_contention_count(&self) -> usize {
+        self.counters.contended_locks()
+    }
+
+    /// Allocate the given layout, applying `FreeListConfig::failure_policy` if it can't be served
+    /// (see `FreeListAllocator::allocate`).
+    ///
+    /// **Panics**: if the allocation fails with `FailurePolicy::Panic`
+    pub fn allocate(&self, layout: Layout) -> *mut u8 {
+        unsafe { self.heap().allocate(layout) }
+    }
+
+    /// Check if the given layout can currently be allocated in the arena (see `FreeListAllocator::can_allocate`).
+    pub fn can_allocate(&self, layout: Layout) -> bool {
+        layout.size() == 0
+            || unsafe {
+                self.allocator
+                    .lock()
+                    .unwrap()
+                    .can_allocate(layout, &self.config)
+            }
+    }
+
+    /// Count the arena bytes lost inside allocations (see `FreeListAllocator::internal_fragmentation_bytes`).
+    pub fn internal_fragmentation_bytes(&self) -> usize {
+        unsafe {
+            self.allocator
+                .lock()
+                .unwrap()
+                .internal_fragmentation_bytes()
+        }
+    }
+
+    /// Count the free bytes that aren't part of the largest free block
+    /// (see `FreeListAllocator::external_fragmentation_bytes`).
+    pub fn external_fragmentation_bytes(&self) -> usize {
+        unsafe {
+            self.allocator
+                .lock()
+                .unwrap()
+                .external_fragmentation_bytes()
+        }
+    }
+
+    /// List the allocations that weren't deallocated yet (see `FreeListAllocator::report_live`).
+    ///
+    /// **Panics**: if `FreeListConfig::live_tracking` isn't enabled
+    pub fn report_live(&self) -> Vec<(usize, usize)> {
+        self.heap_dump()
+            .into_iter()
+            .map(|alloc| (alloc.offset, alloc.size))
+            .collect()
+    }
+
+    /// List the allocations that weren't deallocated yet, with their alignment (see `FreeListAllocator::heap_dump`).
+    ///
+    /// **Panics**: if `FreeListConfig::live_tracking` isn't enabled
+    pub fn heap_dump(&self) -> Vec<AllocInfo> {
+        assert!(self.config.live_tracking, "live allocations aren't tracked");
+        self.heap().live_allocations()
+    }
+
+    /// Get the oldest allocation that wasn't deallocated yet (see `FreeListAllocator::oldest_live_allocation`).
+    ///
+    /// **Panics**: if `FreeListConfig::live_tracking` isn't enabled
+    #[cfg(feature = "alloc_age")]
+    pub fn oldest_live_allocation(&self) -> Option<AgedAllocInfo> {
+        self.aged_allocations()
+            .into_iter()
+            .min_by_key(|aged| aged.sequence)
+    }
+
+    /// List the allocations that weren't deallocated yet although more than `age` allocations were made after them
+    /// (see `FreeListAllocator::live_allocations_older_than`).
+    ///
+    /// **Panics**: if `FreeListConfig::live_tracking` isn't enabled
+    #[cfg(feature = "alloc_age")]
+    pub fn live_allocations_older_than(&self, age: usize) -> Vec<AgedAllocInfo> {
+        let mut aged = self.aged_allocations();
+        aged.retain(|aged| aged.age > age);
+        aged
+    }
+
+    #[cfg(feature = "alloc_age")]
+    fn aged_allocations(&self) -> Vec<AgedAllocInfo> {
+        assert!(self.config.live_tracking, "live allocations aren't tracked");
+        self.heap().aged_allocations()
+    }
+
+    /// Get the offset of the given pointer from the arena start, None if it lies outside of the arena
+    /// (see `FreeListAllocator::offset_of`).
+    pub fn offset_of(&self, ptr: *const u8) -> Option<usize> {
+        let root = self.allocator.lock().unwrap();
+        root.contains(ptr).then(|| root.offset_of(ptr))
+    }
+
+    /// Check if the given pointer lies inside an allocated part of the arena (see `FreeListAllocator::is_allocated`).
+    pub fn is_allocated(&self, ptr: *const u8) -> bool {
+        unsafe { self.allocator.lock().unwrap().is_allocated(ptr as usize) }
+    }
+
+    /// Iterate over the free blocks of the arena, sorted by offset (see `FreeListAllocator::free_blocks`).
+    pub fn free_blocks(&self) -> impl Iterator<Item = FreeBlock> {
+        self.heap().free_blocks().into_iter()
+    }
+
+    /// Capture the free list state, to release the allocations made afterwards with `rewind`
+    /// (see `FreeListAllocator::checkpoint`).
+    pub fn checkpoint(&self) -> FreeListCheckpoint {
+        self.heap().checkpoint()
+    }
+
+    /// Release every allocation made since the given checkpoint was taken (see `FreeListAllocator::rewind`).
+    ///
+    /// **Panics**: if the checkpoint was taken from another allocator
+    ///
+    /// # Safety
+    /// Every allocation made since the checkpoint is invalidated, and must not be used nor deallocated afterwards.
+    pub unsafe fn rewind(&self, checkpoint: &FreeListCheckpoint) {
+        self.heap().rewind(checkpoint);
+    }
+
+    /// Deallocate the given allocations at once (see `FreeListAllocator::dealloc_batch`).
+    ///
+    /// # Safety
+    /// Each pair must be valid for `GlobalAlloc::dealloc`, and no pointer may appear twice.
+    pub unsafe fn dealloc_batch(&self, allocations: &mut [(*mut u8, Layout)]) {
+        self.heap().dealloc_batch(allocations);
+    }
+
+    /// Walk the free list and fix the inconsistencies it can handle (see `FreeListAllocator::repair`).
+    ///
+    /// # Safety
+    /// The free list locations and sizes are trusted to lie outside of live allocations.
+    pub unsafe fn repair(&self) -> RepairReport {
+        self.allocator.lock().unwrap().repair()
+    }
+
+    /// Touch every free page of the arena, so that the system commits them up front (see `FreeListAllocator::prefault`).
+    pub fn prefault(&self) {
+        self.heap().prefault()
+    }
+
+    /// Merge the contiguous free blocks of the arena (see `FreeListAllocator::defragment`).
+    pub fn defragment(&self) -> usize {
+        self.heap().defragment()
+    }
+
+    /// Check that the free list is well-formed, panicking otherwise. This is a no-op in release builds.
+    pub fn debug_assert_valid(&self) {
+        if cfg!(debug_assertions) {
+            unsafe { self.allocator.lock().unwrap().assert_valid() };
+        }
+    }
+
+    fn heap(&self) -> FreeListHeap<'_> {
+        FreeListHeap {
+            root: &self.allocator,
+            spill: &[],
+            config: &self.config,
+            counters: &self.counters,
+        }
+    }
+}
+
+unsafe impl GlobalAlloc for DynFreeListAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.heap().alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.heap().dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        self.heap().realloc(ptr, layout, new_size)
+    }
+}
+
+impl RegionAllocator for DynFreeListAllocator {
+    /// Release every allocation of the arena at once (see `FreeListAllocator::reset`).
+    unsafe fn reset(&self) {
+        self.heap().reset()
+    }
+}
+
+impl Drop for DynFreeListAllocator {
+    fn drop(&mut self) {
+        let root = self.allocator.get_mut().unwrap();
+        let arena_ptr = root.arena_ptr.load(Ordering::Acquire);
+        if arena_ptr.is_null() {
+            return;
+        }
+
+        unsafe { GlobalAlloc::dealloc(&System, arena_ptr, arena_layout(root.arena_size).unwrap()) };
+    }
+}
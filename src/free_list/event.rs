@@ -0,0 +1,10 @@
+use std::alloc::Layout;
+
+/// Allocation activity reported to the event hook of a free list allocator (see `FreeListConfig::event_hook`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AllocEvent {
+    /// An allocation was requested. The pointer is null if the allocation failed.
+    Alloc { ptr: *mut u8, layout: Layout },
+    /// An allocation was released.
+    Dealloc { ptr: *mut u8, layout: Layout },
+}
@@ -0,0 +1,361 @@
+use super::{
+    alloc_root::AllocatorRoot,
+    config::{FailurePolicy, FreeListConfig},
+    event::AllocEvent,
+    inspect::{AllocInfo, FreeBlock, FreeListCheckpoint},
+    stats::{AllocationCounters, FreeListStats},
+};
+use std::{
+    alloc::{handle_alloc_error, GlobalAlloc, Layout, System},
+    cell::RefCell,
+    ops::DerefMut,
+    ptr,
+    sync::Mutex,
+};
+
+#[cfg(feature = "alloc_age")]
+use super::inspect::AgedAllocInfo;
+#[cfg(feature = "lock_contention")]
+use std::sync::TryLockError;
+
+/// Allocation logic shared by the free list allocators, whatever the way their arena is created.
+pub(crate) struct FreeListHeap<'a, R: RootCell = Mutex<AllocatorRoot>> {
+    pub root: &'a R,
+    /// Other arenas tried in order when `root` can't hold an allocation, empty for single arena allocators
+    pub spill: &'a [R],
+    pub config: &'a FreeListConfig,
+    pub counters: &'a AllocationCounters,
+}
+
+/// Exclusive access to an allocator root, whatever the way it is synchronized.
+pub(crate) trait RootCell {
+    fn borrow_root(&self) -> impl DerefMut<Target = AllocatorRoot> + '_;
+
+    /// Same as `borrow_root`, counting the accesses which had to wait for another thread.
+    #[cfg(feature = "lock_contention")]
+    fn borrow_root_counted(
+        &self,
+        counters: &AllocationCounters,
+    ) -> impl DerefMut<Target = AllocatorRoot> + '_;
+}
+
+impl RootCell for Mutex<AllocatorRoot> {
+    fn borrow_root(&self) -> impl DerefMut<Target = AllocatorRoot> + '_ {
+        self.lock().unwrap()
+    }
+
+    #[cfg(feature = "lock_contention")]
+    fn borrow_root_counted(
+        &self,
+        counters: &AllocationCounters,
+    ) -> impl DerefMut<Target = AllocatorRoot> + '_ {
+        match self.try_lock() {
+            Ok(guard) => return guard,
+            Err(TryLockError::WouldBlock) => counters.record_contention(),
+            // Poisoned, panics below like `borrow_root`
+            Err(TryLockError::Poisoned(_)) => {}
+        }
+        self.lock().unwrap()
+    }
+}
+
+/// Single-threaded roots: a reentrant access, from an event hook for instance, panics instead of deadlocking.
+impl RootCell for RefCell<AllocatorRoot> {
+    fn borrow_root(&self) -> impl DerefMut<Target = AllocatorRoot> + '_ {
+        self.borrow_mut()
+    }
+
+    #[cfg(feature = "lock_contention")]
+    fn borrow_root_counted(
+        &self,
+        _counters: &AllocationCounters,
+    ) -> impl DerefMut<Target = AllocatorRoot> + '_ {
+        self.borrow_mut()
+    }
+}
+
+impl<'a, R: RootCell> FreeListHeap<'a, R> {
+    /// Read the activity counters, then the arena usage with a single free list walk.
+    pub fn stats(&self) -> FreeListStats {
+        let mut stats = self.counters.snapshot();
+        unsafe { self.root.borrow_root().arena_stats(&mut stats) };
+        stats
+    }
+
+    /// Collect the free blocks of the arena, sorted by offset.
+    pub fn free_blocks(&self) -> Vec<FreeBlock> {
+        let ((), blocks) = self.with_reserved(
+            |root| unsafe { root.free_node_count() },
+            |root, blocks| unsafe { root.free_blocks(blocks) },
+        );
+        blocks
+    }
+
+    /// Collect the allocations that weren't deallocated yet, sorted by offset.
+    /// Every allocation must have been made with live tracking enabled.
+    pub fn live_allocations(&self) -> Vec<AllocInfo> {
+        let placement = self.config.metadata_placement;
+        let ((), live) = self.with_reserved(
+            |root| unsafe { root.count_live_allocations(placement) },
+            |root, live| unsafe { root.live_allocations(placement, live) },
+        );
+        live
+    }
+
+    /// Collect the allocations that weren't deallocated yet along with their age, sorted by offset
+    /// (see `live_allocations`).
+    #[cfg(feature = "alloc_age")]
+    pub fn aged_allocations(&self) -> Vec<AgedAllocInfo> {
+        let placement = self.config.metadata_placement;
+        let ((), aged) = self.with_reserved(
+            |root| unsafe { root.count_live_allocations(placement) },
+            |root, aged| unsafe { root.aged_allocations(placement, aged) },
+        );
+        aged
+    }
+
+    /// Capture the free list state, so that the allocations made afterwards can be released by `rewind`.
+    pub fn checkpoint(&self) -> FreeListCheckpoint {
+        let ((arena_address, live_bytes), free_blocks) = self.with_reserved(
+            |root| unsafe { root.free_node_count() },
+            |root, blocks| unsafe { root.checkpoint(blocks) },
+        );
+        FreeListCheckpoint {
+            arena_address,
+            free_blocks,
+            live_bytes,
+        }
+    }
+
+    /// Release every allocation made since the given checkpoint was taken by `checkpoint`.
+    ///
+    /// **Panics**: if the checkpoint was taken from another arena
+    pub unsafe fn rewind(&self, checkpoint: &FreeListCheckpoint) {
+        self.root.borrow_root().rewind(checkpoint);
+    }
+
+    /// Call `f` with exclusive access to the root and a vector with room for `count(root)` items, reserved while
+    /// the root is unlocked: allocating with the lock held deadlocks when this allocator is the global allocator,
+    /// since the allocation takes the lock again. The vector is returned along with the result of `f`, so that it
+    /// is dropped once the lock is released as well.
+    ///
+    /// The count may increase between reserving and locking, with allocations from other threads or from the
+    /// reservation itself: the vector is then reserved again.
+    pub fn with_reserved<T, O>(
+        &self,
+        count: impl Fn(&AllocatorRoot) -> usize,
+        f: impl FnOnce(&mut AllocatorRoot, &mut Vec<T>) -> O,
+    ) -> (O, Vec<T>) {
+        let mut items = Vec::new();
+        loop {
+            let needed = count(&self.root.borrow_root());
+            items.reserve(needed);
+
+            let mut root = self.root.borrow_root();
+            if count(&root) <= items.capacity() {
+                let output = f(&mut root, &mut items);
+                drop(root);
+                return (output, items);
+            }
+        }
+    }
+
+    /// Allocate the given layout, notifying the event hook if one is set.
+    pub unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.alloc_block(layout);
+        self.notify(AllocEvent::Alloc { ptr, layout });
+        ptr
+    }
+
+    /// Allocate the given layout, applying `FreeListConfig::failure_policy` if it can't be served.
+    pub unsafe fn allocate(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.alloc(layout);
+        if ptr.is_null() {
+            match self.config.failure_policy {
+                FailurePolicy::Null => {}
+                FailurePolicy::Panic => {
+                    let stats = self.stats();
+                    panic!(
+                        "free list arena exhausted: can't allocate {} bytes aligned to {} ({} bytes free, largest free block of {} bytes)",
+                        layout.size(),
+                        layout.align(),
+                        stats.free_bytes,
+                        stats.largest_free_block
+                    );
+                }
+                FailurePolicy::Abort => handle_alloc_error(layout),
+            }
+        }
+        ptr
+    }
+
+    /// Release the given allocation, notifying the event hook if one is set.
+    ///
+    /// Null pointers (returned by failed allocations) are ignored.
+    pub unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        if ptr.is_null() {
+            return;
+        }
+        self.dealloc_block(ptr, layout);
+        self.notify(AllocEvent::Dealloc { ptr, layout });
+    }
+
+    /// Release the given allocations at once, notifying the event hook for each one if it is set.
+    ///
+    /// Allocations are sorted by address in place. Null pointers are ignored.
+    pub unsafe fn dealloc_batch(&self, allocations: &mut [(*mut u8, Layout)]) {
+        let mut allocator = self.lock(self.root);
+        for &(ptr, layout) in allocations.iter().filter(|(ptr, _)| !ptr.is_null()) {
+            self.counters.record_dealloc();
+            if layout.size() != 0 && self.config.system_fallback && !allocator.contains(ptr) {
+                // Allocated by the system allocator when the arena was exhausted
+                System.dealloc(ptr, layout);
+            }
+        }
+        // Allocations outside of the arena are skipped
+        allocator.dealloc_batch(allocations, self.config);
+        drop(allocator);
+
+        for &(ptr, layout) in allocations.iter().filter(|(ptr, _)| !ptr.is_null()) {
+            self.notify(AllocEvent::Dealloc { ptr, layout });
+        }
+    }
+
+    /// Allocate the given layout in the arena, falling back to the `System` allocator if enabled.
+    unsafe fn alloc_block(&self, layout: Layout) -> *mut u8 {
+        if self.config.size_histogram {
+            // Failed allocations are counted as well, since they reveal the sizes the arena can't serve
+            self.counters.record_size_class(layout.size());
+        }
+        if layout.size() == 0 {
+            // Zero-sized allocation: no arena space is needed, return a dangling but aligned pointer
+            self.counters.record_alloc(0);
+            return ptr::without_provenance_mut(layout.align());
+        }
+
+        let mut ptr = self.arena_alloc(layout);
+        if ptr.is_null() {
+            if let Some(handler) = self.config.oom_handler {
+                // The lock is released, so that the handler can deallocate to make room
+                if handler(layout) {
+                    ptr = self.arena_alloc(layout);
+                }
+            }
+        }
+        if ptr.is_null() && self.config.system_fallback {
+            // Arena is exhausted, fall back to the system allocator
+            ptr = System.alloc(layout);
+            if !ptr.is_null() {
+                self.counters.record_system_alloc();
+            }
+        }
+
+        if !ptr.is_null() {
+            self.counters.record_alloc(layout.size());
+        }
+        ptr
+    }
+
+    /// Get exclusive access to the given root on the allocation and deallocation paths,
+    /// counting the contended locks if the `lock_contention` feature is enabled.
+    #[cfg(feature = "lock_contention")]
+    fn lock(&self, root: &'a R) -> impl DerefMut<Target = AllocatorRoot> + 'a {
+        root.borrow_root_counted(self.counters)
+    }
+
+    #[cfg(not(feature = "lock_contention"))]
+    fn lock(&self, root: &'a R) -> impl DerefMut<Target = AllocatorRoot> + 'a {
+        root.borrow_root()
+    }
+
+    /// Allocate the given layout in the root arena, then in the spill arenas.
+    unsafe fn arena_alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.lock(self.root).alloc(layout, self.config);
+        if !ptr.is_null() {
+            return ptr;
+        }
+
+        for root in self.spill.iter().filter(|root| !ptr::eq(*root, self.root)) {
+            let ptr = self.lock(root).alloc(layout, self.config);
+            if !ptr.is_null() {
+                return ptr;
+            }
+        }
+        ptr::null_mut()
+    }
+
+    /// Release the given allocation, to the arena or to the `System` allocator it was taken from.
+    unsafe fn dealloc_block(&self, ptr: *mut u8, layout: Layout) {
+        self.counters.record_dealloc();
+        if layout.size() == 0 {
+            // Zero-sized allocations are dangling pointers that were never placed in the arena
+            return;
+        }
+
+        let mut allocator = self.lock(self.root);
+        if self.config.system_fallback && !allocator.contains(ptr) {
+            // Allocated by the system allocator when the arena was exhausted
+            drop(allocator);
+            System.dealloc(ptr, layout);
+            return;
+        }
+
+        allocator.dealloc(ptr, layout, self.config);
+    }
+
+    /// Release every allocation of the arena. Allocations served by the `System` allocator are kept.
+    pub unsafe fn reset(&self) {
+        self.root.borrow_root().reset();
+    }
+
+    /// Touch the free pages of the arena, so that the system commits them up front.
+    pub fn prefault(&self) {
+        unsafe { self.root.borrow_root().prefault() };
+    }
+
+    /// Merge the contiguous free blocks of the arena, sorting the free list first if needed.
+    ///
+    /// **Returns**: Count of merged blocks
+    pub fn defragment(&self) -> usize {
+        unsafe { self.root.borrow_root().defragment() }
+    }
+
+    /// Resize the given allocation, shrinking it in place when possible.
+    ///
+    /// The alignment of `layout` is kept: a value shrunk in place keeps its block and alignment padding,
+    /// and a moved value is copied to a block allocated with the same alignment, with its own padding.
+    pub unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        if new_size != 0 && new_size < layout.size() {
+            // Shrink in place, the block tail is released without copying the value
+            let mut allocator = self.lock(self.root);
+            if allocator.contains(ptr) && allocator.shrink(ptr, layout, new_size, self.config) {
+                drop(allocator);
+                self.counters.record_realloc(true);
+                // Reported as the release of the previous layout followed by the new allocation
+                self.notify(AllocEvent::Dealloc { ptr, layout });
+                let new_layout = Layout::from_size_align_unchecked(new_size, layout.align());
+                self.notify(AllocEvent::Alloc {
+                    ptr,
+                    layout: new_layout,
+                });
+                return ptr;
+            }
+        }
+
+        let new_layout = Layout::from_size_align_unchecked(new_size, layout.align());
+        let new_ptr = self.alloc(new_layout);
+        if !new_ptr.is_null() {
+            ptr::copy_nonoverlapping(ptr, new_ptr, layout.size().min(new_size));
+            self.dealloc(ptr, layout);
+            self.counters.record_realloc(false);
+        }
+        new_ptr
+    }
+
+    /// Call the event hook, if one is set. The allocator lock must not be held.
+    fn notify(&self, event: AllocEvent) {
+        if let Some(hook) = self.config.event_hook {
+            hook(event);
+        }
+    }
+}
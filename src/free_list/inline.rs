@@ -0,0 +1,182 @@
+use super::{
+    alloc_root::AllocatorRoot,
+    heap::{FreeListHeap, RootCell},
+    inspect::FreeBlock,
+    node::{NODE_LAYOUT_ALIGN, NODE_LAYOUT_SIZE},
+    stats::{AllocationCounters, FreeListStats},
+    FreeListConfig,
+};
+use crate::region::RegionAllocator;
+use std::{
+    alloc::{GlobalAlloc, Layout},
+    cell::UnsafeCell,
+    ops::DerefMut,
+    sync::{atomic::Ordering, Mutex},
+};
+
+/// Free list allocator storing its arena inline, instead of allocating it from the `System` allocator.
+///
+/// It behaves like `FreeListAllocator`, but the arena is part of the allocator itself: no allocation is needed to create it,
+/// and a static allocator keeps its arena in the program data. This suits `#[global_allocator]` statics.
+///
+/// ## Usage
+/// ```
+/// use allocator::free_list::InlineFreeListAllocator;
+///
+/// #[global_allocator]
+/// static ALLOCATOR: InlineFreeListAllocator<4096> = InlineFreeListAllocator::new();
+/// ```
+///
+/// ## Note
+/// Free Nodes link to each other with absolute pointers. If the allocator is moved, the links are shifted
+/// by the move distance on its next use, under the same lock as the operation itself.
+/// Pointers to live allocations are invalidated by the move, like any reference into the arena.
+pub struct InlineFreeListAllocator<const S: usize> {
+    root: InlineRoot<S>,
+    config: FreeListConfig,
+    counters: AllocationCounters,
+}
+
+/// Inline arena along with its allocator root. The root Node is written in the arena on first access,
+/// and the free list is shifted on the accesses following a move of the allocator.
+struct InlineRoot<const S: usize> {
+    arena: InlineArena<S>,
+    allocator: Mutex<AllocatorRoot>,
+}
+
+/// Arena bytes, aligned for a free Node.
+#[repr(C, align(16))]
+struct InlineArena<const S: usize>(UnsafeCell<[u8; S]>);
+
+// The arena is only accessed through the allocator root, which is guarded by a mutex
+unsafe impl<const S: usize> Sync for InlineFreeListAllocator<S> {}
+
+impl<const S: usize> InlineFreeListAllocator<S> {
+    /// Create an allocator using the default configuration. The arena is part of the allocator, nothing is allocated.
+    #[allow(clippy::new_without_default)]
+    pub const fn new() -> Self {
+        Self::with_config(FreeListConfig::new())
+    }
+
+    /// Create an allocator using the given configuration.
+    pub const fn with_config(config: FreeListConfig) -> Self {
+        const {
+            assert!(
+                S >= NODE_LAYOUT_SIZE,
+                "free list arena must be able to hold a free Node"
+            );
+            assert!(align_of::<InlineArena<S>>() >= NODE_LAYOUT_ALIGN);
+        };
+
+        InlineFreeListAllocator {
+            root: InlineRoot {
+                arena: InlineArena(UnsafeCell::new([0; S])),
+                allocator: Mutex::new(AllocatorRoot::empty()),
+            },
+            config,
+            counters: AllocationCounters::new(),
+        }
+    }
+
+    /// Get the arena size, in bytes.
+    pub const fn capacity(&self) -> usize {
+        S
+    }
+
+    /// Get the count of arena bytes currently in use (see `FreeListAllocator::len`).
+    pub fn len(&self) -> usize {
+        unsafe { self.root.borrow_root().used_bytes() }
+    }
+
+    /// Check if no arena byte is currently in use (see `len`).
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get a snapshot of the allocation activity counters and of the arena usage (see `FreeListAllocator::stats`).
+    pub fn stats(&self) -> FreeListStats {
+        self.heap().stats()
+    }
+
+    /// Iterate over the free blocks of the arena, sorted by offset (see `FreeListAllocator::free_blocks`).
+    pub fn free_blocks(&self) -> impl Iterator<Item = FreeBlock> {
+        self.heap().free_blocks().into_iter()
+    }
+
+    /// Touch every free page of the arena, so that the system commits them up front (see `FreeListAllocator::prefault`).
+    pub fn prefault(&self) {
+        self.heap().prefault()
+    }
+
+    /// Merge the contiguous free blocks of the arena (see `FreeListAllocator::defragment`).
+    pub fn defragment(&self) -> usize {
+        self.heap().defragment()
+    }
+
+    /// Check that the free list is well-formed, panicking otherwise. This is a no-op in release builds.
+    pub fn debug_assert_valid(&self) {
+        if cfg!(debug_assertions) {
+            unsafe { self.root.borrow_root().assert_valid() };
+        }
+    }
+
+    fn heap(&self) -> FreeListHeap<'_, InlineRoot<S>> {
+        FreeListHeap {
+            root: &self.root,
+            spill: &[],
+            config: &self.config,
+            counters: &self.counters,
+        }
+    }
+}
+
+impl<const S: usize> InlineRoot<S> {
+    /// Write the root Node in the arena on first access, or shift the free list if the arena moved since the last one.
+    fn prepare(&self, root: &mut AllocatorRoot) {
+        let arena_ptr = self.arena.0.get() as *mut u8;
+        if root.arena_ptr.load(Ordering::Acquire).is_null() {
+            *root = unsafe { AllocatorRoot::new(arena_ptr, S) };
+        } else {
+            unsafe { root.rebase(arena_ptr) };
+        }
+    }
+}
+
+impl<const S: usize> RootCell for InlineRoot<S> {
+    fn borrow_root(&self) -> impl DerefMut<Target = AllocatorRoot> + '_ {
+        let mut root = self.allocator.borrow_root();
+        self.prepare(&mut root);
+        root
+    }
+
+    #[cfg(feature = "lock_contention")]
+    fn borrow_root_counted(
+        &self,
+        counters: &AllocationCounters,
+    ) -> impl DerefMut<Target = AllocatorRoot> + '_ {
+        let mut root = self.allocator.borrow_root_counted(counters);
+        self.prepare(&mut root);
+        root
+    }
+}
+
+unsafe impl<const S: usize> GlobalAlloc for InlineFreeListAllocator<S> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.heap().alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.heap().dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        self.heap().realloc(ptr, layout, new_size)
+    }
+}
+
+impl<const S: usize> RegionAllocator for InlineFreeListAllocator<S> {
+    /// Release every allocation of the arena at once (see `FreeListAllocator::reset`).
+    unsafe fn reset(&self) {
+        self.heap().reset()
+    }
+}
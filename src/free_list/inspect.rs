@@ -0,0 +1,64 @@
+/// Free block of the arena, as referenced by the free list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FreeBlock {
+    /// Offset of the block from the arena start, in bytes
+    pub offset: usize,
+    /// Size of the block, in bytes
+    pub size: usize,
+}
+
+/// Allocated value of the arena, as listed by `FreeListAllocator::heap_dump`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AllocInfo {
+    /// Offset of the value from the arena start, in bytes
+    pub offset: usize,
+    /// Size of the value, in bytes
+    pub size: usize,
+    /// Alignment of the value, in bytes
+    pub align: usize,
+}
+
+/// Allocated value of the arena along with its age, as listed by `FreeListAllocator::live_allocations_older_than`.
+#[cfg(feature = "alloc_age")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AgedAllocInfo {
+    /// Offset, size and alignment of the value
+    pub alloc: AllocInfo,
+    /// Sequence number of the allocation in its arena: the first tracked allocation is number 0
+    pub sequence: usize,
+    /// Count of tracked allocations made in the arena after this one
+    pub age: usize,
+}
+
+/// Free list state captured by `FreeListAllocator::checkpoint`, to be given back to `FreeListAllocator::rewind`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FreeListCheckpoint {
+    /// Start address of the arena the checkpoint was taken from
+    pub(crate) arena_address: usize,
+    /// Free blocks at the checkpoint, sorted by offset
+    pub(crate) free_blocks: Vec<FreeBlock>,
+    /// Cumulative size of the values allocated at the checkpoint
+    pub(crate) live_bytes: usize,
+}
+
+/// Changes made by `FreeListAllocator::repair` to fix the free list.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RepairReport {
+    /// Count of Nodes which weren't sorted by address, and were moved to their place in the list
+    pub reordered_nodes: usize,
+    /// Count of Nodes merged into the previous one, as they were contiguous or overlapping
+    pub merged_nodes: usize,
+    /// Count of Nodes extending past the arena end, which were shortened
+    pub truncated_nodes: usize,
+    /// Count of Nodes removed from the list: outside of the arena, misaligned, too small to hold a Node,
+    /// or visited twice (the list looped). The rest of the list is dropped along with an invalid Node,
+    /// since its link can't be trusted.
+    pub dropped_nodes: usize,
+}
+
+impl RepairReport {
+    /// Check if the free list was changed.
+    pub fn changed(&self) -> bool {
+        *self != RepairReport::default()
+    }
+}
@@ -0,0 +1,166 @@
+use super::{
+    alloc_root::AllocatorRoot,
+    arena_layout,
+    heap::FreeListHeap,
+    inspect::FreeBlock,
+    node::NODE_LAYOUT_SIZE,
+    stats::{AllocationCounters, FreeListStats},
+    FreeListConfig,
+};
+use crate::region::RegionAllocator;
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::RefCell,
+    sync::atomic::Ordering,
+};
+
+/// Single-threaded free list allocator, guarding its arena with a `RefCell` instead of a `Mutex`.
+///
+/// It behaves like `FreeListAllocator`, without acquiring a lock on each allocation and deallocation.
+/// The arena is allocated when creating the allocator, and given back to the system when it is dropped.
+///
+/// The allocator isn't `Sync`, so it can't be shared across threads: it is meant to be used as a scoped allocator,
+/// owned by a single thread. Allocating through it from an event hook or an OOM handler panics.
+///
+/// ## Usage
+/// ```
+/// use allocator::free_list::LocalFreeListAllocator;
+/// use std::alloc::{GlobalAlloc, Layout};
+///
+/// let allocator = LocalFreeListAllocator::<4096>::new();
+/// let layout = Layout::new::<u64>();
+///
+/// let ptr = unsafe { allocator.alloc(layout) };
+/// unsafe { allocator.dealloc(ptr, layout) };
+/// ```
+///
+/// It can't be used as the global allocator, which must be `Sync`:
+/// ```compile_fail
+/// use allocator::free_list::LocalFreeListAllocator;
+///
+/// fn assert_sync<T: Sync>() {}
+/// assert_sync::<LocalFreeListAllocator<4096>>();
+/// ```
+pub struct LocalFreeListAllocator<const S: usize> {
+    allocator: RefCell<AllocatorRoot>,
+    config: FreeListConfig,
+    counters: AllocationCounters,
+}
+
+impl<const S: usize> LocalFreeListAllocator<S> {
+    /// Create an allocator, allocating its arena.
+    ///
+    /// If the arena can't be allocated, every allocation returns null.
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self::with_config(FreeListConfig::new())
+    }
+
+    /// Create an allocator using the given configuration.
+    pub fn with_config(config: FreeListConfig) -> Self {
+        const {
+            assert!(
+                S >= NODE_LAYOUT_SIZE,
+                "free list arena must be able to hold a free Node"
+            );
+        };
+
+        let arena_ptr = unsafe { GlobalAlloc::alloc(&System, arena_layout(S).unwrap()) };
+        LocalFreeListAllocator {
+            allocator: RefCell::new(unsafe { AllocatorRoot::new(arena_ptr, S) }),
+            config,
+            counters: AllocationCounters::new(),
+        }
+    }
+
+    /// Get the arena size, in bytes.
+    pub const fn capacity(&self) -> usize {
+        S
+    }
+
+    /// Get the count of arena bytes currently in use (see `FreeListAllocator::len`).
+    pub fn len(&self) -> usize {
+        unsafe { self.allocator.borrow().used_bytes() }
+    }
+
+    /// Check if no arena byte is currently in use (see `len`).
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get a snapshot of the allocation activity counters and of the arena usage (see `FreeListAllocator::stats`).
+    pub fn stats(&self) -> FreeListStats {
+        self.heap().stats()
+    }
+
+    /// Allocate the given layout, applying `FreeListConfig::failure_policy` if it can't be served
+    /// (see `FreeListAllocator::allocate`).
+    ///
+    /// **Panics**: if the allocation fails with `FailurePolicy::Panic`
+    pub fn allocate(&self, layout: Layout) -> *mut u8 {
+        unsafe { self.heap().allocate(layout) }
+    }
+
+    /// Iterate over the free blocks of the arena, sorted by offset (see `FreeListAllocator::free_blocks`).
+    pub fn free_blocks(&self) -> impl Iterator<Item = FreeBlock> {
+        self.heap().free_blocks().into_iter()
+    }
+
+    /// Touch every free page of the arena, so that the system commits them up front (see `FreeListAllocator::prefault`).
+    pub fn prefault(&self) {
+        self.heap().prefault()
+    }
+
+    /// Merge the contiguous free blocks of the arena (see `FreeListAllocator::defragment`).
+    pub fn defragment(&self) -> usize {
+        self.heap().defragment()
+    }
+
+    /// Check that the free list is well-formed, panicking otherwise. This is a no-op in release builds.
+    pub fn debug_assert_valid(&self) {
+        if cfg!(debug_assertions) {
+            unsafe { self.allocator.borrow().assert_valid() };
+        }
+    }
+
+    fn heap(&self) -> FreeListHeap<'_, RefCell<AllocatorRoot>> {
+        FreeListHeap {
+            root: &self.allocator,
+            spill: &[],
+            config: &self.config,
+            counters: &self.counters,
+        }
+    }
+}
+
+unsafe impl<const S: usize> GlobalAlloc for LocalFreeListAllocator<S> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.heap().alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.heap().dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        self.heap().realloc(ptr, layout, new_size)
+    }
+}
+
+impl<const S: usize> RegionAllocator for LocalFreeListAllocator<S> {
+    /// Release every allocation of the arena at once (see `FreeListAllocator::reset`).
+    unsafe fn reset(&self) {
+        self.heap().reset()
+    }
+}
+
+impl<const S: usize> Drop for LocalFreeListAllocator<S> {
+    fn drop(&mut self) {
+        let arena_ptr = self.allocator.get_mut().arena_ptr.load(Ordering::Acquire);
+        if arena_ptr.is_null() {
+            return;
+        }
+
+        unsafe { GlobalAlloc::dealloc(&System, arena_ptr, arena_layout(S).unwrap()) };
+    }
+}
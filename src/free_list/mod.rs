@@ -1,116 +1,487 @@
 use self::{
-    alloc_root::AllocatorRoot,
-    node::{AllocationMetadata, ALLOCATION_METADATA_LAYOUT_SIZE},
+    alloc_root::{AllocatorRoot, SNAPSHOT_TRAILER_SIZE},
+    heap::FreeListHeap,
+    node::{ALLOCATION_METADATA_LAYOUT_SIZE, NODE_LAYOUT_ALIGN, NODE_LAYOUT_SIZE},
+    stats::AllocationCounters,
 };
-use node::Node;
-use once_cell::sync::Lazy;
+use crate::region::RegionAllocator;
 use std::{
     alloc::{GlobalAlloc, Layout, System},
-    ptr::{self, null_mut},
-    sync::{
-        atomic::{AtomicPtr, Ordering},
-        Mutex,
-    },
+    ptr::null_mut,
+    sync::{Mutex, Once},
+};
+
+#[cfg(feature = "alloc_age")]
+pub use self::inspect::AgedAllocInfo;
+pub use self::{
+    builder::FreeListAllocatorBuilder,
+    config::{FailurePolicy, FitStrategy, FreeListConfig, MetadataPlacement},
+    dynamic::DynFreeListAllocator,
+    event::AllocEvent,
+    inline::InlineFreeListAllocator,
+    inspect::{AllocInfo, FreeBlock, FreeListCheckpoint, RepairReport},
+    local::LocalFreeListAllocator,
+    sharded::ShardedFreeListAllocator,
+    stats::{FreeListStats, SIZE_CLASSES},
 };
 
 mod alloc_root;
+mod builder;
+mod config;
+mod dynamic;
+mod event;
+mod heap;
+mod inline;
+mod inspect;
+mod local;
 mod node;
+mod sharded;
+mod stats;
 #[cfg(test)]
 mod tests;
 
+/// Alignment of the arenas. It is at least the alignment of a free Node, which is written at the arena start,
+/// and large enough that common allocations don't need padding.
+pub const ARENA_ALIGN: usize = if NODE_LAYOUT_ALIGN > 16 {
+    NODE_LAYOUT_ALIGN
+} else {
+    16
+};
+
+/// Size of the metadata stored along each allocated value, in bytes. Debug builds store additional fields.
+pub const ALLOCATION_METADATA_SIZE: usize = ALLOCATION_METADATA_LAYOUT_SIZE;
+
+/// Size of a free Node, in bytes. Every allocated block is at least this large, so that it can hold a Node once freed.
+pub const NODE_SIZE: usize = NODE_LAYOUT_SIZE;
+
+/// Alignment of free Nodes. Allocated blocks are padded so that the following block starts at a multiple of it.
+pub const NODE_ALIGN: usize = NODE_LAYOUT_ALIGN;
+
+/// Layout of an arena of `size` bytes. The same layout must be used to allocate and free it.
+pub(crate) fn arena_layout(size: usize) -> Option<Layout> {
+    Layout::from_size_align(size, ARENA_ALIGN).ok()
+}
+
 /// Free list allocator. It handles auto defragmentation on deallocation.
 /// The pool size is set using a generic type argument (see usage example).
 ///
 /// ## Usage
 /// ```
+/// use allocator::free_list::FreeListAllocator;
+///
 /// #[global_allocator]
 /// static ALLOCATOR: FreeListAllocator<1024> = FreeListAllocator::new();
 /// ```
 ///
+/// The arena must be able to hold at least a free Node, smaller sizes fail to compile:
+/// ```compile_fail
+/// use allocator::free_list::FreeListAllocator;
+///
+/// static ALLOCATOR: FreeListAllocator<8> = FreeListAllocator::new();
+/// ```
+///
+/// ## Deterministic layout
+/// By default, allocations use a first-fit scan of the free list, which is sorted by address: the lowest-address
+/// free block large enough is always chosen, and the value is placed at its start (after alignment padding).
+/// Given a fixed sequence of allocations and deallocations, allocation offsets from the arena start are
+/// therefore stable across runs, as long as requested alignments don't exceed the arena base alignment (`ARENA_ALIGN`).
+///
 /// ## Note
-/// Lazy is used to circumvent const function limitation, it allows a call to `ptr::write`.
-/// This defers the initialization to first allocation call.
+/// The arena can't be allocated in a const function, so the initialization is deferred to the first call
+/// needing it, guarded by a `Once`. If the arena can't be allocated at that point, the allocator is left empty
+/// and every allocation returns null.
 pub struct FreeListAllocator<const S: usize> {
-    allocator: Lazy<Mutex<AllocatorRoot>>,
+    allocator: Mutex<AllocatorRoot>,
+    init: Once,
+    config: FreeListConfig,
+    counters: AllocationCounters,
 }
 
 impl<const S: usize> FreeListAllocator<S> {
+    #[allow(clippy::new_without_default)]
     pub const fn new() -> Self {
+        Self::with_config(FreeListConfig::new())
+    }
+
+    /// Create an allocator using the given configuration.
+    pub const fn with_config(config: FreeListConfig) -> Self {
+        const {
+            assert!(
+                S >= NODE_LAYOUT_SIZE,
+                "free list arena must be able to hold a free Node"
+            );
+        };
+
         FreeListAllocator {
-            allocator: Lazy::new(|| {
-                let layout = Layout::new::<[u8; S]>();
-                let arena_ptr = unsafe { GlobalAlloc::alloc(&System, layout) };
-
-                // Write root node at the start of the arena
-                let root_node = Node {
-                    size: S,
-                    next_ptr: None,
-                };
-
-                unsafe {
-                    ptr::write(arena_ptr as *mut Node, root_node);
-                };
-
-                Mutex::new(AllocatorRoot {
-                    free_root: Some(AtomicPtr::new(arena_ptr)),
-                })
-            }),
+            allocator: Mutex::new(AllocatorRoot::empty()),
+            init: Once::new(),
+            config,
+            counters: AllocationCounters::new(),
         }
     }
-}
 
-unsafe impl<const S: usize> GlobalAlloc for FreeListAllocator<S> {
-    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
-        let mut allocator = self.allocator.lock().unwrap();
-        let node_ptr = match &allocator.free_root {
-            Some(n) => n,
-            None => return null_mut(), // No memory available
-        };
+    /// Get the arena size, in bytes.
+    pub const fn capacity(&self) -> usize {
+        S
+    }
+
+    /// Get the count of arena bytes currently in use, walking the free list.
+    ///
+    /// Every byte that isn't part of a free block is counted as used: this includes allocation metadata
+    /// and paddings, since they consume the arena as well.
+    pub fn len(&self) -> usize {
+        unsafe { self.root().lock().unwrap().used_bytes() }
+    }
 
-        let size = layout.size();
-        let align = layout.align();
+    /// Check if no arena byte is currently in use (see `len`).
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 
-        // Initial node
-        let mut node = ptr::read(node_ptr.load(Ordering::Acquire) as *const Node);
-        if let Ok(alloc_specs) =
-            node.try_get_alloc_specs(size, align, node_ptr.load(Ordering::Acquire))
-        {
-            return allocator.split_alloc(None, node, alloc_specs);
-        }
+    /// Get the count of free arena bytes, walking the free list. This is `capacity() - len()`.
+    pub fn free_bytes(&self) -> usize {
+        unsafe { self.root().lock().unwrap().free_bytes() }
+    }
+
+    /// Get the count of free blocks, walking the free list. It is 0 if the whole arena is allocated.
+    ///
+    /// Along with `free_bytes`, it gives the average free block size: many small free blocks denote fragmentation.
+    pub fn free_node_count(&self) -> usize {
+        unsafe { self.root().lock().unwrap().free_node_count() }
+    }
+
+    /// Get a snapshot of the allocation activity counters and of the arena usage.
+    ///
+    /// The arena usage is computed in a single free list walk, under one lock acquisition:
+    /// this is cheaper than calling `free_bytes`, `free_node_count` and `len` separately, and the values are consistent.
+    pub fn stats(&self) -> FreeListStats {
+        self.heap().stats()
+    }
+
+    /// Get the count of allocation requests per size class, failed ones included.
+    /// Class `i` counts allocation sizes in `(2^(i-1), 2^i]` (class 0 counts sizes 0 and 1),
+    /// the last class also counts every larger size.
+    ///
+    /// Sizes are only recorded if `FreeListConfig::size_histogram` is enabled, otherwise all counts are 0.
+    pub fn size_histogram(&self) -> [usize; SIZE_CLASSES] {
+        self.counters.size_histogram()
+    }
+
+    /// Get the count of allocations, deallocations and reallocations which had to wait for the allocator lock,
+    /// held by another thread.
+    ///
+    /// The lock is tried first, and only waited for if it is held, so that contention can be counted.
+    /// A count growing along with the allocation count reveals that the lock is a bottleneck,
+    /// in which case `ShardedFreeListAllocator` may scale better.
+    #[cfg(feature = "lock_contention")]
+    pub fn lock_contention_count(&self) -> usize {
+        self.counters.contended_locks()
+    }
 
-        // Iterate over free nodes until one matches size requirements
-        let mut previous_node = node;
-        while let Some(node_ptr) = previous_node.next_ptr {
-            node = ptr::read(node_ptr as *const Node);
-            if let Ok(alloc_specs) = node.try_get_alloc_specs(size, align, node_ptr) {
-                // Allocate in place of the current free node
-                return allocator.split_alloc(Some(previous_node), node, alloc_specs);
+    /// Allocate the given layout like `GlobalAlloc::alloc`, applying `FreeListConfig::failure_policy` if it can't be served:
+    /// the null pointer is only returned with `FailurePolicy::Null`.
+    ///
+    /// Zero-sized layouts are allowed, and get a dangling but aligned pointer. The allocation must be deallocated
+    /// with `GlobalAlloc::dealloc`, using the same layout.
+    ///
+    /// **Panics**: if the allocation fails with `FailurePolicy::Panic`
+    pub fn allocate(&self, layout: Layout) -> *mut u8 {
+        unsafe { self.heap().allocate(layout) }
+    }
+
+    /// Check if the given layout can currently be allocated in the arena, without allocating it.
+    ///
+    /// Only the arena is checked: allocations that would be served by the `System` allocator
+    /// (see `FreeListConfig::system_fallback`) aren't taken into account.
+    pub fn can_allocate(&self, layout: Layout) -> bool {
+        layout.size() == 0
+            || unsafe {
+                self.root()
+                    .lock()
+                    .unwrap()
+                    .can_allocate(layout, &self.config)
             }
+    }
+
+    /// Count the arena bytes lost inside allocations: alignment paddings, metadata and fill paddings.
+    ///
+    /// High internal fragmentation calls for smaller metadata or fewer over-aligned allocations.
+    pub fn internal_fragmentation_bytes(&self) -> usize {
+        unsafe { self.root().lock().unwrap().internal_fragmentation_bytes() }
+    }
+
+    /// Count the free bytes that aren't part of the largest free block, walking the free list.
+    ///
+    /// These bytes can't serve an allocation as large as the total free space: high external fragmentation
+    /// calls for compaction or a different fit strategy.
+    pub fn external_fragmentation_bytes(&self) -> usize {
+        unsafe { self.root().lock().unwrap().external_fragmentation_bytes() }
+    }
+
+    /// List the allocations that weren't deallocated yet, as (offset from the arena start, size) pairs sorted by offset.
+    /// This is meant to report leaks, typically at shutdown.
+    ///
+    /// Allocations served by the `System` allocator (see `FreeListConfig::system_fallback`) aren't listed.
+    ///
+    /// **Panics**: if `FreeListConfig::live_tracking` isn't enabled
+    pub fn report_live(&self) -> Vec<(usize, usize)> {
+        self.heap_dump()
+            .into_iter()
+            .map(|alloc| (alloc.offset, alloc.size))
+            .collect()
+    }
+
+    /// List the allocations that weren't deallocated yet, with their offset from the arena start, size and alignment,
+    /// sorted by offset. This is meant for heap profiling tools.
+    ///
+    /// Along with `free_blocks`, this describes the whole arena: the bytes which belong to neither are allocation
+    /// metadata, headers and paddings. Allocations served by the `System` allocator aren't listed.
+    /// The list is allocated while the allocator lock is released, so this can be called on the global allocator.
+    ///
+    /// **Panics**: if `FreeListConfig::live_tracking` isn't enabled
+    pub fn heap_dump(&self) -> Vec<AllocInfo> {
+        assert!(self.config.live_tracking, "live allocations aren't tracked");
+        self.heap().live_allocations()
+    }
+
+    /// Get the oldest allocation that wasn't deallocated yet, None if there is no live allocation.
+    /// Allocations are numbered in order, so the oldest one has the lowest sequence number.
+    ///
+    /// Long-lived allocations that should be transient are a leak signal: the sequence number tells which allocation
+    /// of a replayable run leaked.
+    ///
+    /// **Panics**: if `FreeListConfig::live_tracking` isn't enabled
+    #[cfg(feature = "alloc_age")]
+    pub fn oldest_live_allocation(&self) -> Option<AgedAllocInfo> {
+        self.aged_allocations()
+            .into_iter()
+            .min_by_key(|aged| aged.sequence)
+    }
+
+    /// List the allocations that weren't deallocated yet although more than `age` allocations were made after them,
+    /// sorted by offset.
+    ///
+    /// **Panics**: if `FreeListConfig::live_tracking` isn't enabled
+    #[cfg(feature = "alloc_age")]
+    pub fn live_allocations_older_than(&self, age: usize) -> Vec<AgedAllocInfo> {
+        let mut aged = self.aged_allocations();
+        aged.retain(|aged| aged.age > age);
+        aged
+    }
+
+    #[cfg(feature = "alloc_age")]
+    fn aged_allocations(&self) -> Vec<AgedAllocInfo> {
+        assert!(self.config.live_tracking, "live allocations aren't tracked");
+        self.heap().aged_allocations()
+    }
+
+    /// Get the offset of the given pointer from the arena start, None if it lies outside of the arena
+    /// (allocations served by the `System` allocator for instance, see `FreeListConfig::system_fallback`).
+    ///
+    /// Unlike addresses, offsets are stable across runs given the same sequence of allocations, which makes logs comparable.
+    pub fn offset_of(&self, ptr: *const u8) -> Option<usize> {
+        let root = self.root().lock().unwrap();
+        root.contains(ptr).then(|| root.offset_of(ptr))
+    }
+
+    /// Check if the given pointer lies inside an allocated part of the arena: inside the arena, but not inside a free block.
+    /// This is meant for debug assertions, catching a use after free for instance. The free list is walked on each call.
+    ///
+    /// Allocation metadata, headers and paddings count as allocated. Pointers outside of the arena aren't allocated,
+    /// including allocations served by the `System` allocator (see `FreeListConfig::system_fallback`).
+    pub fn is_allocated(&self, ptr: *const u8) -> bool {
+        unsafe { self.root().lock().unwrap().is_allocated(ptr as usize) }
+    }
+
+    /// Iterate over the free blocks of the arena, sorted by offset.
+    ///
+    /// The free list is captured when calling this function, so the allocator lock isn't held during iteration.
+    /// The captured blocks are stored in a vector allocated while the lock is released, so that this can be called
+    /// on the global allocator.
+    pub fn free_blocks(&self) -> impl Iterator<Item = FreeBlock> {
+        self.heap().free_blocks().into_iter()
+    }
+
+    /// Copy the whole allocator state: the arena bytes, followed by the free root offset.
+    ///
+    /// The snapshot can be given back to `restore` in order to replay a sequence of allocations and deallocations.
+    ///
+    /// The snapshot is larger than the arena, so it can't be allocated from it: this must not be called on the
+    /// global allocator instance. Use `snapshot_into` with a buffer allocated elsewhere instead.
+    pub fn snapshot(&self) -> Vec<u8> {
+        let mut snapshot = vec![0; self.snapshot_size()];
+        self.snapshot_into(&mut snapshot);
+        snapshot
+    }
+
+    /// Copy the whole allocator state into the given buffer, without allocating (see `snapshot`).
+    ///
+    /// **Panics**: if the buffer size isn't `snapshot_size`
+    pub fn snapshot_into(&self, snapshot: &mut [u8]) {
+        unsafe { self.root().lock().unwrap().snapshot(snapshot) }
+    }
+
+    /// Get the size of a snapshot, in bytes: the arena size, plus a few bytes of allocator state.
+    pub const fn snapshot_size(&self) -> usize {
+        S + SNAPSHOT_TRAILER_SIZE
+    }
+
+    /// Restore the allocator state from a snapshot taken by `snapshot`.
+    ///
+    /// Free Nodes store absolute pointers, so a snapshot can only be restored into the allocator it was taken from:
+    /// the arena base address must be unchanged. Allocation counters aren't restored.
+    ///
+    /// **Panics**: if the snapshot size doesn't match the arena size
+    ///
+    /// # Safety
+    /// The arena is overwritten: every allocation made after the snapshot is invalidated,
+    /// and allocations freed after it are considered live again.
+    pub unsafe fn restore(&self, snapshot: &[u8]) {
+        self.root().lock().unwrap().restore(snapshot);
+    }
+
+    /// Capture the free list state, so that every allocation made afterwards can be released at once by `rewind`.
+    ///
+    /// Unlike `snapshot`, only the free blocks are copied, not the arena bytes. This suits phase-based allocation:
+    /// take a checkpoint, allocate the values of the phase, then rewind instead of deallocating each one.
+    ///
+    /// The free blocks are copied to a vector allocated while the allocator lock is released, and `rewind` doesn't
+    /// allocate, so both can be called on the global allocator.
+    pub fn checkpoint(&self) -> FreeListCheckpoint {
+        self.heap().checkpoint()
+    }
+
+    /// Release every allocation made since the given checkpoint was taken by `checkpoint`.
+    ///
+    /// Allocations older than the checkpoint are left untouched: those deallocated since the checkpoint stay released,
+    /// and contiguous free blocks are merged. However, the block of an older allocation reused by a newer one
+    /// can't be told apart from a live older allocation, and stays allocated: deallocate older allocations after
+    /// rewinding to avoid this. The allocation counters aren't updated, and the cumulative size
+    /// of the live values (see `internal_fragmentation_bytes`) is restored from the checkpoint.
+    ///
+    /// **Panics**: if the checkpoint was taken from another allocator
+    ///
+    /// # Safety
+    /// Every allocation made since the checkpoint is invalidated: no reference to them may be used afterwards,
+    /// and they must not be deallocated.
+    pub unsafe fn rewind(&self, checkpoint: &FreeListCheckpoint) {
+        self.heap().rewind(checkpoint);
+    }
+
+    /// Deallocate the given allocations at once, as `(pointer, layout)` pairs.
+    ///
+    /// The free list is walked a single time, instead of once per deallocation: this is much faster than
+    /// calling `dealloc` in a loop to tear down a large structure. The slice is sorted by address in place,
+    /// so that nothing needs to be allocated. Null pointers are ignored.
+    ///
+    /// # Safety
+    /// Each pair must be valid for `GlobalAlloc::dealloc`, and no pointer may appear twice.
+    pub unsafe fn dealloc_batch(&self, allocations: &mut [(*mut u8, Layout)]) {
+        self.heap().dealloc_batch(allocations);
+    }
+
+    /// Walk the free list and fix the inconsistencies it can handle, as a last resort to keep running
+    /// after a bug corrupted the free list:
+    /// - Nodes that aren't sorted by address are moved to their place
+    /// - Contiguous or overlapping Nodes are merged
+    /// - Nodes extending past the arena end are shortened
+    /// - Nodes outside of the arena, misaligned or too small are dropped, along with the rest of the list when their
+    ///   link can't be trusted. Their bytes are lost until the allocator is dropped.
+    ///
+    /// # Safety
+    /// The free list locations and sizes are trusted to lie outside of live allocations: a Node overlapping
+    /// a live allocation is kept, and the allocation is overwritten by later allocations.
+    pub unsafe fn repair(&self) -> RepairReport {
+        self.root().lock().unwrap().repair()
+    }
+
+    /// Get the allocator root, allocating the arena on first call.
+    fn root(&self) -> &Mutex<AllocatorRoot> {
+        self.init.call_once(|| {
+            let arena_ptr = match arena_layout(S) {
+                Some(layout) => unsafe { GlobalAlloc::alloc(&System, layout) },
+                None => null_mut(),
+            };
+
+            *self.allocator.lock().unwrap() = unsafe { AllocatorRoot::new(arena_ptr, S) };
+        });
+        &self.allocator
+    }
+
+    fn heap(&self) -> FreeListHeap<'_> {
+        FreeListHeap {
+            root: self.root(),
+            spill: &[],
+            config: &self.config,
+            counters: &self.counters,
+        }
+    }
+
+    /// Touch every free page of the arena, so that the system commits them up front instead of on first use.
+    /// This removes page fault latency spikes from later allocations, typically when called at startup.
+    /// The arena is allocated first if it wasn't yet.
+    ///
+    /// Pages are read and written back with the same value, so the arena contents are unchanged.
+    /// Only free blocks are touched: pages of allocated blocks were committed by their allocation.
+    pub fn prefault(&self) {
+        self.heap().prefault()
+    }
+
+    /// Merge the contiguous free blocks of the arena.
+    ///
+    /// Blocks are merged on deallocation by default, so this is only needed when `FreeListConfig::coalesce_on_free`
+    /// is disabled: the free list is then sorted by address and merged, which should be scheduled off the hot path.
+    ///
+    /// **Returns**: Count of merged blocks
+    pub fn defragment(&self) -> usize {
+        self.heap().defragment()
+    }
+
+    /// Give the arena back to the `System` allocator if no allocation is live, to reduce the memory footprint
+    /// between bursts of allocations. The next allocation allocates a new arena, at a different address:
+    /// snapshots and checkpoints taken before can't be restored anymore.
+    ///
+    /// The arena is only released if it is idle, a single free block spanning all of it,
+    /// so that nothing is invalidated. Allocations served by the `System` allocator aren't taken into account.
+    ///
+    /// Queries such as `free_bytes`, `can_allocate` and `stats` count a released arena as a single free block.
+    /// Taking a snapshot or a checkpoint allocates it again, like the next allocation.
+    ///
+    /// **Returns**: true if the arena was released, false if it wasn't allocated or if allocations are live
+    pub fn trim(&self) -> bool {
+        unsafe { self.root().lock().unwrap().trim() }
+    }
 
-            previous_node = node;
+    /// Check that the free list is well-formed, panicking otherwise. This is a no-op in release builds.
+    ///
+    /// It verifies that free nodes are strictly sorted by address, that no two nodes are
+    /// physically contiguous (they should have been merged) and that no node extends past the arena end.
+    /// Order and contiguity aren't checked while deallocations without coalescing left the free list unsorted.
+    pub fn debug_assert_valid(&self) {
+        if cfg!(debug_assertions) {
+            unsafe { self.root().lock().unwrap().assert_valid() };
         }
+    }
+}
 
-        // Failed to find a suitable space
-        null_mut()
+unsafe impl<const S: usize> GlobalAlloc for FreeListAllocator<S> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.heap().alloc(layout)
     }
 
     unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
-        let mut allocator = self.allocator.lock().unwrap();
+        self.heap().dealloc(ptr, layout)
+    }
 
-        // Get allocation metadata
-        let metadata = {
-            let metadata_ptr = ptr.add(layout.size());
-            ptr::read(metadata_ptr as *mut AllocationMetadata)
-        };
-        // Get start of block
-        let block_ptr = ptr.sub(metadata.align_padding);
-
-        allocator.create_free_node(
-            block_ptr,
-            metadata.align_padding
-                + layout.size()
-                + ALLOCATION_METADATA_LAYOUT_SIZE
-                + metadata.fill_padding,
-        );
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        self.heap().realloc(ptr, layout, new_size)
+    }
+}
+
+impl<const S: usize> RegionAllocator for FreeListAllocator<S> {
+    /// Release every allocation of the arena at once. Allocations served by the `System` allocator
+    /// (see `FreeListConfig::system_fallback`) are kept, and allocation counters aren't reset.
+    unsafe fn reset(&self) {
+        self.heap().reset()
     }
 }
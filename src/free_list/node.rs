@@ -1,6 +1,8 @@
-use std::alloc::Layout;
+use super::config::MetadataPlacement;
+use std::{alloc::Layout, ptr};
 
 pub(crate) const NODE_LAYOUT_SIZE: usize = Layout::new::<Node>().size();
+pub(crate) const NODE_LAYOUT_ALIGN: usize = Layout::new::<Node>().align();
 pub(crate) const ALLOCATION_METADATA_LAYOUT_SIZE: usize =
     Layout::new::<AllocationMetadata>().size();
 
@@ -12,42 +14,78 @@ pub(crate) struct Node {
 impl Node {
     /// Check if the given parameters are suitable for an allocation in terms of available space.
     /// If the allocation is possible, retrieve allocation specs.
+    ///
+    /// A free Node is split off after the allocation only if at least `min_block_size` bytes remain,
+    /// otherwise the remaining bytes are added to the fill padding.
+    ///
+    /// `header_size` bytes are reserved at the block start, before the value. The header is part of the allocation padding,
+    /// as is the allocation metadata when it is placed before the value.
+    ///
+    /// The allocation metadata takes the same space wherever it is placed: a Node of `n` bytes can hold a value of at most
+    /// `n - ALLOCATION_METADATA_LAYOUT_SIZE` bytes, less if alignment padding or a header is needed.
+    /// Such an exact fit uses the whole Node, with no fill padding.
     pub fn try_get_alloc_specs(
         &self,
         size: usize,
         align: usize,
         ptr: *const u8,
+        min_block_size: usize,
+        header_size: usize,
+        placement: MetadataPlacement,
     ) -> Result<AllocationSpecs, ()> {
         if size > self.size {
             // Fast out: not enough bytes available
             return Err(());
         }
 
-        let alloc_padding = (align - (ptr as usize % align)) % align;
-        let alloc_size = alloc_padding + size + ALLOCATION_METADATA_LAYOUT_SIZE;
+        let reserved_size = header_size + placement.leading_size();
+        let value_ptr = (ptr as usize).checked_add(reserved_size).ok_or(())?;
+        let alloc_padding = reserved_size + (align - (value_ptr % align)) % align;
+        if alloc_padding > MAX_METADATA_PADDING {
+            // Padding can't be encoded in the allocation metadata
+            return Err(());
+        }
+        // Checked arithmetic: adversarial layouts or a corrupted Node must not wrap around
+        let alloc_size = alloc_padding
+            .checked_add(size)
+            .and_then(|size| size.checked_add(placement.trailing_size()))
+            .ok_or(())?;
+
+        // The block needs to be able to fit a Node once it's deallocated
+        let block_size = alloc_size.max(NODE_LAYOUT_SIZE);
+        // A Node placed after the block must be aligned
+        let block_end = (ptr as usize).checked_add(block_size).ok_or(())?;
+        let node_padding =
+            (NODE_LAYOUT_ALIGN - (block_end % NODE_LAYOUT_ALIGN)) % NODE_LAYOUT_ALIGN;
 
         // Valid if padding + size + alloc metadata can fit inside
-        // It also needs to be able to fit a Node once it's deallocated
-        if self.size > alloc_size + NODE_LAYOUT_SIZE {
-            // Can add a Node after allocation
-            let fill_padding = if NODE_LAYOUT_SIZE <= alloc_size {
-                // Handle usize overflow
-                0
-            } else {
-                NODE_LAYOUT_SIZE - alloc_size
-            };
+        let split_size =
+            (block_size + node_padding).saturating_add(min_block_size.max(NODE_LAYOUT_SIZE));
+        if self.size >= split_size {
+            // Can add a Node after allocation, even if exactly `min_block_size` bytes remain
+            let fill_padding = block_size - alloc_size + node_padding;
             Ok(AllocationSpecs {
                 padding: alloc_padding,
+                header_size,
                 size,
+                align,
                 fill_padding,
                 remaining_size: self.size - alloc_size - fill_padding,
+                placement,
             })
-        } else if alloc_size <= self.size && self.size >= NODE_LAYOUT_SIZE {
+        } else if alloc_size <= self.size
+            && self.size >= NODE_LAYOUT_SIZE
+            && self.size - alloc_size <= MAX_METADATA_PADDING
+        {
+            // Remaining bytes are given to the allocation, as long as they can be encoded in metadata
             Ok(AllocationSpecs {
                 padding: alloc_padding,
+                header_size,
                 size,
+                align,
                 fill_padding: self.size - alloc_size,
                 remaining_size: 0,
+                placement,
             })
         } else {
             // Padding and metadata causes the allocation to fail: not enough bytes available
@@ -58,18 +96,101 @@ impl Node {
 
 /// Specifications of a new allocation. It contains all sizes required to allocate.
 pub(crate) struct AllocationSpecs {
-    /// Allocation padding (to add before value)
+    /// Allocation padding (to add before value), header and leading metadata included
     pub padding: usize,
+    /// Size of the header at the block start, 0 if there is none
+    pub header_size: usize,
     /// Size of the value to allocate
     pub size: usize,
+    /// Alignment of the value to allocate
+    pub align: usize,
     /// Fill padding (to add after metadata)
     pub fill_padding: usize,
     /// Remaining size if it can at least contain a Node
     pub remaining_size: usize,
+    /// Location of the allocation metadata
+    pub placement: MetadataPlacement,
+}
+
+/// Size of the `LiveHeader` placed at the start of blocks when live allocations are tracked.
+pub(crate) const LIVE_HEADER_LAYOUT_SIZE: usize = Layout::new::<LiveHeader>().size();
+
+/// Header placed at the start of each block when live allocations are tracked
+/// (see `FreeListConfig::live_tracking`), allowing to walk allocated blocks.
+///
+/// Blocks start at Node-aligned locations, so the header is always aligned.
+pub(crate) struct LiveHeader {
+    /// Offset of the value from the block start
+    pub value_offset: usize,
+    /// Size of the value
+    pub value_size: usize,
+    /// Alignment of the value
+    pub value_align: usize,
+    /// Allocation sequence number of the value in its arena
+    #[cfg(feature = "alloc_age")]
+    pub sequence: usize,
 }
 
+/// Largest padding value that can be stored in an `AllocationMetadata`.
+pub(crate) const MAX_METADATA_PADDING: usize = u32::MAX as usize;
+
+/// Value written in each `AllocationMetadata` of debug builds, checked before the metadata is used.
+#[cfg(debug_assertions)]
+pub(crate) const METADATA_MAGIC: u32 = 0xA110_CA7E;
+
 /// Metadata added to each allocation in order to handle its deallocation.
+///
+/// Paddings are stored as `u32` to halve the per-allocation overhead on 64-bit targets.
+/// Since it is placed right after or right before the allocated value (see `MetadataPlacement`), it may be unaligned:
+/// always use `AllocationMetadata::read` and `AllocationMetadata::write` to access it.
+///
+/// The alignment padding counts the bytes from the block start to the metadata or the value, whichever comes first,
+/// so that the block size is always the sum of the paddings, the value size and the metadata size.
+///
+/// In debug builds, the allocation alignment is also recorded (as a power of two exponent)
+/// so that deallocation can validate the provided layout, along with a magic number revealing
+/// metadata overwritten by a write past the end of the value.
 pub(crate) struct AllocationMetadata {
-    pub align_padding: usize,
-    pub fill_padding: usize,
+    pub align_padding: u32,
+    pub fill_padding: u32,
+    #[cfg(debug_assertions)]
+    pub align_shift: u32,
+    #[cfg(debug_assertions)]
+    pub magic: u32,
+}
+
+impl AllocationMetadata {
+    /// Build metadata from allocation specs.
+    ///
+    /// Paddings must have been validated against `MAX_METADATA_PADDING` beforehand.
+    pub fn from_specs(specs: &AllocationSpecs) -> Self {
+        AllocationMetadata {
+            align_padding: (specs.padding - specs.placement.leading_size()) as u32,
+            fill_padding: specs.fill_padding as u32,
+            #[cfg(debug_assertions)]
+            align_shift: specs.align.trailing_zeros(),
+            #[cfg(debug_assertions)]
+            magic: METADATA_MAGIC,
+        }
+    }
+
+    /// Check that the metadata wasn't overwritten since the allocation, panicking otherwise.
+    /// This is a no-op in release builds.
+    pub fn check_magic(&self) {
+        #[cfg(debug_assertions)]
+        assert!(
+            self.magic == METADATA_MAGIC,
+            "allocation metadata corrupted"
+        );
+    }
+
+    /// Read metadata stored at the given, possibly unaligned, location.
+    pub unsafe fn read(ptr: *const u8) -> Self {
+        ptr::read_unaligned(ptr as *const AllocationMetadata)
+    }
+
+    /// Write metadata at the given, possibly unaligned, location.
+    pub unsafe fn write(self, ptr: *mut u8) {
+        ptr::write_unaligned(ptr as *mut AllocationMetadata, self);
+    }
 }
@@ -0,0 +1,234 @@
+use super::{
+    alloc_root::AllocatorRoot,
+    arena_layout,
+    heap::FreeListHeap,
+    node::NODE_LAYOUT_SIZE,
+    stats::{AllocationCounters, FreeListStats},
+    FreeListConfig, ARENA_ALIGN,
+};
+use crate::region::RegionAllocator;
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+    ptr::null_mut,
+    sync::{
+        atomic::{AtomicPtr, AtomicUsize, Ordering},
+        Mutex, Once,
+    },
+};
+
+/// Free list allocator split into `K` independent arenas of `S` bytes, each one guarded by its own lock.
+///
+/// Each thread allocates in its own shard, assigned round-robin on its first allocation, so that threads
+/// rarely contend on the same lock. When its shard is exhausted, the allocation is tried in the other shards.
+/// Deallocations go back to the shard owning the pointer, wherever the calling thread is.
+///
+/// The shard arenas are carved from a single allocation, so the owning shard is found from the pointer offset,
+/// without locking any shard.
+///
+/// ## Usage
+/// ```
+/// use allocator::free_list::ShardedFreeListAllocator;
+///
+/// #[global_allocator]
+/// static ALLOCATOR: ShardedFreeListAllocator<4096, 4> = ShardedFreeListAllocator::new();
+/// ```
+pub struct ShardedFreeListAllocator<const S: usize, const K: usize> {
+    shards: [Mutex<AllocatorRoot>; K],
+    /// Start of the first shard arena, the other ones follow every `SHARD_STRIDE` bytes
+    arena_ptr: AtomicPtr<u8>,
+    init: Once,
+    config: FreeListConfig,
+    counters: AllocationCounters,
+}
+
+/// Source of the shard indexes assigned to threads.
+static NEXT_SHARD: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    /// Shard index of the current thread, assigned on first use. It is reduced modulo the shard count of each allocator.
+    // Const initialized without destructor, so that it never allocates: it is used inside the global allocator
+    static THREAD_SHARD: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+impl<const S: usize, const K: usize> ShardedFreeListAllocator<S, K> {
+    /// Distance between the starts of two shard arenas: shards start aligned like a standalone arena.
+    const SHARD_STRIDE: usize = S.next_multiple_of(ARENA_ALIGN);
+
+    #[allow(clippy::new_without_default)]
+    pub const fn new() -> Self {
+        Self::with_config(FreeListConfig::new())
+    }
+
+    /// Create an allocator using the given configuration, shared by all shards.
+    pub const fn with_config(config: FreeListConfig) -> Self {
+        const {
+            assert!(
+                S >= NODE_LAYOUT_SIZE,
+                "free list arena must be able to hold a free Node"
+            );
+            assert!(K > 0, "sharded allocator needs at least one shard");
+        };
+
+        ShardedFreeListAllocator {
+            shards: [const { Mutex::new(AllocatorRoot::empty()) }; K],
+            arena_ptr: AtomicPtr::new(null_mut()),
+            init: Once::new(),
+            config,
+            counters: AllocationCounters::new(),
+        }
+    }
+
+    /// Get the total size of the shard arenas, in bytes.
+    pub const fn capacity(&self) -> usize {
+        S * K
+    }
+
+    /// Get the count of shards.
+    pub const fn shard_count(&self) -> usize {
+        K
+    }
+
+    /// Get the count of arena bytes currently in use, in all shards (see `FreeListAllocator::len`).
+    pub fn len(&self) -> usize {
+        self.shards()
+            .iter()
+            .map(|shard| unsafe { shard.lock().unwrap().used_bytes() })
+            .sum()
+    }
+
+    /// Check if no arena byte is currently in use (see `len`).
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get a snapshot of the allocation activity counters and of the arena usage, summed over all shards
+    /// (see `FreeListAllocator::stats`).
+    ///
+    /// Shards are locked one after another. `largest_free_block` is the largest free block of any shard,
+    /// and `peak_used` the sum of the shard peaks, which may not have been reached at the same time.
+    pub fn stats(&self) -> FreeListStats {
+        let mut stats = self.counters.snapshot();
+        for shard in self.shards() {
+            let mut shard_stats = stats;
+            unsafe { shard.lock().unwrap().arena_stats(&mut shard_stats) };
+            stats.free_bytes += shard_stats.free_bytes;
+            stats.used_bytes += shard_stats.used_bytes;
+            stats.largest_free_block = stats.largest_free_block.max(shard_stats.largest_free_block);
+            stats.free_node_count += shard_stats.free_node_count;
+            stats.peak_used += shard_stats.peak_used;
+        }
+        stats
+    }
+
+    /// Get the count of allocator operations which had to wait for a shard lock, in all shards
+    /// (see `FreeListAllocator::lock_contention_count`).
+    #[cfg(feature = "lock_contention")]
+    pub fn lock_contention_count(&self) -> usize {
+        self.counters.contended_locks()
+    }
+
+    /// Check that the free list of every shard is well-formed, panicking otherwise. This is a no-op in release builds.
+    pub fn debug_assert_valid(&self) {
+        if cfg!(debug_assertions) {
+            for shard in self.shards() {
+                unsafe { shard.lock().unwrap().assert_valid() };
+            }
+        }
+    }
+
+    /// Get the shard roots, allocating the shard arenas on first call.
+    ///
+    /// If the arenas can't be allocated, every shard is left empty and every allocation returns null.
+    fn shards(&self) -> &[Mutex<AllocatorRoot>; K] {
+        self.init.call_once(|| {
+            let arena_ptr = match Self::SHARD_STRIDE.checked_mul(K).and_then(arena_layout) {
+                Some(layout) => unsafe { GlobalAlloc::alloc(&System, layout) },
+                None => null_mut(),
+            };
+            if arena_ptr.is_null() {
+                return;
+            }
+
+            for (index, shard) in self.shards.iter().enumerate() {
+                let shard_ptr = unsafe { arena_ptr.add(index * Self::SHARD_STRIDE) };
+                *shard.lock().unwrap() = unsafe { AllocatorRoot::new(shard_ptr, S) };
+            }
+            self.arena_ptr.store(arena_ptr, Ordering::Release);
+        });
+        &self.shards
+    }
+
+    /// Get the index of the shard of the calling thread.
+    fn thread_shard(&self) -> usize {
+        let index = THREAD_SHARD
+            .try_with(|shard| {
+                shard.get().unwrap_or_else(|| {
+                    let index = NEXT_SHARD.fetch_add(1, Ordering::Relaxed);
+                    shard.set(Some(index));
+                    index
+                })
+            })
+            // The thread local storage is being destroyed, on thread exit
+            .unwrap_or(0);
+        index % K
+    }
+
+    /// Get the index of the shard owning the given pointer. Pointers outside of the arenas are attributed to the first shard.
+    fn owning_shard(&self, ptr: *const u8) -> usize {
+        let offset = (ptr as usize).wrapping_sub(self.arena_ptr.load(Ordering::Acquire) as usize);
+        let index = offset / Self::SHARD_STRIDE;
+        if index < K {
+            index
+        } else {
+            0
+        }
+    }
+
+    fn heap(&self, shard: usize) -> FreeListHeap<'_> {
+        let shards = self.shards();
+        FreeListHeap {
+            root: &shards[shard],
+            spill: shards,
+            config: &self.config,
+            counters: &self.counters,
+        }
+    }
+}
+
+unsafe impl<const S: usize, const K: usize> GlobalAlloc for ShardedFreeListAllocator<S, K> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.heap(self.thread_shard()).alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.heap(self.owning_shard(ptr)).dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        // Shrunk in the owning shard, or moved to any shard
+        self.heap(self.owning_shard(ptr))
+            .realloc(ptr, layout, new_size)
+    }
+}
+
+impl<const S: usize, const K: usize> RegionAllocator for ShardedFreeListAllocator<S, K> {
+    /// Release every allocation of all shards at once (see `FreeListAllocator::reset`).
+    unsafe fn reset(&self) {
+        for shard in 0..K {
+            self.heap(shard).reset();
+        }
+    }
+}
+
+impl<const S: usize, const K: usize> Drop for ShardedFreeListAllocator<S, K> {
+    fn drop(&mut self) {
+        let arena_ptr = *self.arena_ptr.get_mut();
+        if arena_ptr.is_null() {
+            return;
+        }
+
+        let layout = arena_layout(Self::SHARD_STRIDE * K).unwrap();
+        unsafe { GlobalAlloc::dealloc(&System, arena_ptr, layout) };
+    }
+}
@@ -0,0 +1,141 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Snapshot of the allocation activity and the arena usage of a free list allocator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FreeListStats {
+    /// Count of free arena bytes
+    pub free_bytes: usize,
+    /// Count of arena bytes in use, allocation paddings and metadata included
+    pub used_bytes: usize,
+    /// Size of the largest free block, in bytes: larger allocations can't be served by the arena
+    pub largest_free_block: usize,
+    /// Count of free blocks
+    pub free_node_count: usize,
+    /// Highest count of arena bytes in use since creation
+    pub peak_used: usize,
+    /// Count of successful allocations since creation
+    pub allocations: usize,
+    /// Count of deallocations since creation
+    pub deallocations: usize,
+    /// Count of allocations that weren't deallocated yet
+    pub live_allocations: usize,
+    /// Cumulative size of all successful allocation requests, in bytes
+    pub bytes_requested: usize,
+    /// Count of allocations served by the `System` allocator because the arena was exhausted
+    /// (see `FreeListConfig::system_fallback`)
+    pub system_allocations: usize,
+    /// Count of reallocations resized in place, without copying the value
+    pub realloc_in_place: usize,
+    /// Count of reallocations served by a new allocation, the value being copied to it
+    pub realloc_copied: usize,
+}
+
+/// Count of size classes in the allocation size histogram (see `FreeListConfig::size_histogram`).
+pub const SIZE_CLASSES: usize = 32;
+
+/// Activity counters, updated on each allocation and deallocation.
+///
+/// Atomics are used so that reading a snapshot doesn't contend the allocator lock.
+pub(crate) struct AllocationCounters {
+    allocations: AtomicUsize,
+    deallocations: AtomicUsize,
+    live_allocations: AtomicUsize,
+    bytes_requested: AtomicUsize,
+    system_allocations: AtomicUsize,
+    realloc_in_place: AtomicUsize,
+    realloc_copied: AtomicUsize,
+    size_classes: [AtomicUsize; SIZE_CLASSES],
+    /// Count of allocator lock acquisitions which had to wait for another thread
+    #[cfg(feature = "lock_contention")]
+    contended_locks: AtomicUsize,
+}
+
+impl AllocationCounters {
+    pub(crate) const fn new() -> Self {
+        AllocationCounters {
+            allocations: AtomicUsize::new(0),
+            deallocations: AtomicUsize::new(0),
+            live_allocations: AtomicUsize::new(0),
+            bytes_requested: AtomicUsize::new(0),
+            system_allocations: AtomicUsize::new(0),
+            realloc_in_place: AtomicUsize::new(0),
+            realloc_copied: AtomicUsize::new(0),
+            size_classes: [const { AtomicUsize::new(0) }; SIZE_CLASSES],
+            #[cfg(feature = "lock_contention")]
+            contended_locks: AtomicUsize::new(0),
+        }
+    }
+
+    /// Register a successful allocation of the given size.
+    pub(crate) fn record_alloc(&self, size: usize) {
+        self.allocations.fetch_add(1, Ordering::Relaxed);
+        self.live_allocations.fetch_add(1, Ordering::Relaxed);
+        self.bytes_requested.fetch_add(size, Ordering::Relaxed);
+    }
+
+    /// Register an allocation served by the `System` allocator, in addition to `record_alloc`.
+    pub(crate) fn record_system_alloc(&self) {
+        self.system_allocations.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Register an allocation of the given size in its size class.
+    ///
+    /// Class `i` counts sizes in `(2^(i-1), 2^i]`, the last class also counts every larger size.
+    pub(crate) fn record_size_class(&self, size: usize) {
+        let class = size
+            .checked_next_power_of_two()
+            .map_or(SIZE_CLASSES, |power| power.trailing_zeros() as usize)
+            .min(SIZE_CLASSES - 1);
+        self.size_classes[class].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Register a successful reallocation, resized in place or copied to a new allocation.
+    pub(crate) fn record_realloc(&self, in_place: bool) {
+        if in_place {
+            self.realloc_in_place.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.realloc_copied.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Register a deallocation.
+    pub(crate) fn record_dealloc(&self) {
+        self.deallocations.fetch_add(1, Ordering::Relaxed);
+        self.live_allocations.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// Read the counters. Arena usage fields are left to 0, they are filled by walking the free list.
+    pub(crate) fn snapshot(&self) -> FreeListStats {
+        FreeListStats {
+            free_bytes: 0,
+            used_bytes: 0,
+            largest_free_block: 0,
+            free_node_count: 0,
+            peak_used: 0,
+            allocations: self.allocations.load(Ordering::Relaxed),
+            deallocations: self.deallocations.load(Ordering::Relaxed),
+            live_allocations: self.live_allocations.load(Ordering::Relaxed),
+            bytes_requested: self.bytes_requested.load(Ordering::Relaxed),
+            system_allocations: self.system_allocations.load(Ordering::Relaxed),
+            realloc_in_place: self.realloc_in_place.load(Ordering::Relaxed),
+            realloc_copied: self.realloc_copied.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Register an allocator lock acquisition which had to wait for another thread.
+    #[cfg(feature = "lock_contention")]
+    pub(crate) fn record_contention(&self) {
+        self.contended_locks.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[cfg(feature = "lock_contention")]
+    pub(crate) fn contended_locks(&self) -> usize {
+        self.contended_locks.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn size_histogram(&self) -> [usize; SIZE_CLASSES] {
+        self.size_classes
+            .each_ref()
+            .map(|count| count.load(Ordering::Relaxed))
+    }
+}
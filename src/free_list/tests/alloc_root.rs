@@ -4,7 +4,7 @@ use std::{
     sync::atomic::{AtomicPtr, Ordering},
 };
 
-use crate::free_list::{alloc_root::*, node::Node};
+use crate::free_list::{alloc_root::*, node::Node, FitStrategy, FreeListConfig, RepairReport};
 
 #[test]
 fn create_free_node_no_root_becomes_root() {
@@ -105,6 +105,97 @@ fn create_free_node_previous_node_exists_doesnt_become_root() {
     );
 }
 
+#[test]
+fn create_free_node_between_non_adjacent_nodes_is_linked() {
+    let mut alloc_data = init_allocator::<160>(vec![
+        TestNode {
+            size: 32,
+            free: true, // Current root
+        },
+        TestNode {
+            size: 32,
+            free: false,
+        },
+        TestNode {
+            size: 32,
+            free: false,
+        },
+        TestNode {
+            size: 32,
+            free: false,
+        },
+        TestNode {
+            size: 32,
+            free: true,
+        },
+    ]);
+
+    unsafe {
+        alloc_data
+            .allocator
+            .create_free_node(alloc_data.ptr_collection[2] as *mut u8, 32)
+    };
+
+    // The previous Node links to the new one, which links to the next one
+    let free_nodes: Vec<_> = unsafe { alloc_data.allocator.free_nodes() }
+        .map(|(ptr, node)| (ptr, node.size))
+        .collect();
+    assert_eq!(
+        vec![
+            (alloc_data.ptr_collection[0], 32),
+            (alloc_data.ptr_collection[2], 32),
+            (alloc_data.ptr_collection[4], 32)
+        ],
+        free_nodes
+    );
+    unsafe { alloc_data.allocator.assert_valid() };
+}
+
+#[test]
+fn create_free_node_merged_with_next_only_is_linked() {
+    let mut alloc_data = init_allocator::<160>(vec![
+        TestNode {
+            size: 32,
+            free: true, // Current root
+        },
+        TestNode {
+            size: 32,
+            free: false,
+        },
+        TestNode {
+            size: 32,
+            free: false,
+        },
+        TestNode {
+            size: 32,
+            free: true,
+        },
+        TestNode {
+            size: 32,
+            free: false,
+        },
+    ]);
+
+    unsafe {
+        alloc_data
+            .allocator
+            .create_free_node(alloc_data.ptr_collection[2] as *mut u8, 32)
+    };
+
+    // The previous Node links to the merged Node, instead of the former next Node location
+    let free_nodes: Vec<_> = unsafe { alloc_data.allocator.free_nodes() }
+        .map(|(ptr, node)| (ptr, node.size))
+        .collect();
+    assert_eq!(
+        vec![
+            (alloc_data.ptr_collection[0], 32),
+            (alloc_data.ptr_collection[2], 64)
+        ],
+        free_nodes
+    );
+    unsafe { alloc_data.allocator.assert_valid() };
+}
+
 #[test]
 fn find_insertion_point_at_root() {
     let alloc_data = init_allocator::<128>(vec![
@@ -199,6 +290,36 @@ fn find_insertion_point_at_end() {
     assert_eq!(None, next);
 }
 
+#[test]
+#[should_panic(expected = "free list cycle detected")]
+fn find_insertion_point_cycle_panics() {
+    let alloc_data = init_allocator::<128>(vec![
+        TestNode {
+            size: 32,
+            free: true,
+        },
+        TestNode {
+            size: 32,
+            free: true,
+        },
+        TestNode {
+            size: 64,
+            free: false,
+        },
+    ]);
+
+    // Corrupt the list: the second node links back to the first one
+    let second_ptr = alloc_data.ptr_collection[1] as *mut Node;
+    unsafe { (*second_ptr).next_ptr = Some(alloc_data.ptr_collection[0]) };
+
+    unsafe {
+        alloc_data.allocator.find_insertion_point(
+            alloc_data.ptr_collection[2],
+            alloc_data.free_root_ptr.unwrap(),
+        )
+    };
+}
+
 #[test]
 fn try_merge_nodes_can_merge_previous() {
     let alloc_data = init_allocator::<128>(vec![
@@ -450,6 +571,518 @@ fn try_merge_nodes_cannot_merge_any_none_previous_and_next() {
     assert_eq!(alloc_data.ptr_collection[1], destination_ptr);
 }
 
+#[test]
+fn new_null_arena_cannot_allocate() {
+    let mut allocator = unsafe { AllocatorRoot::new(null_mut(), 128) };
+
+    assert!(allocator.free_root.is_none());
+    let ptr = unsafe { allocator.alloc(Layout::new::<u64>(), &FreeListConfig::new()) };
+    assert!(ptr.is_null());
+}
+
+#[test]
+fn defragment_merges_contiguous_nodes() {
+    let mut alloc_data = init_allocator::<256>(vec![
+        TestNode {
+            size: 32,
+            free: true,
+        },
+        TestNode {
+            size: 32,
+            free: true,
+        },
+        TestNode {
+            size: 32,
+            free: true,
+        },
+        TestNode {
+            size: 32,
+            free: false,
+        },
+        TestNode {
+            size: 32,
+            free: true,
+        },
+    ]);
+
+    let merged = unsafe { alloc_data.allocator.defragment() };
+
+    assert_eq!(2, merged);
+    let mut blocks = Vec::new();
+    unsafe { alloc_data.allocator.free_blocks(&mut blocks) };
+    assert_eq!(2, blocks.len());
+    assert_eq!(32 * 3, blocks[0].size);
+    unsafe { alloc_data.allocator.assert_valid() };
+}
+
+#[test]
+fn alloc_defragments_on_failure() {
+    // Contiguous free Nodes are only left by deallocations without coalescing
+    let config = FreeListConfig {
+        coalesce_on_free: false,
+        ..FreeListConfig::new()
+    };
+    let arena_layout = crate::free_list::arena_layout(128).unwrap();
+    let arena_ptr = unsafe { GlobalAlloc::alloc(&System, arena_layout) };
+    let mut allocator = unsafe { AllocatorRoot::new(arena_ptr, 128) };
+    let layout = Layout::from_size_align(32, 1).unwrap();
+    unsafe {
+        let first = allocator.alloc(layout, &config);
+        let second = allocator.alloc(layout, &config);
+        allocator.dealloc(first, layout, &config);
+        allocator.dealloc(second, layout, &config);
+    }
+
+    // Doesn't fit in any single node, but fits once they are merged
+    let large = Layout::from_size_align(80, 1).unwrap();
+    assert!(!unsafe { allocator.can_allocate(large, &config) });
+    let ptr = unsafe { allocator.alloc(large, &config) };
+
+    assert!(allocator.contains(ptr));
+    unsafe {
+        allocator.assert_valid();
+        GlobalAlloc::dealloc(&System, arena_ptr, arena_layout);
+    }
+}
+
+#[test]
+fn alloc_first_fit_uses_lowest_address_node() {
+    let mut alloc_data = init_allocator::<128>(vec![
+        TestNode {
+            size: 64,
+            free: true,
+        },
+        TestNode {
+            size: 32,
+            free: false,
+        },
+        TestNode {
+            size: 32,
+            free: true,
+        },
+    ]);
+
+    let ptr = unsafe {
+        alloc_data.allocator.alloc(
+            Layout::from_size_align(8, 1).unwrap(),
+            &FreeListConfig::new(),
+        )
+    };
+
+    assert_eq!(alloc_data.ptr_collection[0], ptr as *const u8);
+}
+
+#[test]
+fn alloc_best_fit_uses_smallest_node() {
+    let mut alloc_data = init_allocator::<128>(vec![
+        TestNode {
+            size: 64,
+            free: true,
+        },
+        TestNode {
+            size: 32,
+            free: false,
+        },
+        TestNode {
+            size: 32,
+            free: true,
+        },
+    ]);
+    let config = FreeListConfig {
+        fit_strategy: FitStrategy::BestFit,
+        ..FreeListConfig::new()
+    };
+
+    let ptr = unsafe {
+        alloc_data
+            .allocator
+            .alloc(Layout::from_size_align(8, 1).unwrap(), &config)
+    };
+
+    assert_eq!(alloc_data.ptr_collection[2], ptr as *const u8);
+    // The whole node is used, the previous node must now be the last one
+    assert_eq!(
+        vec![(alloc_data.ptr_collection[0], 64)],
+        unsafe { alloc_data.allocator.free_nodes() }
+            .map(|(ptr, node)| (ptr, node.size))
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn alloc_worst_fit_uses_largest_node() {
+    let mut alloc_data = init_allocator::<192>(vec![
+        TestNode {
+            size: 32,
+            free: true,
+        },
+        TestNode {
+            size: 32,
+            free: false,
+        },
+        TestNode {
+            size: 96,
+            free: true,
+        },
+        TestNode {
+            size: 32,
+            free: false,
+        },
+    ]);
+    let config = FreeListConfig {
+        fit_strategy: FitStrategy::WorstFit,
+        ..FreeListConfig::new()
+    };
+
+    let ptr = unsafe {
+        alloc_data
+            .allocator
+            .alloc(Layout::from_size_align(8, 1).unwrap(), &config)
+    };
+
+    assert_eq!(alloc_data.ptr_collection[2], ptr as *const u8);
+    unsafe { alloc_data.allocator.assert_valid() };
+}
+
+#[test]
+fn alloc_recent_free_cache_bypasses_fit_strategy() {
+    let mut alloc_data = init_allocator::<128>(vec![
+        TestNode {
+            size: 64,
+            free: false,
+        },
+        TestNode {
+            size: 32,
+            free: false,
+        },
+        TestNode {
+            size: 32,
+            free: true,
+        },
+    ]);
+    let config = FreeListConfig {
+        fit_strategy: FitStrategy::BestFit,
+        recent_free_cache: true,
+        ..FreeListConfig::new()
+    };
+
+    let freed_ptr = alloc_data.ptr_collection[0] as *mut u8;
+    unsafe { alloc_data.allocator.create_free_node(freed_ptr, 64) };
+    assert_eq!(
+        Some(RecentFree {
+            node_offset: 0,
+            previous_offset: None
+        }),
+        alloc_data.allocator.recent_free
+    );
+
+    // The smallest Node would be the last one, but the freed Node is reused
+    let ptr = unsafe {
+        alloc_data
+            .allocator
+            .alloc(Layout::from_size_align(8, 1).unwrap(), &config)
+    };
+    assert_eq!(freed_ptr, ptr);
+    // The Node was split, it isn't cached anymore
+    assert_eq!(None, alloc_data.allocator.recent_free);
+    unsafe { alloc_data.allocator.assert_valid() };
+}
+
+#[test]
+fn alloc_recent_free_cache_stale_entry_misses() {
+    let mut alloc_data = init_allocator::<128>(vec![
+        TestNode {
+            size: 64,
+            free: true,
+        },
+        TestNode {
+            size: 64,
+            free: false,
+        },
+    ]);
+    let config = FreeListConfig {
+        recent_free_cache: true,
+        ..FreeListConfig::new()
+    };
+
+    // Points inside the allocated block, which isn't linked from the free list
+    alloc_data.allocator.recent_free = Some(RecentFree {
+        node_offset: 64,
+        previous_offset: None,
+    });
+
+    let ptr = unsafe {
+        alloc_data
+            .allocator
+            .alloc(Layout::from_size_align(8, 1).unwrap(), &config)
+    };
+    assert_eq!(alloc_data.ptr_collection[0], ptr as *const u8);
+}
+
+#[test]
+fn alloc_past_arena_end_fails() {
+    let mut alloc_data = init_allocator::<128>(vec![
+        TestNode {
+            size: 64,
+            free: true,
+        },
+        TestNode {
+            size: 64,
+            free: false,
+        },
+    ]);
+
+    // Corrupt the free Node: its size exceeds the arena
+    let node_ptr = alloc_data.ptr_collection[0] as *mut Node;
+    unsafe { (*node_ptr).size = 4096 };
+
+    let ptr = unsafe {
+        alloc_data.allocator.alloc(
+            Layout::from_size_align(1024, 1).unwrap(),
+            &FreeListConfig::new(),
+        )
+    };
+    assert!(ptr.is_null());
+}
+
+#[test]
+#[should_panic(expected = "misaligned")]
+fn assert_valid_misaligned_node_panics() {
+    let mut alloc_data = init_allocator::<128>(vec![
+        TestNode {
+            size: 64,
+            free: false,
+        },
+        TestNode {
+            size: 64,
+            free: true,
+        },
+    ]);
+
+    // Move the free Node by one byte
+    let node_ptr = unsafe { alloc_data.ptr_collection[1].add(1) } as *mut u8;
+    unsafe {
+        ptr::write_unaligned(
+            node_ptr as *mut Node,
+            Node {
+                next_ptr: None,
+                size: 63,
+            },
+        )
+    };
+    alloc_data.allocator.free_root = Some(AtomicPtr::new(node_ptr));
+
+    unsafe { alloc_data.allocator.assert_valid() };
+}
+
+#[test]
+fn assert_valid_sorted_separated_nodes() {
+    let alloc_data = init_allocator::<128>(vec![
+        TestNode {
+            size: 32,
+            free: true,
+        },
+        TestNode {
+            size: 32,
+            free: false,
+        },
+        TestNode {
+            size: 64,
+            free: true,
+        },
+    ]);
+
+    unsafe { alloc_data.allocator.assert_valid() };
+}
+
+#[test]
+#[should_panic(expected = "contiguous")]
+fn assert_valid_contiguous_nodes_panic() {
+    let alloc_data = init_allocator::<128>(vec![
+        TestNode {
+            size: 32,
+            free: true,
+        },
+        TestNode {
+            size: 32,
+            free: true,
+        },
+        TestNode {
+            size: 64,
+            free: false,
+        },
+    ]);
+
+    unsafe { alloc_data.allocator.assert_valid() };
+}
+
+#[test]
+#[should_panic(expected = "past the arena end")]
+fn assert_valid_node_past_arena_end_panic() {
+    let alloc_data = init_allocator::<128>(vec![
+        TestNode {
+            size: 32,
+            free: false,
+        },
+        TestNode {
+            size: 96,
+            free: true,
+        },
+    ]);
+
+    // Corrupt the node size
+    let node_ptr = alloc_data.ptr_collection[1] as *mut Node;
+    unsafe { (*node_ptr).size = 128 };
+
+    unsafe { alloc_data.allocator.assert_valid() };
+}
+
+#[test]
+fn repair_valid_list_unchanged() {
+    let mut alloc_data = init_allocator::<128>(vec![
+        TestNode {
+            size: 32,
+            free: true,
+        },
+        TestNode {
+            size: 32,
+            free: false,
+        },
+        TestNode {
+            size: 64,
+            free: true,
+        },
+    ]);
+
+    let report = unsafe { alloc_data.allocator.repair() };
+    assert!(!report.changed());
+    unsafe { alloc_data.allocator.assert_valid() };
+}
+
+#[test]
+fn repair_sorts_merges_and_truncates() {
+    let mut alloc_data = init_allocator::<160>(vec![
+        TestNode {
+            size: 32,
+            free: true,
+        },
+        TestNode {
+            size: 32,
+            free: false,
+        },
+        TestNode {
+            size: 32,
+            free: true,
+        },
+        TestNode {
+            size: 32,
+            free: true,
+        },
+        TestNode {
+            size: 32,
+            free: false,
+        },
+    ]);
+    let [first, _, second, third, _] = alloc_data.ptr_collection[..] else {
+        unreachable!()
+    };
+    // Corrupt the list: third -> first -> second, third extending past the arena end
+    unsafe {
+        ptr::write(
+            third as *mut Node,
+            Node {
+                size: 4096,
+                next_ptr: Some(first),
+            },
+        );
+        ptr::write(
+            first as *mut Node,
+            Node {
+                size: 32,
+                next_ptr: Some(second),
+            },
+        );
+        ptr::write(
+            second as *mut Node,
+            Node {
+                size: 32,
+                next_ptr: None,
+            },
+        );
+    }
+    alloc_data.allocator.free_root = Some(AtomicPtr::new(third.cast_mut()));
+
+    let report = unsafe { alloc_data.allocator.repair() };
+    assert_eq!(
+        RepairReport {
+            reordered_nodes: 2,
+            merged_nodes: 1,
+            truncated_nodes: 1,
+            dropped_nodes: 0,
+        },
+        report
+    );
+    unsafe { alloc_data.allocator.assert_valid() };
+    assert_eq!(
+        vec![(first, 32), (second, 160 - 64)],
+        unsafe { alloc_data.allocator.free_nodes() }
+            .map(|(ptr, node)| (ptr, node.size))
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn repair_breaks_cycles_and_drops_invalid_nodes() {
+    let mut alloc_data = init_allocator::<128>(vec![
+        TestNode {
+            size: 32,
+            free: true,
+        },
+        TestNode {
+            size: 32,
+            free: false,
+        },
+        TestNode {
+            size: 32,
+            free: true,
+        },
+        TestNode {
+            size: 32,
+            free: false,
+        },
+    ]);
+    let (first, second) = (alloc_data.ptr_collection[0], alloc_data.ptr_collection[2]);
+    // second links back to first
+    unsafe {
+        ptr::write(
+            second as *mut Node,
+            Node {
+                size: 32,
+                next_ptr: Some(first),
+            },
+        )
+    };
+
+    let report = unsafe { alloc_data.allocator.repair() };
+    assert_eq!(1, report.dropped_nodes);
+    unsafe { alloc_data.allocator.assert_valid() };
+    assert_eq!(2, unsafe { alloc_data.allocator.free_nodes() }.count());
+
+    // Pointer outside of the arena
+    unsafe {
+        ptr::write(
+            second as *mut Node,
+            Node {
+                size: 32,
+                next_ptr: Some(std::ptr::dangling()),
+            },
+        )
+    };
+    let report = unsafe { alloc_data.allocator.repair() };
+    assert_eq!(1, report.dropped_nodes);
+    unsafe { alloc_data.allocator.assert_valid() };
+}
+
 /// Test utility function to generate an allocator populated with the given nodes
 ///
 /// **Notes**:
@@ -506,6 +1139,16 @@ fn init_allocator<const S: usize>(nodes: Vec<TestNode>) -> AllocatorData {
     AllocatorData {
         allocator: AllocatorRoot {
             free_root: atomic_root,
+            arena_ptr: AtomicPtr::new(arena_ptr),
+            arena_size: S,
+            live_bytes: 0,
+            allocated_bytes: 0,
+            peak_allocated_bytes: 0,
+            sorted: true,
+            recent_free: None,
+            released_size: None,
+            #[cfg(feature = "alloc_age")]
+            next_sequence: 0,
         },
         ptr_collection: node_ptr_collection,
         free_root_ptr,
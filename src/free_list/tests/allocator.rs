@@ -0,0 +1,1478 @@
+use std::{
+    alloc::{GlobalAlloc, Layout},
+    ptr,
+    sync::atomic::{AtomicPtr, AtomicUsize, Ordering},
+};
+
+#[cfg(feature = "alloc_age")]
+use crate::free_list::AgedAllocInfo;
+use crate::{
+    free_list::{
+        node::{ALLOCATION_METADATA_LAYOUT_SIZE, NODE_LAYOUT_ALIGN, NODE_LAYOUT_SIZE},
+        AllocEvent, AllocInfo, FailurePolicy, FreeBlock, FreeListAllocator, FreeListCheckpoint,
+        FreeListConfig, MetadataPlacement, ALLOCATION_METADATA_SIZE, ARENA_ALIGN, NODE_ALIGN,
+        NODE_SIZE, SIZE_CLASSES,
+    },
+    region::RegionAllocator,
+};
+
+#[test]
+fn alloc_zero_size_returns_aligned_dangling() {
+    let allocator = FreeListAllocator::<128>::new();
+    let layout = Layout::from_size_align(0, 16).unwrap();
+
+    let ptr = unsafe { allocator.alloc(layout) };
+
+    assert!(!ptr.is_null());
+    assert_eq!(0, ptr as usize % 16);
+}
+
+#[test]
+fn alloc_zero_size_doesnt_consume_arena() {
+    let allocator = FreeListAllocator::<128>::new();
+    let zst_layout = Layout::from_size_align(0, 8).unwrap();
+    let zst_ptr = unsafe { allocator.alloc(zst_layout) };
+    unsafe { allocator.dealloc(zst_ptr, zst_layout) };
+
+    // The whole arena is still available
+    let layout = Layout::from_size_align(128 - ALLOCATION_METADATA_LAYOUT_SIZE, 1).unwrap();
+    let ptr = unsafe { allocator.alloc(layout) };
+    assert!(!ptr.is_null());
+}
+
+#[test]
+fn stats_track_alloc_and_dealloc() {
+    let allocator = FreeListAllocator::<256>::new();
+    let layout = Layout::new::<u64>();
+
+    let first = unsafe { allocator.alloc(layout) };
+    let second = unsafe { allocator.alloc(layout) };
+    let stats = allocator.stats();
+    assert_eq!(2, stats.allocations);
+    assert_eq!(0, stats.deallocations);
+    assert_eq!(2, stats.live_allocations);
+    assert_eq!(2 * layout.size(), stats.bytes_requested);
+
+    unsafe {
+        allocator.dealloc(first, layout);
+        allocator.dealloc(second, layout);
+    }
+    let stats = allocator.stats();
+    assert_eq!(2, stats.allocations);
+    assert_eq!(2, stats.deallocations);
+    assert_eq!(0, stats.live_allocations);
+}
+
+#[test]
+fn stats_report_arena_usage() {
+    let allocator = FreeListAllocator::<512>::new();
+    let layout = Layout::new::<[u8; 64]>();
+
+    let first = unsafe { allocator.alloc(layout) };
+    let second = unsafe { allocator.alloc(layout) };
+    let third = unsafe { allocator.alloc(layout) };
+    unsafe { allocator.dealloc(second, layout) };
+
+    let stats = allocator.stats();
+    assert_eq!(allocator.free_bytes(), stats.free_bytes);
+    assert_eq!(allocator.len(), stats.used_bytes);
+    assert_eq!(512, stats.free_bytes + stats.used_bytes);
+    assert_eq!(2, stats.free_node_count);
+    let largest = allocator
+        .free_blocks()
+        .map(|block| block.size)
+        .max()
+        .unwrap();
+    assert_eq!(largest, stats.largest_free_block);
+    // The peak was reached while all three values were allocated
+    assert!(stats.peak_used > stats.used_bytes);
+    let peak = stats.peak_used;
+
+    unsafe {
+        allocator.dealloc(first, layout);
+        allocator.dealloc(third, layout);
+    }
+    let stats = allocator.stats();
+    assert_eq!(0, stats.used_bytes);
+    assert_eq!(512, stats.largest_free_block);
+    assert_eq!(peak, stats.peak_used);
+}
+
+#[test]
+fn stats_peak_used_tracks_shrink() {
+    let allocator = FreeListAllocator::<512>::new();
+    let layout = Layout::new::<[u8; 256]>();
+
+    let ptr = unsafe { allocator.alloc(layout) };
+    let peak = allocator.stats().peak_used;
+    assert_eq!(allocator.len(), peak);
+
+    let shrunk = unsafe { allocator.realloc(ptr, layout, 16) };
+    let stats = allocator.stats();
+    assert_eq!(peak, stats.peak_used);
+    assert!(stats.used_bytes < peak);
+
+    // Allocating again in the released tail doesn't exceed the previous peak
+    let other = unsafe { allocator.alloc(Layout::new::<[u8; 64]>()) };
+    assert_eq!(peak, allocator.stats().peak_used);
+    unsafe {
+        allocator.dealloc(shrunk, Layout::new::<[u8; 16]>());
+        allocator.dealloc(other, Layout::new::<[u8; 64]>());
+    }
+}
+
+#[test]
+fn stats_ignore_failed_alloc() {
+    let allocator = FreeListAllocator::<64>::new();
+    let layout = Layout::from_size_align(128, 1).unwrap();
+
+    let ptr = unsafe { allocator.alloc(layout) };
+    assert!(ptr.is_null());
+    assert_eq!(0, allocator.stats().allocations);
+}
+
+#[test]
+fn debug_assert_valid_after_alloc_dealloc_sequence() {
+    let allocator = FreeListAllocator::<512>::new();
+    let small = Layout::from_size_align(16, 8).unwrap();
+    let large = Layout::from_size_align(64, 8).unwrap();
+
+    let a = unsafe { allocator.alloc(small) };
+    let b = unsafe { allocator.alloc(small) };
+    let c = unsafe { allocator.alloc(small) };
+    allocator.debug_assert_valid();
+
+    // Free list: [a] -> [c + remaining arena]
+    unsafe {
+        allocator.dealloc(c, small);
+        allocator.dealloc(a, small);
+    }
+    allocator.debug_assert_valid();
+
+    // Doesn't fit in the first node, allocated from the second one
+    let d = unsafe { allocator.alloc(large) };
+    assert!(!d.is_null());
+    unsafe { ptr::write_bytes(d, 0xFF, large.size()) }; // Use the allocated memory
+    allocator.debug_assert_valid();
+
+    unsafe {
+        allocator.dealloc(b, small);
+        allocator.dealloc(d, large);
+    }
+    allocator.debug_assert_valid();
+}
+
+#[test]
+fn free_blocks_single_block_when_empty() {
+    let allocator = FreeListAllocator::<256>::new();
+
+    let blocks: Vec<FreeBlock> = allocator.free_blocks().collect();
+    assert_eq!(
+        vec![FreeBlock {
+            offset: 0,
+            size: 256
+        }],
+        blocks
+    );
+}
+
+#[test]
+fn free_blocks_reflect_allocations() {
+    let allocator = FreeListAllocator::<256>::new();
+    let layout =
+        Layout::from_size_align(NODE_LAYOUT_SIZE - ALLOCATION_METADATA_LAYOUT_SIZE, 1).unwrap();
+
+    let a = unsafe { allocator.alloc(layout) };
+    let b = unsafe { allocator.alloc(layout) };
+    unsafe { allocator.dealloc(a, layout) };
+
+    let blocks: Vec<FreeBlock> = allocator.free_blocks().collect();
+    assert_eq!(
+        vec![
+            FreeBlock {
+                offset: 0,
+                size: NODE_LAYOUT_SIZE
+            },
+            FreeBlock {
+                offset: 2 * NODE_LAYOUT_SIZE,
+                size: 256 - 2 * NODE_LAYOUT_SIZE
+            }
+        ],
+        blocks
+    );
+
+    // Freeing the middle block coalesces everything
+    unsafe { allocator.dealloc(b, layout) };
+    let blocks: Vec<FreeBlock> = allocator.free_blocks().collect();
+    assert_eq!(
+        vec![FreeBlock {
+            offset: 0,
+            size: 256
+        }],
+        blocks
+    );
+}
+
+#[test]
+fn alloc_offsets_are_deterministic() {
+    let allocator = FreeListAllocator::<256>::new();
+    let arena_start = {
+        let _ = allocator.free_blocks(); // Force initialization
+        allocator
+            .allocator
+            .lock()
+            .unwrap()
+            .arena_ptr
+            .load(Ordering::Acquire) as usize
+    };
+    let offset = |ptr: *mut u8| ptr as usize - arena_start;
+    let small = Layout::new::<u64>();
+    let large = Layout::new::<[u64; 4]>();
+
+    let a = unsafe { allocator.alloc(small) };
+    let b = unsafe { allocator.alloc(large) };
+    assert_eq!(0, offset(a));
+    assert_eq!(NODE_LAYOUT_SIZE, offset(b));
+
+    // The lowest-address free block is reused first
+    unsafe { allocator.dealloc(a, small) };
+    let c = unsafe { allocator.alloc(small) };
+    assert_eq!(0, offset(c));
+
+    let d = unsafe { allocator.alloc(small) };
+    assert_eq!(
+        NODE_LAYOUT_SIZE
+            + (large.size() + ALLOCATION_METADATA_LAYOUT_SIZE).next_multiple_of(NODE_LAYOUT_ALIGN),
+        offset(d)
+    );
+}
+
+#[test]
+fn system_fallback_when_arena_exhausted() {
+    let allocator = FreeListAllocator::<64>::with_config(FreeListConfig {
+        system_fallback: true,
+        ..FreeListConfig::new()
+    });
+    let layout = Layout::new::<[u8; 128]>();
+
+    let ptr = unsafe { allocator.alloc(layout) };
+    assert!(!ptr.is_null());
+    unsafe { ptr::write_bytes(ptr, 0xFF, layout.size()) };
+    assert_eq!(1, allocator.stats().system_allocations);
+
+    // Arena is untouched
+    unsafe { allocator.dealloc(ptr, layout) };
+    let blocks: Vec<FreeBlock> = allocator.free_blocks().collect();
+    assert_eq!(
+        vec![FreeBlock {
+            offset: 0,
+            size: 64
+        }],
+        blocks
+    );
+}
+
+#[test]
+fn system_fallback_disabled_returns_null() {
+    let allocator = FreeListAllocator::<64>::new();
+
+    let ptr = unsafe { allocator.alloc(Layout::new::<[u8; 128]>()) };
+    assert!(ptr.is_null());
+    assert_eq!(0, allocator.stats().system_allocations);
+}
+
+#[test]
+fn allocate_with_null_policy_returns_null() {
+    let allocator = FreeListAllocator::<64>::new();
+    let layout = Layout::new::<[u8; 16]>();
+
+    let ptr = allocator.allocate(layout);
+    assert!(!ptr.is_null());
+    assert!(allocator.allocate(Layout::new::<[u8; 128]>()).is_null());
+    unsafe { allocator.dealloc(ptr, layout) };
+    assert!(allocator.is_empty());
+}
+
+#[test]
+#[should_panic(expected = "can't allocate 128 bytes aligned to 1")]
+fn allocate_with_panic_policy_panics() {
+    let allocator = FreeListAllocator::<64>::with_config(FreeListConfig {
+        failure_policy: FailurePolicy::Panic,
+        ..FreeListConfig::new()
+    });
+    allocator.allocate(Layout::new::<[u8; 128]>());
+}
+
+#[test]
+fn global_alloc_ignores_failure_policy() {
+    let allocator = FreeListAllocator::<64>::with_config(FreeListConfig {
+        failure_policy: FailurePolicy::Panic,
+        ..FreeListConfig::new()
+    });
+
+    let ptr = unsafe { allocator.alloc(Layout::new::<[u8; 128]>()) };
+    assert!(ptr.is_null());
+}
+
+#[test]
+#[cfg(feature = "lock_contention")]
+fn lock_contention_counts_waiting_alloc() {
+    let allocator = FreeListAllocator::<1024>::new();
+    let layout = Layout::new::<u64>();
+    assert_eq!(0, allocator.lock_contention_count());
+
+    let guard = allocator.root().lock().unwrap();
+    let ptr = std::thread::scope(|scope| {
+        let thread = scope.spawn(|| unsafe { allocator.alloc(layout) } as usize);
+        // The contention is counted before waiting for the lock
+        while allocator.lock_contention_count() == 0 {
+            std::thread::yield_now();
+        }
+        drop(guard);
+        thread.join().unwrap() as *mut u8
+    });
+    assert!(!ptr.is_null());
+    assert_eq!(1, allocator.lock_contention_count());
+
+    unsafe { allocator.dealloc(ptr, layout) };
+    assert_eq!(1, allocator.lock_contention_count());
+}
+
+#[test]
+fn trim_releases_idle_arena() {
+    let allocator = FreeListAllocator::<1024>::with_config(FreeListConfig {
+        coalesce_on_free: false,
+        ..FreeListConfig::new()
+    });
+    let layout = Layout::new::<[u64; 4]>();
+    let first = unsafe { allocator.alloc(layout) };
+    let second = unsafe { allocator.alloc(layout) };
+    assert!(!allocator.trim());
+
+    unsafe {
+        allocator.dealloc(first, layout);
+        allocator.dealloc(second, layout);
+    }
+    assert!(allocator.trim());
+    assert!(!allocator.trim());
+    assert_eq!(0, allocator.len());
+    assert_eq!(
+        vec![FreeBlock {
+            offset: 0,
+            size: 1024
+        }],
+        allocator.free_blocks().collect::<Vec<_>>()
+    );
+
+    // The arena is allocated again on the next allocation
+    let ptr = unsafe { allocator.alloc(layout) };
+    assert!(!ptr.is_null());
+    assert_eq!(Some(0), allocator.offset_of(ptr));
+    allocator.debug_assert_valid();
+    unsafe { allocator.dealloc(ptr, layout) };
+    assert!(allocator.trim());
+}
+
+#[test]
+fn trimmed_arena_queries_count_it_free() {
+    let allocator = FreeListAllocator::<1024>::with_config(FreeListConfig {
+        live_tracking: true,
+        ..FreeListConfig::new()
+    });
+    let layout = Layout::new::<[u64; 4]>();
+    unsafe { allocator.dealloc(allocator.alloc(layout), layout) };
+    assert!(allocator.trim());
+
+    assert!(allocator.is_empty());
+    assert_eq!(1024, allocator.free_bytes());
+    assert_eq!(1, allocator.free_node_count());
+    assert!(allocator.can_allocate(layout));
+    assert!(!allocator.can_allocate(Layout::new::<[u8; 2048]>()));
+    let stats = allocator.stats();
+    assert_eq!(1024, stats.free_bytes);
+    assert_eq!(0, stats.used_bytes);
+    assert_eq!(1024, stats.largest_free_block);
+    assert_eq!(1, stats.free_node_count);
+    assert!(allocator.report_live().is_empty());
+    assert_eq!(None, allocator.offset_of(ptr::null()));
+    // Nothing is allocated by the queries
+    assert!(!allocator.trim());
+}
+
+#[test]
+fn trimmed_arena_checkpoint_and_snapshot() {
+    let allocator = FreeListAllocator::<1024>::new();
+    let layout = Layout::new::<[u64; 4]>();
+    unsafe { allocator.dealloc(allocator.alloc(layout), layout) };
+
+    assert!(allocator.trim());
+    let checkpoint = allocator.checkpoint();
+    unsafe { allocator.alloc(layout) };
+    unsafe { allocator.rewind(&checkpoint) };
+    assert_eq!(1024, allocator.free_bytes());
+
+    assert!(allocator.trim());
+    let snapshot = allocator.snapshot();
+    let ptr = unsafe { allocator.alloc(layout) };
+    assert!(!ptr.is_null());
+    unsafe { allocator.restore(&snapshot) };
+    assert_eq!(1024, allocator.free_bytes());
+    allocator.debug_assert_valid();
+}
+
+#[test]
+fn alloc_in_node_sized_arena_frees_whole_node() {
+    fn check<const S: usize>() {
+        let allocator = FreeListAllocator::<S>::new();
+        let layout = Layout::new::<u8>();
+        let ptr = unsafe { allocator.alloc(layout) };
+        assert!(!ptr.is_null());
+        assert_eq!(S, allocator.len());
+
+        unsafe { allocator.dealloc(ptr, layout) };
+        allocator.debug_assert_valid();
+        assert_eq!(
+            vec![FreeBlock { offset: 0, size: S }],
+            allocator.free_blocks().collect::<Vec<_>>()
+        );
+    }
+
+    check::<NODE_SIZE>();
+    check::<{ NODE_SIZE + 1 }>();
+}
+
+#[test]
+fn alloc_in_node_sized_free_block_frees_whole_node() {
+    let allocator = FreeListAllocator::<256>::new();
+    let layout = Layout::new::<u8>();
+    let ptrs = [(); 3].map(|_| unsafe { allocator.alloc(layout) });
+    let offset = allocator.offset_of(ptrs[1]).unwrap();
+    unsafe { allocator.dealloc(ptrs[1], layout) };
+    assert!(allocator.free_blocks().any(|block| block
+        == FreeBlock {
+            offset,
+            size: NODE_SIZE
+        }));
+
+    // First fit reuses the Node sized block, and gives it back whole
+    let ptr = unsafe { allocator.alloc(layout) };
+    assert_eq!(ptrs[1], ptr);
+    unsafe { allocator.dealloc(ptr, layout) };
+    allocator.debug_assert_valid();
+    assert!(allocator.free_blocks().any(|block| block
+        == FreeBlock {
+            offset,
+            size: NODE_SIZE
+        }));
+
+    unsafe {
+        allocator.dealloc(ptrs[0], layout);
+        allocator.dealloc(ptrs[2], layout);
+    }
+    assert!(allocator.is_empty());
+}
+
+#[test]
+fn dealloc_null_is_ignored() {
+    let allocator = FreeListAllocator::<64>::new();
+    let layout = Layout::new::<[u8; 128]>();
+    let ptr = unsafe { allocator.alloc(layout) };
+    assert!(ptr.is_null());
+
+    // Error paths may release the result of a failed allocation
+    unsafe { allocator.dealloc(ptr, layout) };
+    assert_eq!(0, allocator.stats().deallocations);
+    allocator.debug_assert_valid();
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "alignment doesn't match")]
+fn dealloc_with_mismatched_align_panics() {
+    let allocator = FreeListAllocator::<256>::new();
+    let ptr = unsafe { allocator.alloc(Layout::from_size_align(16, 16).unwrap()) };
+    assert!(!ptr.is_null());
+
+    unsafe { allocator.dealloc(ptr, Layout::from_size_align(16, 8).unwrap()) };
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "allocation metadata corrupted")]
+fn dealloc_after_overrun_panics() {
+    let allocator = FreeListAllocator::<256>::new();
+    let layout = Layout::new::<[u8; 16]>();
+    let ptr = unsafe { allocator.alloc(layout) };
+    assert!(!ptr.is_null());
+
+    // Write past the end of the value, over the metadata magic
+    unsafe {
+        ptr.add(layout.size())
+            .write_bytes(0xFF, ALLOCATION_METADATA_LAYOUT_SIZE)
+    };
+    unsafe { allocator.dealloc(ptr, layout) };
+}
+
+#[test]
+fn realloc_shrink_releases_tail_in_place() {
+    let allocator = FreeListAllocator::<256>::new();
+    let layout = Layout::new::<[u8; 128]>();
+
+    let ptr = unsafe { allocator.alloc(layout) };
+    unsafe { ptr::write_bytes(ptr, 0xAB, layout.size()) };
+    let shrunk = unsafe { allocator.realloc(ptr, layout, 16) };
+    assert_eq!(ptr, shrunk);
+    assert_eq!([0xAB; 16], unsafe { *(shrunk as *const [u8; 16]) });
+
+    // The tail is merged with the trailing free block
+    let tail_offset = (16 + ALLOCATION_METADATA_LAYOUT_SIZE)
+        .max(NODE_LAYOUT_SIZE)
+        .next_multiple_of(NODE_LAYOUT_ALIGN);
+    let blocks: Vec<FreeBlock> = allocator.free_blocks().collect();
+    assert_eq!(
+        vec![FreeBlock {
+            offset: tail_offset,
+            size: 256 - tail_offset
+        }],
+        blocks
+    );
+
+    unsafe { allocator.dealloc(shrunk, Layout::new::<[u8; 16]>()) };
+    let blocks: Vec<FreeBlock> = allocator.free_blocks().collect();
+    assert_eq!(
+        vec![FreeBlock {
+            offset: 0,
+            size: 256
+        }],
+        blocks
+    );
+}
+
+#[test]
+fn realloc_keeps_over_alignment() {
+    for placement in [
+        MetadataPlacement::AfterValue,
+        MetadataPlacement::BeforeValue,
+    ] {
+        let allocator = FreeListAllocator::<1024>::with_config(FreeListConfig {
+            metadata_placement: placement,
+            ..FreeListConfig::new()
+        });
+        // Leave the free list start poorly aligned
+        let head = unsafe { allocator.alloc(Layout::new::<u8>()) };
+        let layout = Layout::from_size_align(24, 64).unwrap();
+        let ptr = unsafe { allocator.alloc(layout) };
+        unsafe { ptr::write_bytes(ptr, 0xAB, layout.size()) };
+
+        // Moved to a new block
+        let grown = unsafe { allocator.realloc(ptr, layout, 200) };
+        assert_eq!(0, grown as usize % 64);
+        assert_eq!([0xAB; 24], unsafe { *(grown as *const [u8; 24]) });
+
+        // Shrunk in place
+        let grown_layout = Layout::from_size_align(200, 64).unwrap();
+        let shrunk = unsafe { allocator.realloc(grown, grown_layout, 8) };
+        assert_eq!(grown, shrunk);
+        allocator.debug_assert_valid();
+
+        // Debug builds check the deallocation alignment against the recorded one
+        unsafe { allocator.dealloc(shrunk, Layout::from_size_align(8, 64).unwrap()) };
+        unsafe { allocator.dealloc(head, Layout::new::<u8>()) };
+        assert!(allocator.is_empty());
+        assert_eq!(1, allocator.free_blocks().count());
+    }
+}
+
+#[test]
+fn dealloc_between_separated_free_blocks_is_reachable() {
+    let allocator = FreeListAllocator::<512>::new();
+    let layout = Layout::new::<[u8; 32]>();
+    // Free, live, freed, live, free: the middle block is surrounded by live blocks
+    let ptrs: Vec<*mut u8> = (0..5).map(|_| unsafe { allocator.alloc(layout) }).collect();
+    unsafe {
+        allocator.dealloc(ptrs[0], layout);
+        allocator.dealloc(ptrs[4], layout);
+        allocator.dealloc(ptrs[2], layout);
+    }
+    allocator.debug_assert_valid();
+
+    let middle_offset = allocator.offset_of(ptrs[2]).unwrap();
+    assert!(allocator
+        .free_blocks()
+        .any(|block| block.offset == middle_offset));
+    // The middle block is allocated again rather than lost
+    assert_eq!(ptrs[0], unsafe { allocator.alloc(layout) });
+    assert_eq!(ptrs[2], unsafe { allocator.alloc(layout) });
+}
+
+#[test]
+fn realloc_small_shrink_keeps_block() {
+    let allocator = FreeListAllocator::<256>::new();
+    let layout = Layout::new::<[u8; 128]>();
+
+    let ptr = unsafe { allocator.alloc(layout) };
+    let blocks_before: Vec<FreeBlock> = allocator.free_blocks().collect();
+    let shrunk = unsafe { allocator.realloc(ptr, layout, 124) };
+    assert_eq!(ptr, shrunk);
+
+    // The freed bytes can't hold a Node: the block is kept as-is
+    let blocks: Vec<FreeBlock> = allocator.free_blocks().collect();
+    assert_eq!(blocks_before, blocks);
+
+    unsafe { allocator.dealloc(shrunk, Layout::new::<[u8; 124]>()) };
+    let blocks: Vec<FreeBlock> = allocator.free_blocks().collect();
+    assert_eq!(
+        vec![FreeBlock {
+            offset: 0,
+            size: 256
+        }],
+        blocks
+    );
+}
+
+#[test]
+fn stats_count_realloc_in_place_and_copied() {
+    let allocator = FreeListAllocator::<256>::new();
+    let layout = Layout::new::<[u8; 64]>();
+
+    let ptr = unsafe { allocator.alloc(layout) };
+    let shrunk = unsafe { allocator.realloc(ptr, layout, 16) };
+    let grown = unsafe { allocator.realloc(shrunk, Layout::new::<[u8; 16]>(), 32) };
+    // Failed reallocations aren't counted
+    assert!(unsafe { allocator.realloc(grown, Layout::new::<[u8; 32]>(), 512) }.is_null());
+
+    let stats = allocator.stats();
+    assert_eq!(1, stats.realloc_in_place);
+    assert_eq!(1, stats.realloc_copied);
+    unsafe { allocator.dealloc(grown, Layout::new::<[u8; 32]>()) };
+}
+
+#[test]
+fn restore_snapshot_replays_allocations() {
+    let allocator = FreeListAllocator::<256>::new();
+    let layout = Layout::new::<u64>();
+
+    let a = unsafe { allocator.alloc(layout) };
+    unsafe { ptr::write(a as *mut u64, 42) };
+    let snapshot = allocator.snapshot();
+    assert_eq!(256 + 2 * std::mem::size_of::<usize>(), snapshot.len());
+    let blocks: Vec<FreeBlock> = allocator.free_blocks().collect();
+
+    let b = unsafe { allocator.alloc(layout) };
+    unsafe { ptr::write(a as *mut u64, 7) };
+    unsafe { allocator.restore(&snapshot) };
+
+    assert_eq!(42, unsafe { ptr::read(a as *const u64) });
+    assert_eq!(blocks, allocator.free_blocks().collect::<Vec<FreeBlock>>());
+    // Same sequence, same pointer
+    assert_eq!(b, unsafe { allocator.alloc(layout) });
+    allocator.debug_assert_valid();
+}
+
+#[test]
+fn snapshot_into_matches_snapshot() {
+    let allocator = FreeListAllocator::<256>::new();
+    let layout = Layout::new::<u64>();
+    let a = unsafe { allocator.alloc(layout) };
+    unsafe { ptr::write(a as *mut u64, 42) };
+
+    let mut buffer = vec![0; allocator.snapshot_size()];
+    allocator.snapshot_into(&mut buffer);
+    assert_eq!(allocator.snapshot(), buffer);
+}
+
+#[test]
+#[should_panic(expected = "Snapshot size")]
+fn snapshot_into_size_mismatch_panics() {
+    let allocator = FreeListAllocator::<256>::new();
+    allocator.snapshot_into(&mut [0; 256]);
+}
+
+#[test]
+#[should_panic(expected = "Snapshot size")]
+fn restore_snapshot_size_mismatch_panics() {
+    let allocator = FreeListAllocator::<256>::new();
+    unsafe { allocator.restore(&[0; 8]) };
+}
+
+#[test]
+fn rewind_releases_allocations_since_checkpoint() {
+    let allocator = FreeListAllocator::<1024>::new();
+    let layout = Layout::new::<[u64; 4]>();
+    let kept = unsafe { allocator.alloc(layout) };
+    let released_later = unsafe { allocator.alloc(layout) };
+    let used = allocator.len();
+
+    let checkpoint = allocator.checkpoint();
+    let phase: Vec<*mut u8> = (0..4).map(|_| unsafe { allocator.alloc(layout) }).collect();
+    assert!(phase.iter().all(|ptr| !ptr.is_null()));
+    unsafe { allocator.dealloc(phase[1], layout) };
+
+    unsafe { allocator.rewind(&checkpoint) };
+    allocator.debug_assert_valid();
+    assert_eq!(used, allocator.len());
+    assert!(allocator.is_allocated(kept));
+    assert!(phase.iter().all(|ptr| !allocator.is_allocated(*ptr)));
+
+    // Deallocations of older allocations made after the checkpoint are kept
+    let checkpoint = allocator.checkpoint();
+    let _ = unsafe { allocator.alloc(layout) };
+    unsafe { allocator.dealloc(released_later, layout) };
+    unsafe { allocator.rewind(&checkpoint) };
+    unsafe { allocator.dealloc(kept, layout) };
+    assert!(allocator.is_empty());
+    assert_eq!(
+        vec![FreeBlock {
+            offset: 0,
+            size: 1024
+        }],
+        allocator.free_blocks().collect::<Vec<_>>()
+    );
+}
+
+#[test]
+#[should_panic(expected = "Checkpoint taken from another arena")]
+fn rewind_checkpoint_from_another_allocator_panics() {
+    let allocator = FreeListAllocator::<1024>::new();
+    let other = FreeListAllocator::<1024>::new();
+    let checkpoint: FreeListCheckpoint = other.checkpoint();
+    unsafe { allocator.rewind(&checkpoint) };
+}
+
+#[test]
+fn size_histogram_counts_size_classes() {
+    let allocator = FreeListAllocator::<256>::with_config(FreeListConfig {
+        size_histogram: true,
+        ..FreeListConfig::new()
+    });
+
+    unsafe {
+        allocator.alloc(Layout::new::<u8>());
+        allocator.alloc(Layout::new::<[u8; 3]>());
+        allocator.alloc(Layout::new::<u32>());
+        allocator.alloc(Layout::new::<[u8; 33]>());
+    }
+
+    let histogram = allocator.size_histogram();
+    assert_eq!(1, histogram[0]);
+    assert_eq!(2, histogram[2]);
+    assert_eq!(1, histogram[6]);
+    assert_eq!(4, histogram.iter().sum::<usize>());
+}
+
+#[test]
+fn size_histogram_disabled_records_nothing() {
+    let allocator = FreeListAllocator::<256>::new();
+    unsafe { allocator.alloc(Layout::new::<u64>()) };
+
+    assert_eq!([0; SIZE_CLASSES], allocator.size_histogram());
+}
+
+static HOOK_ALLOCATED_BYTES: AtomicUsize = AtomicUsize::new(0);
+static HOOK_DEALLOCATED_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+fn record_event(event: AllocEvent) {
+    match event {
+        AllocEvent::Alloc { ptr, layout } => {
+            assert!(!ptr.is_null());
+            HOOK_ALLOCATED_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        }
+        AllocEvent::Dealloc { layout, .. } => {
+            HOOK_DEALLOCATED_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        }
+    }
+}
+
+#[test]
+fn event_hook_called_on_alloc_and_dealloc() {
+    let allocator = FreeListAllocator::<256>::with_config(FreeListConfig {
+        event_hook: Some(record_event),
+        ..FreeListConfig::new()
+    });
+    let layout = Layout::new::<[u8; 32]>();
+
+    let ptr = unsafe { allocator.alloc(layout) };
+    let shrunk = unsafe { allocator.realloc(ptr, layout, 8) };
+    unsafe { allocator.dealloc(shrunk, Layout::new::<[u8; 8]>()) };
+
+    // The in place shrink is reported as a deallocation followed by an allocation
+    assert_eq!(40, HOOK_ALLOCATED_BYTES.load(Ordering::Relaxed));
+    assert_eq!(40, HOOK_DEALLOCATED_BYTES.load(Ordering::Relaxed));
+}
+
+#[test]
+fn first_alloc_needs_no_padding() {
+    let allocator = FreeListAllocator::<256>::new();
+    let layout = Layout::from_size_align(16, ARENA_ALIGN).unwrap();
+
+    let ptr = unsafe { allocator.alloc(layout) };
+    assert_eq!(0, ptr as usize % ARENA_ALIGN);
+
+    // The value is placed at the arena start
+    let block_size = (16 + ALLOCATION_METADATA_LAYOUT_SIZE)
+        .max(NODE_LAYOUT_SIZE)
+        .next_multiple_of(NODE_LAYOUT_ALIGN);
+    let blocks: Vec<FreeBlock> = allocator.free_blocks().collect();
+    assert_eq!(
+        vec![FreeBlock {
+            offset: block_size,
+            size: 256 - block_size
+        }],
+        blocks
+    );
+}
+
+#[test]
+fn can_allocate_doesnt_allocate() {
+    let allocator = FreeListAllocator::<128>::new();
+    let fitting = Layout::new::<[u8; 64]>();
+
+    assert!(allocator.can_allocate(fitting));
+    assert!(!allocator.can_allocate(Layout::new::<[u8; 256]>()));
+    assert!(allocator.can_allocate(Layout::new::<()>()));
+    assert_eq!(0, allocator.stats().allocations);
+
+    unsafe { allocator.alloc(fitting) };
+    assert!(!allocator.can_allocate(fitting));
+}
+
+#[test]
+fn dealloc_mixed_alignments_keeps_nodes_aligned() {
+    let allocator = FreeListAllocator::<1024>::new();
+    let layouts = [
+        Layout::from_size_align(3, 1).unwrap(),
+        Layout::from_size_align(5, 2).unwrap(),
+        Layout::from_size_align(17, 16).unwrap(),
+        Layout::from_size_align(1, 64).unwrap(),
+        Layout::from_size_align(30, 4).unwrap(),
+    ];
+
+    let ptrs: Vec<*mut u8> = layouts
+        .iter()
+        .map(|layout| unsafe { allocator.alloc(*layout) })
+        .collect();
+    allocator.debug_assert_valid();
+
+    for (ptr, layout) in ptrs.into_iter().zip(layouts).rev() {
+        assert_eq!(0, ptr as usize % layout.align());
+        unsafe { allocator.dealloc(ptr, layout) };
+        allocator.debug_assert_valid();
+    }
+    let blocks: Vec<FreeBlock> = allocator.free_blocks().collect();
+    assert_eq!(
+        vec![FreeBlock {
+            offset: 0,
+            size: 1024
+        }],
+        blocks
+    );
+}
+
+#[test]
+fn free_node_count_walks_free_list() {
+    let allocator = FreeListAllocator::<256>::new();
+    let layout = Layout::new::<[u8; 32]>();
+    assert_eq!(1, allocator.free_node_count());
+    assert_eq!(256, allocator.free_bytes());
+
+    let a = unsafe { allocator.alloc(layout) };
+    let b = unsafe { allocator.alloc(layout) };
+    unsafe { allocator.dealloc(a, layout) };
+    // Freed block and trailing free space, separated by the live allocation
+    assert_eq!(2, allocator.free_node_count());
+    assert_eq!(
+        allocator.capacity() - allocator.len(),
+        allocator.free_bytes()
+    );
+
+    unsafe { allocator.dealloc(b, layout) };
+    assert_eq!(1, allocator.free_node_count());
+
+    // Whole arena allocated
+    let allocator = FreeListAllocator::<64>::new();
+    let ptr = unsafe { allocator.alloc(Layout::new::<[u8; 48]>()) };
+    assert!(!ptr.is_null());
+    assert_eq!(0, allocator.free_node_count());
+    assert_eq!(0, allocator.free_bytes());
+}
+
+#[test]
+fn alloc_whole_arena() {
+    let allocator = FreeListAllocator::<256>::new();
+    let largest = Layout::from_size_align(256 - ALLOCATION_METADATA_LAYOUT_SIZE, 1).unwrap();
+    let too_large = Layout::from_size_align(largest.size() + 1, 1).unwrap();
+
+    assert!(unsafe { allocator.alloc(too_large) }.is_null());
+    let ptr = unsafe { allocator.alloc(largest) };
+    assert!(!ptr.is_null());
+    assert_eq!(256, allocator.len());
+
+    unsafe { allocator.dealloc(ptr, largest) };
+    assert!(allocator.is_empty());
+    allocator.debug_assert_valid();
+}
+
+#[test]
+fn len_counts_used_bytes() {
+    let allocator = FreeListAllocator::<256>::new();
+    let layout = Layout::new::<[u8; 64]>();
+    assert_eq!(256, allocator.capacity());
+    assert!(allocator.is_empty());
+
+    let ptr = unsafe { allocator.alloc(layout) };
+    // Metadata and paddings are counted as used
+    let free_bytes: usize = allocator.free_blocks().map(|block| block.size).sum();
+    assert_eq!(256 - free_bytes, allocator.len());
+    assert!(allocator.len() >= layout.size() + ALLOCATION_METADATA_LAYOUT_SIZE);
+
+    unsafe { allocator.dealloc(ptr, layout) };
+    assert!(allocator.is_empty());
+}
+
+#[test]
+fn fragmentation_bytes() {
+    let allocator = FreeListAllocator::<256>::new();
+    let layout = Layout::new::<[u8; 32]>();
+    assert_eq!(0, allocator.internal_fragmentation_bytes());
+    assert_eq!(0, allocator.external_fragmentation_bytes());
+
+    let a = unsafe { allocator.alloc(layout) };
+    let b = unsafe { allocator.alloc(layout) };
+    assert_eq!(
+        allocator.len() - 2 * layout.size(),
+        allocator.internal_fragmentation_bytes()
+    );
+
+    // The freed block is separated from the trailing free block
+    unsafe { allocator.dealloc(a, layout) };
+    let blocks: Vec<FreeBlock> = allocator.free_blocks().collect();
+    assert_eq!(blocks[0].size, allocator.external_fragmentation_bytes());
+
+    unsafe { allocator.dealloc(b, layout) };
+    assert_eq!(0, allocator.internal_fragmentation_bytes());
+    assert_eq!(0, allocator.external_fragmentation_bytes());
+}
+
+#[test]
+fn report_live_lists_allocations() {
+    let allocator = FreeListAllocator::<512>::with_config(FreeListConfig {
+        live_tracking: true,
+        ..FreeListConfig::new()
+    });
+    assert!(allocator.report_live().is_empty());
+
+    let small = Layout::new::<u8>();
+    let aligned = Layout::from_size_align(40, 32).unwrap();
+    let a = unsafe { allocator.alloc(small) };
+    let b = unsafe { allocator.alloc(aligned) };
+    let c = unsafe { allocator.alloc(small) };
+    allocator.debug_assert_valid();
+    assert_eq!(0, b as usize % 32);
+
+    let live = allocator.report_live();
+    assert_eq!(3, live.len());
+    assert_eq!(
+        vec![1, 40, 1],
+        live.iter().map(|(_, size)| *size).collect::<Vec<_>>()
+    );
+    assert_eq!(c as usize - a as usize, live[2].0 - live[0].0);
+
+    // Shrunk and freed allocations are reflected
+    let b = unsafe { allocator.realloc(b, aligned, 8) };
+    unsafe { allocator.dealloc(c, small) };
+    let live = allocator.report_live();
+    assert_eq!(
+        vec![1, 8],
+        live.iter().map(|(_, size)| *size).collect::<Vec<_>>()
+    );
+
+    unsafe {
+        allocator.dealloc(a, small);
+        allocator.dealloc(b, Layout::from_size_align(8, 32).unwrap());
+    }
+    assert!(allocator.report_live().is_empty());
+}
+
+#[test]
+fn heap_dump_lists_allocations_outside_free_blocks() {
+    let allocator = FreeListAllocator::<512>::with_config(FreeListConfig {
+        live_tracking: true,
+        ..FreeListConfig::new()
+    });
+    let layouts = [
+        Layout::from_size_align(3, 1).unwrap(),
+        Layout::from_size_align(40, 32).unwrap(),
+        Layout::from_size_align(16, 8).unwrap(),
+    ];
+    let ptrs = layouts.map(|layout| unsafe { allocator.alloc(layout) });
+    unsafe { allocator.dealloc(ptrs[0], layouts[0]) };
+
+    let dump = allocator.heap_dump();
+    assert_eq!(
+        vec![
+            AllocInfo {
+                offset: allocator.offset_of(ptrs[1]).unwrap(),
+                size: 40,
+                align: 32
+            },
+            AllocInfo {
+                offset: allocator.offset_of(ptrs[2]).unwrap(),
+                size: 16,
+                align: 8
+            },
+        ],
+        dump
+    );
+
+    // Values never overlap free blocks, and free and used bytes cover the whole arena
+    let free_blocks: Vec<FreeBlock> = allocator.free_blocks().collect();
+    for alloc in &dump {
+        assert!(free_blocks
+            .iter()
+            .all(|block| alloc.offset + alloc.size <= block.offset
+                || block.offset + block.size <= alloc.offset));
+    }
+    let free_bytes: usize = free_blocks.iter().map(|block| block.size).sum();
+    assert_eq!(allocator.capacity(), free_bytes + allocator.len());
+}
+
+#[test]
+fn heap_dump_with_unsorted_free_list() {
+    let allocator = FreeListAllocator::<512>::with_config(FreeListConfig {
+        live_tracking: true,
+        coalesce_on_free: false,
+        ..FreeListConfig::new()
+    });
+    let layout = Layout::new::<[u8; 32]>();
+    let ptrs: Vec<*mut u8> = (0..4).map(|_| unsafe { allocator.alloc(layout) }).collect();
+    unsafe {
+        allocator.dealloc(ptrs[0], layout);
+        allocator.dealloc(ptrs[2], layout);
+    }
+
+    let offsets: Vec<usize> = allocator
+        .heap_dump()
+        .iter()
+        .map(|alloc| alloc.offset)
+        .collect();
+    assert_eq!(
+        vec![
+            allocator.offset_of(ptrs[1]).unwrap(),
+            allocator.offset_of(ptrs[3]).unwrap()
+        ],
+        offsets
+    );
+}
+
+#[test]
+#[cfg(feature = "alloc_age")]
+fn live_allocations_report_their_age() {
+    let allocator = FreeListAllocator::<1024>::with_config(FreeListConfig {
+        live_tracking: true,
+        ..FreeListConfig::new()
+    });
+    let layout = Layout::new::<u64>();
+    assert_eq!(None, allocator.oldest_live_allocation());
+
+    let ptrs: Vec<*mut u8> = (0..5).map(|_| unsafe { allocator.alloc(layout) }).collect();
+    unsafe { allocator.dealloc(ptrs[0], layout) };
+
+    let oldest = allocator.oldest_live_allocation().unwrap();
+    assert_eq!(
+        AgedAllocInfo {
+            alloc: AllocInfo {
+                offset: allocator.offset_of(ptrs[1]).unwrap(),
+                size: 8,
+                align: 8
+            },
+            sequence: 1,
+            age: 3
+        },
+        oldest
+    );
+
+    let old: Vec<usize> = allocator
+        .live_allocations_older_than(1)
+        .iter()
+        .map(|aged| aged.sequence)
+        .collect();
+    assert_eq!(vec![1, 2], old);
+    assert!(allocator.live_allocations_older_than(3).is_empty());
+}
+
+#[test]
+#[should_panic(expected = "aren't tracked")]
+fn report_live_disabled_panics() {
+    let allocator = FreeListAllocator::<256>::new();
+    allocator.report_live();
+}
+
+#[test]
+fn recent_free_cache_reuses_freed_block() {
+    let allocator = FreeListAllocator::<512>::with_config(FreeListConfig {
+        recent_free_cache: true,
+        ..FreeListConfig::new()
+    });
+    let layout = Layout::new::<[u8; 32]>();
+    let kept = unsafe { allocator.alloc(layout) };
+
+    let first = unsafe { allocator.alloc(layout) };
+    unsafe { allocator.dealloc(first, layout) };
+    for _ in 0..16 {
+        let ptr = unsafe { allocator.alloc(layout) };
+        assert_eq!(first, ptr);
+        unsafe { allocator.dealloc(ptr, layout) };
+        allocator.debug_assert_valid();
+    }
+
+    unsafe { allocator.dealloc(kept, layout) };
+    assert_eq!(0, allocator.len());
+}
+
+static OOM_ALLOCATOR: FreeListAllocator<256> = FreeListAllocator::with_config(FreeListConfig {
+    oom_handler: Some(release_cached_block),
+    ..FreeListConfig::new()
+});
+static CACHED_BLOCK: AtomicPtr<u8> = AtomicPtr::new(ptr::null_mut());
+const CACHED_BLOCK_LAYOUT: Layout = Layout::new::<[u8; 128]>();
+
+fn release_cached_block(_: Layout) -> bool {
+    let ptr = CACHED_BLOCK.swap(ptr::null_mut(), Ordering::Relaxed);
+    if ptr.is_null() {
+        return false;
+    }
+    unsafe { OOM_ALLOCATOR.dealloc(ptr, CACHED_BLOCK_LAYOUT) };
+    true
+}
+
+#[test]
+fn oom_handler_releases_memory_and_retries() {
+    let cached = unsafe { OOM_ALLOCATOR.alloc(CACHED_BLOCK_LAYOUT) };
+    assert!(!cached.is_null());
+    CACHED_BLOCK.store(cached, Ordering::Relaxed);
+
+    // Only fits once the cached block is released
+    let layout = Layout::new::<[u8; 192]>();
+    let ptr = unsafe { OOM_ALLOCATOR.alloc(layout) };
+    assert_eq!(cached, ptr);
+    assert!(CACHED_BLOCK.load(Ordering::Relaxed).is_null());
+
+    // Nothing left to release
+    assert!(unsafe { OOM_ALLOCATOR.alloc(layout) }.is_null());
+    unsafe { OOM_ALLOCATOR.dealloc(ptr, layout) };
+    OOM_ALLOCATOR.debug_assert_valid();
+}
+
+#[test]
+fn dealloc_batch_releases_all_allocations() {
+    let allocator = FreeListAllocator::<1024>::new();
+    let layout = Layout::new::<[u8; 32]>();
+    let ptrs: Vec<*mut u8> = (0..8).map(|_| unsafe { allocator.alloc(layout) }).collect();
+
+    // Every other allocation, in reverse order: blocks are inserted between live allocations
+    let mut odd: Vec<(*mut u8, Layout)> = ptrs
+        .iter()
+        .skip(1)
+        .step_by(2)
+        .rev()
+        .map(|ptr| (*ptr, layout))
+        .collect();
+    unsafe { allocator.dealloc_batch(&mut odd) };
+    allocator.debug_assert_valid();
+    assert_eq!(4, allocator.free_node_count());
+    assert_eq!(4, allocator.stats().deallocations);
+
+    let mut even: Vec<(*mut u8, Layout)> =
+        ptrs.iter().step_by(2).map(|ptr| (*ptr, layout)).collect();
+    even.push((ptr::null_mut(), layout));
+    unsafe { allocator.dealloc_batch(&mut even) };
+    allocator.debug_assert_valid();
+    assert!(allocator.is_empty());
+    assert_eq!(8, allocator.stats().deallocations);
+}
+
+#[test]
+fn allocation_overhead_matches_block_size() {
+    for live_tracking in [false, true] {
+        let config = FreeListConfig {
+            live_tracking,
+            ..FreeListConfig::new()
+        };
+        let allocator = FreeListAllocator::<512>::with_config(config);
+        let layout = Layout::new::<[u8; 64]>();
+
+        let ptr = unsafe { allocator.alloc(layout) };
+        let expected = (layout.size() + config.allocation_overhead())
+            .max(NODE_SIZE)
+            .next_multiple_of(NODE_ALIGN);
+        assert_eq!(expected, allocator.len());
+        unsafe { allocator.dealloc(ptr, layout) };
+    }
+    assert_eq!(
+        ALLOCATION_METADATA_SIZE,
+        FreeListConfig::new().allocation_overhead()
+    );
+}
+
+#[test]
+fn offset_of_arena_pointers_only() {
+    let allocator = FreeListAllocator::<256>::with_config(FreeListConfig {
+        system_fallback: true,
+        ..FreeListConfig::new()
+    });
+    let small = Layout::new::<[u8; 32]>();
+    let large = Layout::new::<[u8; 512]>();
+
+    let a = unsafe { allocator.alloc(small) };
+    let b = unsafe { allocator.alloc(small) };
+    let system = unsafe { allocator.alloc(large) };
+    assert_eq!(Some(0), allocator.offset_of(a));
+    assert_eq!(Some(b as usize - a as usize), allocator.offset_of(b));
+    assert_eq!(None, allocator.offset_of(system));
+
+    unsafe {
+        allocator.dealloc(a, small);
+        allocator.dealloc(b, small);
+        allocator.dealloc(system, large);
+    }
+}
+
+#[test]
+fn is_allocated_until_dealloc() {
+    let allocator = FreeListAllocator::<256>::with_config(FreeListConfig {
+        system_fallback: true,
+        ..FreeListConfig::new()
+    });
+    let small = Layout::new::<[u8; 32]>();
+    let large = Layout::new::<[u8; 512]>();
+
+    let a = unsafe { allocator.alloc(small) };
+    let b = unsafe { allocator.alloc(small) };
+    let system = unsafe { allocator.alloc(large) };
+    assert!(allocator.is_allocated(a));
+    assert!(allocator.is_allocated(unsafe { a.add(31) }));
+    assert!(allocator.is_allocated(b));
+    assert!(!allocator.is_allocated(system));
+    assert!(!allocator.is_allocated(b.wrapping_add(256)));
+
+    unsafe { allocator.dealloc(a, small) };
+    assert!(!allocator.is_allocated(a));
+    assert!(allocator.is_allocated(b));
+
+    unsafe {
+        allocator.dealloc(b, small);
+        allocator.dealloc(system, large);
+    }
+    assert!(!allocator.is_allocated(b));
+}
+
+#[test]
+fn dealloc_without_coalescing_defers_merges() {
+    let allocator = FreeListAllocator::<512>::with_config(FreeListConfig {
+        coalesce_on_free: false,
+        ..FreeListConfig::new()
+    });
+    let layout = Layout::new::<[u8; 64]>();
+
+    let ptrs: Vec<*mut u8> = (0..3).map(|_| unsafe { allocator.alloc(layout) }).collect();
+    for &ptr in &ptrs {
+        unsafe { allocator.dealloc(ptr, layout) };
+    }
+    // Freed blocks are prepended, along with the free tail of the arena
+    assert_eq!(4, allocator.free_node_count());
+    allocator.debug_assert_valid();
+    let offsets: Vec<usize> = allocator.free_blocks().map(|block| block.offset).collect();
+    assert!(offsets.is_sorted());
+
+    assert_eq!(3, allocator.defragment());
+    assert_eq!(
+        vec![FreeBlock {
+            offset: 0,
+            size: 512
+        }],
+        allocator.free_blocks().collect::<Vec<_>>()
+    );
+    allocator.debug_assert_valid();
+}
+
+#[test]
+fn dealloc_without_coalescing_reuses_last_freed_block() {
+    let allocator = FreeListAllocator::<512>::with_config(FreeListConfig {
+        coalesce_on_free: false,
+        ..FreeListConfig::new()
+    });
+    let layout = Layout::new::<[u8; 64]>();
+
+    let first = unsafe { allocator.alloc(layout) };
+    let second = unsafe { allocator.alloc(layout) };
+    unsafe {
+        allocator.dealloc(first, layout);
+        allocator.dealloc(second, layout);
+    }
+
+    // The most recently freed block is first in the free list
+    assert_eq!(second, unsafe { allocator.alloc(layout) });
+}
+
+#[test]
+fn dealloc_without_coalescing_exhausted_arena_defragments() {
+    let allocator = FreeListAllocator::<256>::with_config(FreeListConfig {
+        coalesce_on_free: false,
+        ..FreeListConfig::new()
+    });
+    let layout = Layout::new::<[u8; 64]>();
+
+    let first = unsafe { allocator.alloc(layout) };
+    let second = unsafe { allocator.alloc(layout) };
+    unsafe {
+        allocator.dealloc(first, layout);
+        allocator.dealloc(second, layout);
+    }
+
+    // Only the merged blocks can hold this layout
+    let large = Layout::from_size_align(200, 1).unwrap();
+    assert!(!allocator.can_allocate(large));
+    assert!(!unsafe { allocator.alloc(large) }.is_null());
+    allocator.debug_assert_valid();
+}
+
+#[test]
+fn prefault_keeps_arena_contents() {
+    let allocator = FreeListAllocator::<16384>::new();
+    let layout = Layout::new::<u64>();
+    let ptr = unsafe { allocator.alloc(layout) };
+    unsafe { ptr::write(ptr as *mut u64, 42) };
+    let blocks: Vec<FreeBlock> = allocator.free_blocks().collect();
+
+    allocator.prefault();
+
+    assert_eq!(42, unsafe { ptr::read(ptr as *const u64) });
+    assert_eq!(blocks, allocator.free_blocks().collect::<Vec<_>>());
+    allocator.debug_assert_valid();
+    unsafe { allocator.dealloc(ptr, layout) };
+}
+
+#[test]
+fn region_reset_frees_arena() {
+    let allocator = FreeListAllocator::<256>::new();
+    let layout = Layout::new::<[u8; 64]>();
+    for _ in 0..3 {
+        assert!(!unsafe { allocator.alloc(layout) }.is_null());
+    }
+
+    unsafe { allocator.reset() };
+    assert!(allocator.is_empty());
+    assert_eq!(1, allocator.free_node_count());
+    allocator.debug_assert_valid();
+}
+
+/// Free a small block starting 16 bytes past a 256 boundary, followed by a 512-aligned free block,
+/// so that the free list starts with a Node too small to hold a 256-aligned value.
+fn alloc_with_aligned_free_block(allocator: &FreeListAllocator<2048>) -> *mut u8 {
+    let heap = allocator.heap();
+    let overhead = heap.config.allocation_overhead();
+    let arena_start = heap.root.lock().unwrap().arena_ptr.load(Ordering::Acquire) as usize;
+    // Arena start is aligned to 16 bytes only: a leading value places the head block at a fixed misalignment
+    let head_start = (arena_start + 64 + 256 - 16).next_multiple_of(256) + 16;
+    let lead = Layout::from_size_align(head_start - arena_start - overhead, 1).unwrap();
+    let lead_ptr = unsafe { allocator.alloc(lead) };
+    assert!(!lead_ptr.is_null());
+
+    let head = Layout::from_size_align(8, 8).unwrap();
+    let head_ptr = unsafe { allocator.alloc(head) };
+    assert_eq!(head_start, head_ptr as usize);
+    // The filler block ends exactly on a 512 boundary, where the second free block starts
+    let filler_start = head_start + NODE_LAYOUT_SIZE;
+    let boundary = (filler_start + 256).next_multiple_of(512);
+    let filler = Layout::from_size_align(boundary - filler_start - overhead, 1).unwrap();
+    let filler_ptr = unsafe { allocator.alloc(filler) };
+    unsafe { allocator.dealloc(head_ptr, head) };
+    filler_ptr
+}
+
+#[test]
+fn least_padding_align_picks_aligned_node() {
+    let config = FreeListConfig {
+        least_padding_align: 256,
+        ..FreeListConfig::new()
+    };
+    let allocator = FreeListAllocator::<2048>::with_config(config);
+    alloc_with_aligned_free_block(&allocator);
+    let blocks: Vec<FreeBlock> = allocator.free_blocks().collect();
+    assert_eq!(2, blocks.len());
+    assert_eq!(NODE_LAYOUT_SIZE, blocks[0].size);
+
+    let layout = Layout::from_size_align(16, 256).unwrap();
+    let ptr = unsafe { allocator.alloc(layout) };
+    assert_eq!(0, ptr as usize % 256);
+    // Placed in the second free block, which needs less padding than the first one
+    let offset = allocator.offset_of(ptr).unwrap();
+    assert!(offset >= blocks[1].offset);
+    assert!(offset - blocks[1].offset < 256);
+}
+
+fn metadata_before_config() -> FreeListConfig {
+    FreeListConfig {
+        metadata_placement: MetadataPlacement::BeforeValue,
+        ..FreeListConfig::new()
+    }
+}
+
+#[test]
+fn metadata_before_value_round_trip() {
+    let allocator = FreeListAllocator::<1024>::with_config(FreeListConfig {
+        live_tracking: true,
+        ..metadata_before_config()
+    });
+    let layouts = [
+        Layout::from_size_align(1, 1).unwrap(),
+        Layout::from_size_align(13, 8).unwrap(),
+        Layout::from_size_align(40, 64).unwrap(),
+        Layout::from_size_align(24, 8).unwrap(),
+    ];
+    let ptrs: Vec<*mut u8> = layouts
+        .iter()
+        .map(|layout| unsafe {
+            let ptr = allocator.alloc(*layout);
+            assert_eq!(0, ptr as usize % layout.align());
+            ptr.write_bytes(0xAB, layout.size());
+            ptr
+        })
+        .collect();
+    allocator.debug_assert_valid();
+    assert_eq!(
+        vec![1, 13, 40, 24],
+        allocator
+            .report_live()
+            .iter()
+            .map(|(_, size)| *size)
+            .collect::<Vec<_>>()
+    );
+
+    // Shrink in place, then free everything in mixed order
+    let shrunk = unsafe { allocator.realloc(ptrs[2], layouts[2], 8) };
+    assert_eq!(ptrs[2], shrunk);
+    unsafe {
+        allocator.dealloc(ptrs[1], layouts[1]);
+        allocator.dealloc(shrunk, Layout::from_size_align(8, 64).unwrap());
+        allocator.dealloc(ptrs[3], layouts[3]);
+        allocator.dealloc(ptrs[0], layouts[0]);
+    }
+    allocator.debug_assert_valid();
+    let blocks: Vec<FreeBlock> = allocator.free_blocks().collect();
+    assert_eq!(1, blocks.len());
+    assert_eq!(1024, blocks[0].size);
+}
+
+#[test]
+fn metadata_before_value_survives_forward_overrun() {
+    let allocator = FreeListAllocator::<256>::with_config(metadata_before_config());
+    // The block is rounded up to a multiple of the Node alignment: 3 bytes of fill padding follow the value
+    let layout = Layout::new::<[u8; 13]>();
+    let ptr = unsafe { allocator.alloc(layout) };
+    unsafe { ptr.write_bytes(0xFF, layout.size() + 3) };
+
+    unsafe { allocator.dealloc(ptr, layout) };
+    allocator.debug_assert_valid();
+    assert_eq!(0, allocator.len());
+}
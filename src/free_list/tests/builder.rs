@@ -0,0 +1,67 @@
+use std::alloc::Layout;
+
+use crate::free_list::{
+    FailurePolicy, FitStrategy, FreeListAllocator, FreeListAllocatorBuilder, FreeListConfig,
+    MetadataPlacement,
+};
+
+#[test]
+fn builder_default_config() {
+    assert_eq!(
+        FreeListConfig::new(),
+        FreeListAllocatorBuilder::new().config()
+    );
+}
+
+fn never_release(_: Layout) -> bool {
+    false
+}
+
+#[test]
+fn builder_collects_options() {
+    let builder = FreeListAllocatorBuilder::new()
+        .min_block_size(64)
+        .fit_strategy(FitStrategy::BestFit)
+        .system_fallback(true)
+        .size_histogram(true)
+        .recent_free_cache(true)
+        .oom_handler(never_release)
+        .coalesce_on_free(false)
+        .least_padding_align(4096)
+        .metadata_placement(MetadataPlacement::BeforeValue)
+        .failure_policy(FailurePolicy::Panic);
+
+    assert_eq!(
+        FreeListConfig {
+            min_block_size: 64,
+            fit_strategy: FitStrategy::BestFit,
+            system_fallback: true,
+            size_histogram: true,
+            recent_free_cache: true,
+            oom_handler: Some(never_release),
+            coalesce_on_free: false,
+            least_padding_align: 4096,
+            metadata_placement: MetadataPlacement::BeforeValue,
+            failure_policy: FailurePolicy::Panic,
+            ..FreeListConfig::new()
+        },
+        builder.config()
+    );
+}
+
+#[test]
+fn builder_build_dyn() {
+    let allocator = FreeListAllocatorBuilder::new()
+        .fit_strategy(FitStrategy::BestFit)
+        .build_dyn(512);
+
+    assert_eq!(512, allocator.size());
+}
+
+#[test]
+fn builder_build_const() {
+    static ALLOCATOR: FreeListAllocator<256> =
+        FreeListAllocatorBuilder::new().min_block_size(64).build();
+
+    assert_eq!(256, ALLOCATOR.capacity());
+}
@@ -0,0 +1,80 @@
+use std::alloc::{GlobalAlloc, Layout};
+
+use crate::free_list::{node::NODE_LAYOUT_SIZE, DynFreeListAllocator, FreeBlock, FreeListConfig};
+
+#[test]
+fn new_allocates_runtime_sized_arena() {
+    let size = 64 * NODE_LAYOUT_SIZE;
+    let allocator = DynFreeListAllocator::new(size);
+
+    assert_eq!(size, allocator.size());
+    let blocks: Vec<FreeBlock> = allocator.free_blocks().collect();
+    assert_eq!(vec![FreeBlock { offset: 0, size }], blocks);
+}
+
+#[test]
+fn new_too_small_cannot_allocate() {
+    let allocator = DynFreeListAllocator::new(NODE_LAYOUT_SIZE - 1);
+
+    assert_eq!(0, allocator.size());
+    assert!(unsafe { allocator.alloc(Layout::new::<u8>()) }.is_null());
+}
+
+#[test]
+fn sized_for_holds_requested_allocations() {
+    // Awkward sizes, each one rounded up to the Node alignment
+    let sizes = [1, 9, 17, 100, 3, 250, 600, 5, 14, 1];
+    let data_bytes = sizes.iter().sum();
+    let allocator = DynFreeListAllocator::sized_for(data_bytes, sizes.len());
+    assert_eq!(
+        FreeListConfig::new().arena_size_for(data_bytes, sizes.len()),
+        allocator.size()
+    );
+
+    for size in sizes {
+        let ptr = unsafe { allocator.alloc(Layout::from_size_align(size, 1).unwrap()) };
+        assert!(!ptr.is_null());
+    }
+}
+
+#[test]
+fn arena_size_for_counts_live_tracking_header() {
+    let config = FreeListConfig::new();
+    let tracked = FreeListConfig {
+        live_tracking: true,
+        ..config
+    };
+
+    assert_eq!(
+        config.arena_size_for(1000, 10)
+            + 10 * (tracked.allocation_overhead() - config.allocation_overhead()),
+        tracked.arena_size_for(1000, 10)
+    );
+    assert_eq!(NODE_LAYOUT_SIZE, config.arena_size_for(0, 0));
+}
+
+#[test]
+fn alloc_dealloc_restores_arena() {
+    let allocator = DynFreeListAllocator::new(512);
+    let layout = Layout::new::<[u64; 4]>();
+
+    let a = unsafe { allocator.alloc(layout) };
+    let b = unsafe { allocator.alloc(layout) };
+    assert!(!a.is_null() && !b.is_null());
+    assert_ne!(a, b);
+
+    unsafe {
+        allocator.dealloc(a, layout);
+        allocator.dealloc(b, layout);
+    }
+    allocator.debug_assert_valid();
+    let blocks: Vec<FreeBlock> = allocator.free_blocks().collect();
+    assert_eq!(
+        vec![FreeBlock {
+            offset: 0,
+            size: 512
+        }],
+        blocks
+    );
+    assert_eq!(0, allocator.stats().live_allocations);
+}
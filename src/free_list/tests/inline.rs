@@ -0,0 +1,46 @@
+use std::alloc::{GlobalAlloc, Layout};
+
+use crate::free_list::{FreeBlock, InlineFreeListAllocator};
+
+#[test]
+fn alloc_dealloc_in_inline_arena() {
+    let allocator = InlineFreeListAllocator::<256>::new();
+    let layout = Layout::new::<[u64; 4]>();
+
+    let ptr = unsafe { allocator.alloc(layout) };
+    let arena =
+        &allocator as *const _ as usize..&allocator as *const _ as usize + size_of_val(&allocator);
+    assert!(arena.contains(&(ptr as usize)));
+    assert_eq!(0, ptr as usize % layout.align());
+
+    unsafe { allocator.dealloc(ptr, layout) };
+    assert!(allocator.is_empty());
+    assert_eq!(256, allocator.capacity());
+}
+
+#[test]
+fn moved_allocator_rebases_free_list() {
+    let allocator = InlineFreeListAllocator::<256>::new();
+    let layout = Layout::new::<[u8; 32]>();
+    let a = unsafe { allocator.alloc(layout) };
+    let b = unsafe { allocator.alloc(layout) };
+    unsafe { allocator.dealloc(a, layout) };
+    let b_offset = b as usize - a as usize;
+    let blocks_before: Vec<FreeBlock> = allocator.free_blocks().collect();
+
+    // Move the allocator along with its arena
+    let moved = Box::new(allocator);
+    moved.debug_assert_valid();
+    let blocks: Vec<FreeBlock> = moved.free_blocks().collect();
+    assert_eq!(blocks_before, blocks);
+
+    // The freed block is found back at the new location
+    let a = unsafe { moved.alloc(layout) };
+    let b = unsafe { a.add(b_offset) };
+    unsafe {
+        moved.dealloc(a, layout);
+        moved.dealloc(b, layout);
+    }
+    moved.debug_assert_valid();
+    assert!(moved.is_empty());
+}
@@ -0,0 +1,61 @@
+use std::alloc::{GlobalAlloc, Layout};
+
+use crate::{
+    free_list::{FreeBlock, LocalFreeListAllocator},
+    region::RegionAllocator,
+};
+
+#[test]
+fn alloc_dealloc_restores_arena() {
+    let allocator = LocalFreeListAllocator::<256>::new();
+    let layout = Layout::new::<[u64; 4]>();
+
+    let a = unsafe { allocator.alloc(layout) };
+    let b = unsafe { allocator.alloc(layout) };
+    assert!(!a.is_null() && !b.is_null());
+    assert_ne!(a, b);
+    assert_eq!(2, allocator.stats().allocations);
+
+    unsafe {
+        allocator.dealloc(b, layout);
+        allocator.dealloc(a, layout);
+    }
+    allocator.debug_assert_valid();
+    assert!(allocator.is_empty());
+    let blocks: Vec<FreeBlock> = allocator.free_blocks().collect();
+    assert_eq!(
+        vec![FreeBlock {
+            offset: 0,
+            size: 256
+        }],
+        blocks
+    );
+}
+
+#[test]
+fn reset_releases_every_allocation() {
+    let allocator = LocalFreeListAllocator::<256>::new();
+    let layout = Layout::new::<[u8; 64]>();
+    unsafe {
+        allocator.alloc(layout);
+        allocator.alloc(layout);
+        allocator.reset();
+    }
+
+    assert!(allocator.is_empty());
+    assert!(!unsafe { allocator.alloc(Layout::new::<[u8; 128]>()) }.is_null());
+}
+
+#[test]
+fn allocator_can_move_to_another_thread() {
+    let allocator = LocalFreeListAllocator::<256>::new();
+    let layout = Layout::new::<u64>();
+    let ptr = unsafe { allocator.alloc(layout) } as usize;
+
+    std::thread::spawn(move || {
+        unsafe { allocator.dealloc(ptr as *mut u8, layout) };
+        assert!(allocator.is_empty());
+    })
+    .join()
+    .unwrap();
+}
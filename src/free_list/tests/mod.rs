@@ -1,2 +1,9 @@
 mod alloc_root;
+mod allocator;
+mod builder;
+mod dynamic;
+mod inline;
+mod local;
+mod model;
 mod node;
+mod sharded;
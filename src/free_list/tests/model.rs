@@ -0,0 +1,160 @@
+use std::alloc::{GlobalAlloc, Layout};
+
+use crate::free_list::{
+    FitStrategy, FreeBlock, FreeListAllocator, FreeListConfig, MetadataPlacement,
+};
+
+const ARENA_SIZE: usize = 4096;
+const OPERATIONS: usize = 2000;
+
+/// Live allocation of the reference model: its contents are filled with `tag`
+struct Region {
+    ptr: *mut u8,
+    layout: Layout,
+    tag: u8,
+}
+
+/// Small deterministic pseudo-random generator (xorshift64), so that failures can be replayed from the seed.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Random value in `0..bound`
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+}
+
+/// Check the model invariants: live regions lie within the arena, don't overlap, and kept their contents.
+fn check_regions(allocator: &FreeListAllocator<ARENA_SIZE>, live: &[Region]) {
+    let mut ranges: Vec<(usize, usize)> = live
+        .iter()
+        .map(|region| {
+            let offset = allocator
+                .offset_of(region.ptr)
+                .expect("live allocation outside of the arena");
+            assert!(offset + region.layout.size() <= ARENA_SIZE);
+            assert_eq!(0, region.ptr as usize % region.layout.align());
+            let bytes = unsafe { std::slice::from_raw_parts(region.ptr, region.layout.size()) };
+            assert!(bytes.iter().all(|byte| *byte == region.tag));
+            (offset, offset + region.layout.size())
+        })
+        .collect();
+    ranges.sort_unstable();
+    for pair in ranges.windows(2) {
+        assert!(pair[0].1 <= pair[1].0, "live allocations overlap: {pair:?}");
+    }
+}
+
+/// Run a random sequence of allocations, deallocations and reallocations against a model of the live regions.
+fn run_model(seed: u64, config: FreeListConfig) {
+    let allocator = FreeListAllocator::<ARENA_SIZE>::with_config(config);
+    let mut rng = Rng(seed);
+    let mut live: Vec<Region> = Vec::new();
+
+    for operation in 0..OPERATIONS {
+        let tag = (operation % 251) as u8 + 1;
+        match rng.below(4) {
+            // Allocate as often as anything else, deallocate once 64 values are live
+            0 | 1 if live.len() < 64 => {
+                let layout =
+                    Layout::from_size_align(1 + rng.below(160), 1 << rng.below(6)).unwrap();
+                let ptr = unsafe { allocator.alloc(layout) };
+                if !ptr.is_null() {
+                    unsafe { ptr.write_bytes(tag, layout.size()) };
+                    live.push(Region { ptr, layout, tag });
+                }
+            }
+            2 if !live.is_empty() => {
+                let index = rng.below(live.len());
+                let region = &mut live[index];
+                let new_size = 1 + rng.below(200);
+                let ptr = unsafe { allocator.realloc(region.ptr, region.layout, new_size) };
+                if !ptr.is_null() {
+                    // The kept prefix must have been copied, then the whole value gets a new tag
+                    let kept = region.layout.size().min(new_size);
+                    let bytes = unsafe { std::slice::from_raw_parts(ptr, kept) };
+                    assert!(bytes.iter().all(|byte| *byte == region.tag));
+                    unsafe { ptr.write_bytes(tag, new_size) };
+                    *region = Region {
+                        ptr,
+                        layout: Layout::from_size_align(new_size, region.layout.align()).unwrap(),
+                        tag,
+                    };
+                }
+            }
+            _ if !live.is_empty() => {
+                let index = rng.below(live.len());
+                let region = live.swap_remove(index);
+                unsafe { allocator.dealloc(region.ptr, region.layout) };
+            }
+            _ => {}
+        }
+        check_regions(&allocator, &live);
+        allocator.debug_assert_valid();
+    }
+
+    for region in live.drain(..) {
+        unsafe { allocator.dealloc(region.ptr, region.layout) };
+    }
+    allocator.defragment();
+    allocator.debug_assert_valid();
+    assert!(allocator.is_empty());
+    let blocks: Vec<FreeBlock> = allocator.free_blocks().collect();
+    assert_eq!(
+        vec![FreeBlock {
+            offset: 0,
+            size: ARENA_SIZE
+        }],
+        blocks
+    );
+}
+
+#[test]
+fn model_first_fit() {
+    for seed in 1..=8 {
+        run_model(seed, FreeListConfig::new());
+    }
+}
+
+#[test]
+fn model_best_and_worst_fit() {
+    for fit_strategy in [FitStrategy::BestFit, FitStrategy::WorstFit] {
+        run_model(
+            0x5EED,
+            FreeListConfig {
+                fit_strategy,
+                ..FreeListConfig::new()
+            },
+        );
+    }
+}
+
+#[test]
+fn model_alternative_configs() {
+    let configs = [
+        FreeListConfig {
+            coalesce_on_free: false,
+            ..FreeListConfig::new()
+        },
+        FreeListConfig {
+            recent_free_cache: true,
+            live_tracking: true,
+            ..FreeListConfig::new()
+        },
+        FreeListConfig {
+            metadata_placement: MetadataPlacement::BeforeValue,
+            min_block_size: 64,
+            ..FreeListConfig::new()
+        },
+    ];
+    for config in configs {
+        run_model(0xA110C, config);
+    }
+}
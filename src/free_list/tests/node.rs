@@ -1,4 +1,5 @@
 use crate::free_list::node::*;
+use crate::free_list::MetadataPlacement;
 
 #[test]
 fn try_get_alloc_specs_not_enough_size() {
@@ -7,7 +8,14 @@ fn try_get_alloc_specs_not_enough_size() {
         next_ptr: None,
     };
 
-    let result = node.try_get_alloc_specs(64, 1, std::ptr::null::<u8>());
+    let result = node.try_get_alloc_specs(
+        64,
+        1,
+        std::ptr::null::<u8>(),
+        NODE_LAYOUT_SIZE,
+        0,
+        MetadataPlacement::AfterValue,
+    );
     assert!(result.is_err())
 }
 
@@ -18,7 +26,14 @@ fn try_get_alloc_specs_not_enough_with_padding() {
         next_ptr: None,
     };
 
-    let result = node.try_get_alloc_specs(16, 32, 0x5 as *const u8);
+    let result = node.try_get_alloc_specs(
+        16,
+        32,
+        0x5 as *const u8,
+        NODE_LAYOUT_SIZE,
+        0,
+        MetadataPlacement::AfterValue,
+    );
     assert!(result.is_err())
 }
 
@@ -29,7 +44,14 @@ fn try_get_alloc_specs_not_enough_for_future_node() {
         next_ptr: None,
     };
 
-    let result = node.try_get_alloc_specs(4, 1, std::ptr::null::<u8>());
+    let result = node.try_get_alloc_specs(
+        4,
+        1,
+        std::ptr::null::<u8>(),
+        NODE_LAYOUT_SIZE,
+        0,
+        MetadataPlacement::AfterValue,
+    );
     assert!(result.is_err())
 }
 
@@ -41,7 +63,14 @@ fn try_get_alloc_specs_can_add_node() {
     };
 
     let size = 4;
-    let result = node.try_get_alloc_specs(size, 1, std::ptr::null::<u8>());
+    let result = node.try_get_alloc_specs(
+        size,
+        1,
+        std::ptr::null::<u8>(),
+        NODE_LAYOUT_SIZE,
+        0,
+        MetadataPlacement::AfterValue,
+    );
     assert!(result.is_ok());
     let specs = result.unwrap();
     assert_eq!(0, specs.padding);
@@ -67,8 +96,16 @@ fn try_get_alloc_specs_cannot_add_node() {
         next_ptr: None,
     };
 
-    let size = 32;
-    let result = node.try_get_alloc_specs(size, 1, std::ptr::null::<u8>());
+    // One byte short of leaving room for a Node after the allocation, whatever the metadata size
+    let size = 64 - ALLOCATION_METADATA_LAYOUT_SIZE - NODE_LAYOUT_SIZE + 1;
+    let result = node.try_get_alloc_specs(
+        size,
+        1,
+        std::ptr::null::<u8>(),
+        NODE_LAYOUT_SIZE,
+        0,
+        MetadataPlacement::AfterValue,
+    );
     assert!(result.is_ok());
     let specs = result.unwrap();
     assert_eq!(0, specs.padding);
@@ -79,3 +116,309 @@ fn try_get_alloc_specs_cannot_add_node() {
     );
     assert_eq!(0, specs.remaining_size);
 }
+
+#[test]
+#[cfg(target_pointer_width = "64")]
+fn try_get_alloc_specs_padding_too_large_for_metadata() {
+    let node = Node {
+        size: usize::MAX / 2,
+        next_ptr: None,
+    };
+
+    // Padding of 2^33 - 3 bytes can't be stored in metadata
+    let result = node.try_get_alloc_specs(
+        1,
+        1 << 33,
+        0x3 as *const u8,
+        NODE_LAYOUT_SIZE,
+        0,
+        MetadataPlacement::AfterValue,
+    );
+    assert!(result.is_err())
+}
+
+#[test]
+fn allocation_metadata_read_write_unaligned() {
+    let mut buffer = [0u8; 2 * ALLOCATION_METADATA_LAYOUT_SIZE];
+    let metadata = AllocationMetadata {
+        align_padding: 7,
+        fill_padding: 13,
+        #[cfg(debug_assertions)]
+        align_shift: 3,
+        #[cfg(debug_assertions)]
+        magic: METADATA_MAGIC,
+    };
+
+    // Odd offset: not aligned for the metadata structure
+    let metadata_ptr = unsafe { buffer.as_mut_ptr().add(1) };
+    unsafe { metadata.write(metadata_ptr) };
+    let result = unsafe { AllocationMetadata::read(metadata_ptr) };
+
+    assert_eq!(7, result.align_padding);
+    assert_eq!(13, result.fill_padding);
+    #[cfg(debug_assertions)]
+    assert_eq!(3, result.align_shift);
+    result.check_magic();
+}
+
+#[test]
+fn allocation_metadata_is_compact() {
+    // The alignment and the magic number are only recorded in debug builds
+    let fields = if cfg!(debug_assertions) { 4 } else { 2 };
+    assert_eq!(
+        fields * std::mem::size_of::<u32>(),
+        ALLOCATION_METADATA_LAYOUT_SIZE
+    );
+}
+
+#[test]
+fn try_get_alloc_specs_trailing_node_aligned() {
+    let node = Node {
+        size: 128,
+        next_ptr: None,
+    };
+    let node_ptr = NODE_LAYOUT_ALIGN as *const u8;
+
+    // Block end isn't a multiple of the Node alignment without additional fill padding
+    let size = NODE_LAYOUT_SIZE + 1 - ALLOCATION_METADATA_LAYOUT_SIZE;
+    let specs = node
+        .try_get_alloc_specs(
+            size,
+            1,
+            node_ptr,
+            NODE_LAYOUT_SIZE,
+            0,
+            MetadataPlacement::AfterValue,
+        )
+        .unwrap();
+
+    let trailing_node_offset =
+        specs.padding + specs.size + ALLOCATION_METADATA_LAYOUT_SIZE + specs.fill_padding;
+    assert_eq!(
+        0,
+        (node_ptr as usize + trailing_node_offset) % NODE_LAYOUT_ALIGN
+    );
+    assert_eq!(NODE_LAYOUT_ALIGN - 1, specs.fill_padding);
+    assert_eq!(node.size - trailing_node_offset, specs.remaining_size);
+}
+
+#[test]
+fn try_get_alloc_specs_remaining_fits_node() {
+    let node = Node {
+        size: 2 * NODE_LAYOUT_SIZE - 1,
+        next_ptr: None,
+    };
+
+    // Block is filled up to a Node size: the remaining bytes can't hold another Node
+    let specs = node
+        .try_get_alloc_specs(
+            1,
+            1,
+            std::ptr::null::<u8>(),
+            NODE_LAYOUT_SIZE,
+            0,
+            MetadataPlacement::AfterValue,
+        )
+        .unwrap();
+    assert_eq!(0, specs.remaining_size);
+}
+
+#[test]
+fn try_get_alloc_specs_remaining_below_min_block_size() {
+    let node = Node {
+        size: 128,
+        next_ptr: None,
+    };
+
+    let specs = node
+        .try_get_alloc_specs(
+            32,
+            1,
+            std::ptr::null::<u8>(),
+            128,
+            0,
+            MetadataPlacement::AfterValue,
+        )
+        .unwrap();
+    assert_eq!(0, specs.remaining_size);
+    assert_eq!(
+        node.size - 32 - ALLOCATION_METADATA_LAYOUT_SIZE,
+        specs.fill_padding
+    );
+}
+
+#[test]
+fn try_get_alloc_specs_remaining_above_min_block_size() {
+    let node = Node {
+        size: 128,
+        next_ptr: None,
+    };
+
+    let specs = node
+        .try_get_alloc_specs(
+            32,
+            1,
+            std::ptr::null::<u8>(),
+            64,
+            0,
+            MetadataPlacement::AfterValue,
+        )
+        .unwrap();
+    // The trailing Node is aligned
+    assert_eq!(
+        128 - (32 + ALLOCATION_METADATA_LAYOUT_SIZE).next_multiple_of(NODE_LAYOUT_ALIGN),
+        specs.remaining_size
+    );
+}
+
+#[test]
+fn try_get_alloc_specs_overflowing_size_fails() {
+    // Corrupted Node: its size would allow any layout
+    let node = Node {
+        size: usize::MAX,
+        next_ptr: None,
+    };
+
+    let result = node.try_get_alloc_specs(
+        usize::MAX - 4,
+        1,
+        0x10 as *const u8,
+        NODE_LAYOUT_SIZE,
+        0,
+        MetadataPlacement::AfterValue,
+    );
+    assert!(result.is_err())
+}
+
+#[test]
+fn try_get_alloc_specs_exact_fit_uses_whole_node() {
+    let node = Node {
+        size: 128,
+        next_ptr: None,
+    };
+
+    let size = node.size - ALLOCATION_METADATA_LAYOUT_SIZE;
+    let specs = node
+        .try_get_alloc_specs(
+            size,
+            1,
+            std::ptr::null::<u8>(),
+            NODE_LAYOUT_SIZE,
+            0,
+            MetadataPlacement::AfterValue,
+        )
+        .unwrap();
+    assert_eq!(0, specs.padding);
+    assert_eq!(size, specs.size);
+    assert_eq!(0, specs.fill_padding);
+    assert_eq!(0, specs.remaining_size);
+}
+
+#[test]
+fn try_get_alloc_specs_exact_fit_plus_one_byte_fails() {
+    let node = Node {
+        size: 128,
+        next_ptr: None,
+    };
+
+    // The value alone fits, but not along with its metadata
+    let size = node.size - ALLOCATION_METADATA_LAYOUT_SIZE + 1;
+    let result = node.try_get_alloc_specs(
+        size,
+        1,
+        std::ptr::null::<u8>(),
+        NODE_LAYOUT_SIZE,
+        0,
+        MetadataPlacement::AfterValue,
+    );
+    assert!(result.is_err())
+}
+
+#[test]
+fn try_get_alloc_specs_node_sized_block_holds_node() {
+    for node_size in [NODE_LAYOUT_SIZE, NODE_LAYOUT_SIZE + 1] {
+        let node = Node {
+            size: node_size,
+            next_ptr: None,
+        };
+        for placement in [
+            MetadataPlacement::AfterValue,
+            MetadataPlacement::BeforeValue,
+        ] {
+            let specs = node
+                .try_get_alloc_specs(1, 1, std::ptr::null::<u8>(), NODE_LAYOUT_SIZE, 0, placement)
+                .unwrap();
+
+            // The whole Node is used, so that the block can hold a Node once freed
+            let block_size =
+                specs.padding + specs.size + placement.trailing_size() + specs.fill_padding;
+            assert_eq!(node_size, block_size);
+            assert_eq!(0, specs.remaining_size);
+        }
+    }
+}
+
+/// Try to allocate a value occupying exactly 64 bytes with its metadata, in a Node of `64 + extra` bytes.
+fn alloc_specs_with_extra_bytes(extra: usize) -> AllocationSpecs {
+    let node = Node {
+        size: 64 + extra,
+        next_ptr: None,
+    };
+    node.try_get_alloc_specs(
+        64 - ALLOCATION_METADATA_LAYOUT_SIZE,
+        1,
+        std::ptr::null::<u8>(),
+        NODE_LAYOUT_SIZE,
+        0,
+        MetadataPlacement::AfterValue,
+    )
+    .unwrap()
+}
+
+#[test]
+fn try_get_alloc_specs_split_boundaries() {
+    // Exact fit
+    let specs = alloc_specs_with_extra_bytes(0);
+    assert_eq!((0, 0), (specs.fill_padding, specs.remaining_size));
+
+    // One byte short of a Node: given to the allocation
+    let specs = alloc_specs_with_extra_bytes(NODE_LAYOUT_SIZE - 1);
+    assert_eq!(
+        (NODE_LAYOUT_SIZE - 1, 0),
+        (specs.fill_padding, specs.remaining_size)
+    );
+
+    // Exactly a Node: split off
+    let specs = alloc_specs_with_extra_bytes(NODE_LAYOUT_SIZE);
+    assert_eq!(
+        (0, NODE_LAYOUT_SIZE),
+        (specs.fill_padding, specs.remaining_size)
+    );
+
+    let specs = alloc_specs_with_extra_bytes(NODE_LAYOUT_SIZE + 1);
+    assert_eq!(
+        (0, NODE_LAYOUT_SIZE + 1),
+        (specs.fill_padding, specs.remaining_size)
+    );
+}
+
+#[test]
+fn try_get_alloc_specs_remaining_exactly_min_block_size() {
+    let node = Node {
+        size: 128,
+        next_ptr: None,
+    };
+
+    let specs = node
+        .try_get_alloc_specs(
+            64 - ALLOCATION_METADATA_LAYOUT_SIZE,
+            1,
+            std::ptr::null::<u8>(),
+            64,
+            0,
+            MetadataPlacement::AfterValue,
+        )
+        .unwrap();
+    assert_eq!(0, specs.fill_padding);
+    assert_eq!(64, specs.remaining_size);
+}
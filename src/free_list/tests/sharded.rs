@@ -0,0 +1,74 @@
+use std::{
+    alloc::{GlobalAlloc, Layout},
+    thread,
+};
+
+use crate::free_list::{FreeListConfig, ShardedFreeListAllocator};
+
+#[test]
+fn alloc_dealloc_across_threads() {
+    let allocator = ShardedFreeListAllocator::<1024, 4>::new();
+    let layout = Layout::new::<[u64; 4]>();
+
+    // Allocate from several threads, deallocate from the main one
+    let ptrs: Vec<usize> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                scope.spawn(|| {
+                    let ptr = unsafe { allocator.alloc(layout) };
+                    assert!(!ptr.is_null());
+                    unsafe { (ptr as *mut [u64; 4]).write([7; 4]) };
+                    ptr as usize
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect()
+    });
+    assert!(!allocator.is_empty());
+
+    for ptr in ptrs {
+        assert_eq!([7; 4], unsafe { (ptr as *const [u64; 4]).read() });
+        unsafe { allocator.dealloc(ptr as *mut u8, layout) };
+    }
+    allocator.debug_assert_valid();
+    assert!(allocator.is_empty());
+    assert_eq!(4, allocator.stats().free_node_count);
+}
+
+#[test]
+fn exhausted_shard_spills_to_other_shards() {
+    let allocator = ShardedFreeListAllocator::<256, 2>::new();
+    let layout = Layout::from_size_align(200, 8).unwrap();
+
+    // Each shard holds a single allocation of this size
+    let first = unsafe { allocator.alloc(layout) };
+    let second = unsafe { allocator.alloc(layout) };
+    assert!(!first.is_null() && !second.is_null());
+    assert!(unsafe { allocator.alloc(layout) }.is_null());
+
+    // Freed in its owning shard
+    unsafe { allocator.dealloc(first, layout) };
+    assert_eq!(first, unsafe { allocator.alloc(layout) });
+    unsafe {
+        allocator.dealloc(first, layout);
+        allocator.dealloc(second, layout);
+    }
+    assert!(allocator.is_empty());
+}
+
+#[test]
+fn stats_sum_shards() {
+    let allocator = ShardedFreeListAllocator::<200, 3>::with_config(FreeListConfig::new());
+    let layout = Layout::new::<u64>();
+    let ptr = unsafe { allocator.alloc(layout) };
+
+    let stats = allocator.stats();
+    assert_eq!(600, allocator.capacity());
+    assert_eq!(600, stats.free_bytes + stats.used_bytes);
+    assert_eq!(1, stats.live_allocations);
+    assert_eq!(200, stats.largest_free_block);
+    unsafe { allocator.dealloc(ptr, layout) };
+}
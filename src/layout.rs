@@ -0,0 +1,34 @@
+use crate::error::AllocError;
+use std::alloc::Layout;
+
+/// Get the layout of an array of `len` values of type `T`, as used by the slice and array allocation methods.
+///
+/// Unlike multiplying `len` by the value size, this never overflows: huge lengths fail cleanly.
+///
+/// **Returns**: `AllocError::LayoutOverflow` if the array size exceeds `isize::MAX`
+pub fn array_layout<T>(len: usize) -> Result<Layout, AllocError> {
+    Ok(Layout::array::<T>(len)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn array_layout_of_values() {
+        assert_eq!(Ok(Layout::new::<[u64; 3]>()), array_layout::<u64>(3));
+        assert_eq!(Ok(Layout::new::<[u16; 0]>()), array_layout::<u16>(0));
+    }
+
+    #[test]
+    fn array_layout_overflow() {
+        assert_eq!(
+            Err(AllocError::LayoutOverflow),
+            array_layout::<u64>(usize::MAX)
+        );
+        assert_eq!(
+            Err(AllocError::LayoutOverflow),
+            array_layout::<u16>(isize::MAX as usize / 2 + 1)
+        );
+    }
+}
@@ -1,5 +1,21 @@
+#[cfg(feature = "buddy")]
+pub mod buddy;
+
 #[cfg(feature = "bump")]
 pub mod bumper;
 
+pub mod error;
+pub mod layout;
+pub mod region;
+
 #[cfg(feature = "free_list")]
 pub mod free_list;
+
+#[cfg(feature = "pool")]
+pub mod pool;
+
+#[cfg(feature = "slab")]
+pub mod slab;
+
+#[cfg(feature = "tiered")]
+pub mod tiered;
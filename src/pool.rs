@@ -0,0 +1,205 @@
+use crate::layout::array_layout;
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+    ptr,
+    sync::{
+        atomic::{AtomicPtr, Ordering},
+        Mutex,
+    },
+};
+
+/// Typed object pool with a fixed capacity.
+///
+/// Values are placed in slots of a `System`-backed arena, allocated once on creation.
+/// Acquiring a value returns a `PoolBox` handle: it derefs to the value and returns the slot to the pool when dropped.
+/// Acquiring and releasing never call the global allocator.
+///
+/// ## Usage
+/// ```
+/// use allocator::pool::Pool;
+///
+/// let pool = Pool::<u64, 16>::new();
+/// let mut value = pool.acquire_with(42).unwrap();
+/// *value += 1;
+/// drop(value); // Slot is returned to the pool
+/// ```
+pub struct Pool<T, const CAP: usize> {
+    arena_ptr: AtomicPtr<T>,
+    free_slots: Mutex<FreeSlots<CAP>>,
+    _marker: PhantomData<T>,
+}
+
+/// Stack of free slot indices.
+struct FreeSlots<const CAP: usize> {
+    indices: [usize; CAP],
+    len: usize,
+}
+
+impl<T, const CAP: usize> Pool<T, CAP> {
+    /// Create a new pool, allocating room for `CAP` values.
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        let arena_ptr = if Self::arena_layout().size() == 0 {
+            // Zero-sized values or capacity: no memory is required
+            ptr::dangling_mut()
+        } else {
+            unsafe { GlobalAlloc::alloc(&System, Self::arena_layout()) as *mut T }
+        };
+
+        // Every slot is free, lowest indices are acquired first
+        let mut indices = [0; CAP];
+        for (i, index) in indices.iter_mut().enumerate() {
+            *index = CAP - 1 - i;
+        }
+        let len = if arena_ptr.is_null() { 0 } else { CAP };
+
+        Pool {
+            arena_ptr: AtomicPtr::new(arena_ptr),
+            free_slots: Mutex::new(FreeSlots { indices, len }),
+            _marker: PhantomData,
+        }
+    }
+
+    fn arena_layout() -> Layout {
+        array_layout::<T>(CAP).expect("pool capacity overflows")
+    }
+
+    /// Place the value in a free slot of the pool.
+    ///
+    /// **Returns**: handle to the value, None if the pool is exhausted
+    pub fn acquire_with(&self, value: T) -> Option<PoolBox<'_, T, CAP>> {
+        let index = {
+            let mut free_slots = self.free_slots.lock().unwrap();
+            if free_slots.len == 0 {
+                return None;
+            }
+            free_slots.len -= 1;
+            free_slots.indices[free_slots.len]
+        };
+
+        unsafe { ptr::write(self.slot_ptr(index), value) };
+        Some(PoolBox {
+            pool: self,
+            index,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Count of values that can still be acquired.
+    pub fn available(&self) -> usize {
+        self.free_slots.lock().unwrap().len
+    }
+
+    fn slot_ptr(&self, index: usize) -> *mut T {
+        unsafe { self.arena_ptr.load(Ordering::Acquire).add(index) }
+    }
+
+    /// Return a slot to the pool, its value must have been dropped.
+    fn release(&self, index: usize) {
+        let mut free_slots = self.free_slots.lock().unwrap();
+        let len = free_slots.len;
+        free_slots.indices[len] = index;
+        free_slots.len += 1;
+    }
+}
+
+impl<T: Default, const CAP: usize> Pool<T, CAP> {
+    /// Place a default value in a free slot of the pool.
+    ///
+    /// **Returns**: handle to the value, None if the pool is exhausted
+    pub fn acquire(&self) -> Option<PoolBox<'_, T, CAP>> {
+        self.acquire_with(T::default())
+    }
+}
+
+impl<T, const CAP: usize> Drop for Pool<T, CAP> {
+    fn drop(&mut self) {
+        // Handles borrow the pool: no value is alive anymore
+        let arena_ptr = self.arena_ptr.load(Ordering::Acquire);
+        if Self::arena_layout().size() != 0 && !arena_ptr.is_null() {
+            unsafe { GlobalAlloc::dealloc(&System, arena_ptr as *mut u8, Self::arena_layout()) };
+        }
+    }
+}
+
+/// Handle to a value stored in a `Pool`. The value is dropped and its slot released when the handle is dropped.
+pub struct PoolBox<'a, T, const CAP: usize> {
+    pool: &'a Pool<T, CAP>,
+    index: usize,
+    _marker: PhantomData<T>,
+}
+
+impl<T, const CAP: usize> Deref for PoolBox<'_, T, CAP> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.pool.slot_ptr(self.index) }
+    }
+}
+
+impl<T, const CAP: usize> DerefMut for PoolBox<'_, T, CAP> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.pool.slot_ptr(self.index) }
+    }
+}
+
+impl<T, const CAP: usize> Drop for PoolBox<'_, T, CAP> {
+    fn drop(&mut self) {
+        unsafe { ptr::drop_in_place(self.pool.slot_ptr(self.index)) };
+        self.pool.release(self.index);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::pool::*;
+    use std::rc::Rc;
+
+    #[test]
+    fn acquire_until_exhausted() {
+        let pool = Pool::<u32, 2>::new();
+
+        let first = pool.acquire_with(1).unwrap();
+        let second = pool.acquire_with(2).unwrap();
+        assert!(pool.acquire_with(3).is_none());
+        assert_eq!(0, pool.available());
+
+        assert_eq!(1, *first);
+        assert_eq!(2, *second);
+    }
+
+    #[test]
+    fn drop_returns_slot() {
+        let pool = Pool::<u32, 1>::new();
+
+        let value = pool.acquire().unwrap();
+        assert_eq!(0, *value);
+        drop(value);
+
+        assert_eq!(1, pool.available());
+        assert!(pool.acquire().is_some());
+    }
+
+    #[test]
+    fn drop_runs_value_destructor() {
+        let pool = Pool::<Rc<()>, 4>::new();
+        let counter = Rc::new(());
+
+        let value = pool.acquire_with(counter.clone()).unwrap();
+        assert_eq!(2, Rc::strong_count(&counter));
+        drop(value);
+
+        assert_eq!(1, Rc::strong_count(&counter));
+    }
+
+    #[test]
+    fn deref_mut_updates_value() {
+        let pool = Pool::<String, 4>::new();
+
+        let mut value = pool.acquire().unwrap();
+        value.push_str("pool");
+        assert_eq!("pool", value.as_str());
+    }
+}
@@ -0,0 +1,31 @@
+use std::alloc::GlobalAlloc;
+
+/// Allocator managing a memory region, which can be reset as a whole.
+///
+/// Allocation and deallocation come from `GlobalAlloc`, so that generic code, such as a benchmark,
+/// can drive every allocator of this crate the same way, and swap them without further changes.
+///
+/// ## Usage
+/// ```
+/// use allocator::region::RegionAllocator;
+/// use std::alloc::Layout;
+///
+/// fn churn<A: RegionAllocator>(allocator: &A) {
+///     let layout = Layout::new::<[u64; 4]>();
+///     for _ in 0..16 {
+///         let ptr = unsafe { allocator.alloc(layout) };
+///         unsafe { allocator.dealloc(ptr, layout) };
+///     }
+///     unsafe { allocator.reset() };
+/// }
+/// # #[cfg(feature = "bump")]
+/// # churn(&allocator::bumper::BumpAllocator::<1024>::new());
+/// ```
+pub trait RegionAllocator: GlobalAlloc {
+    /// Release every allocation of the region at once, making the whole region available again.
+    ///
+    /// # Safety
+    /// Every pointer allocated so far is invalidated: none of them may be used or deallocated afterwards.
+    /// No allocation or deallocation may run concurrently.
+    unsafe fn reset(&self);
+}
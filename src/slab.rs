@@ -0,0 +1,220 @@
+use crate::region::RegionAllocator;
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    mem::size_of,
+    ptr::{self, null_mut},
+    sync::{
+        atomic::{AtomicPtr, Ordering},
+        Mutex,
+    },
+};
+
+/// Alignment cap for the arena, slots are never aligned further than a page.
+const MAX_SLOT_ALIGN: usize = 4096;
+
+/// Heap allocator carving its arena into fixed size slots.
+///
+/// Allocation and deallocation are constant time: free slots are kept in an intrusive linked list
+/// of slot indices, each free slot storing the index of the next one.
+/// Any layout fitting in a slot can be allocated, in terms of both size and alignment.
+///
+/// * `N`: arena size, in bytes
+/// * `BLOCK`: slot size, in bytes. It must be able to hold a `usize` (free slot link)
+pub struct SlabAllocator<const N: usize, const BLOCK: usize> {
+    arena_ptr: AtomicPtr<u8>,
+    slots: Mutex<SlotList>,
+}
+
+/// Free slots tracking.
+struct SlotList {
+    /// First free slot index of the intrusive linked list, if any
+    free_head: Option<usize>,
+    /// Index of the first slot that was never allocated: all slots from it are free but not linked yet
+    unused: usize,
+}
+
+/// Marker stored in a free slot to indicate the end of the free list.
+const NO_NEXT_SLOT: usize = usize::MAX;
+
+impl<const N: usize, const BLOCK: usize> SlabAllocator<N, BLOCK> {
+    /// Slot count of the arena.
+    pub const CAPACITY: usize = N / BLOCK;
+    /// Alignment guaranteed for each slot.
+    pub const SLOT_ALIGN: usize = {
+        let align = 1 << BLOCK.trailing_zeros();
+        if align > MAX_SLOT_ALIGN {
+            MAX_SLOT_ALIGN
+        } else {
+            align
+        }
+    };
+
+    /// Create a new instance of slab allocator, initialize the heap memory region for future allocations.
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        const {
+            assert!(
+                BLOCK >= size_of::<usize>(),
+                "slab block size must be able to hold a usize"
+            );
+            assert!(N >= BLOCK, "slab arena must hold at least one block");
+        };
+
+        let arena_ptr = unsafe { GlobalAlloc::alloc(&System, Self::arena_layout()) };
+        Self {
+            arena_ptr: AtomicPtr::new(arena_ptr),
+            slots: Mutex::new(SlotList {
+                free_head: None,
+                unused: 0,
+            }),
+        }
+    }
+
+    /// Layout of the arena, aligned so that each slot is aligned to `SLOT_ALIGN`.
+    fn arena_layout() -> Layout {
+        Layout::from_size_align(N, Self::SLOT_ALIGN).unwrap()
+    }
+
+    /// Check if the given layout fits in a slot.
+    pub fn fits(layout: Layout) -> bool {
+        layout.size() <= BLOCK && layout.align() <= Self::SLOT_ALIGN
+    }
+
+    /// Get the pointer to the slot at the given index.
+    fn slot_ptr(&self, index: usize) -> *mut u8 {
+        unsafe { self.arena_ptr.load(Ordering::Acquire).add(index * BLOCK) }
+    }
+}
+
+unsafe impl<const N: usize, const BLOCK: usize> GlobalAlloc for SlabAllocator<N, BLOCK> {
+    /// Allocate a slot for a layout, in constant time.
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if !Self::fits(layout) || self.arena_ptr.load(Ordering::Acquire).is_null() {
+            return null_mut();
+        }
+
+        let mut slots = self.slots.lock().unwrap();
+        let index = match slots.free_head {
+            Some(index) => {
+                // Pop the free list head
+                let next = ptr::read_unaligned(self.slot_ptr(index) as *const usize);
+                slots.free_head = if next == NO_NEXT_SLOT {
+                    None
+                } else {
+                    Some(next)
+                };
+                index
+            }
+            None if slots.unused < Self::CAPACITY => {
+                // Take a never used slot
+                slots.unused += 1;
+                slots.unused - 1
+            }
+            None => return null_mut(), // All slots are allocated
+        };
+
+        self.slot_ptr(index)
+    }
+
+    /// Release a slot, in constant time.
+    unsafe fn dealloc(&self, ptr: *mut u8, _layout: Layout) {
+        let index = (ptr as usize - self.arena_ptr.load(Ordering::Acquire) as usize) / BLOCK;
+
+        // Push the slot as the free list head
+        let mut slots = self.slots.lock().unwrap();
+        let next = slots.free_head.unwrap_or(NO_NEXT_SLOT);
+        ptr::write_unaligned(ptr as *mut usize, next);
+        slots.free_head = Some(index);
+    }
+}
+
+impl<const N: usize, const BLOCK: usize> RegionAllocator for SlabAllocator<N, BLOCK> {
+    /// Release every slot at once, in constant time: slots become never used again.
+    unsafe fn reset(&self) {
+        let mut slots = self.slots.lock().unwrap();
+        slots.free_head = None;
+        slots.unused = 0;
+    }
+}
+
+impl<const N: usize, const BLOCK: usize> Drop for SlabAllocator<N, BLOCK> {
+    fn drop(&mut self) {
+        let arena_ptr = self.arena_ptr.load(Ordering::Acquire);
+        if !arena_ptr.is_null() {
+            unsafe { GlobalAlloc::dealloc(&System, arena_ptr, Self::arena_layout()) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::slab::*;
+
+    #[test]
+    fn reset_frees_all_slots() {
+        let slab = SlabAllocator::<64, 16>::new();
+        let layout = Layout::new::<u64>();
+        let first = unsafe { slab.alloc(layout) };
+        for _ in 1..SlabAllocator::<64, 16>::CAPACITY {
+            unsafe { slab.alloc(layout) };
+        }
+        assert!(unsafe { slab.alloc(layout) }.is_null());
+
+        unsafe { slab.reset() };
+        assert_eq!(first, unsafe { slab.alloc(layout) });
+    }
+
+    #[test]
+    fn alloc_too_large_layout_null() {
+        let slab = SlabAllocator::<256, 16>::new();
+        let ptr = unsafe { slab.alloc(Layout::new::<[u8; 32]>()) };
+        assert!(ptr.is_null());
+    }
+
+    #[test]
+    fn alloc_over_aligned_layout_null() {
+        let slab = SlabAllocator::<256, 16>::new();
+        let ptr = unsafe { slab.alloc(Layout::from_size_align(8, 32).unwrap()) };
+        assert!(ptr.is_null());
+    }
+
+    #[test]
+    fn alloc_all_slots() {
+        let slab = SlabAllocator::<64, 16>::new();
+        let layout = Layout::new::<u64>();
+
+        let mut ptrs = Vec::new();
+        for _ in 0..SlabAllocator::<64, 16>::CAPACITY {
+            let ptr = unsafe { slab.alloc(layout) };
+            assert!(!ptr.is_null());
+            assert_eq!(0, ptr as usize % SlabAllocator::<64, 16>::SLOT_ALIGN);
+            ptrs.push(ptr);
+        }
+
+        // Distinct slots
+        ptrs.sort();
+        ptrs.dedup();
+        assert_eq!(4, ptrs.len());
+
+        // Arena is full
+        let ptr = unsafe { slab.alloc(layout) };
+        assert!(ptr.is_null());
+    }
+
+    #[test]
+    fn dealloc_slot_is_reused() {
+        let slab = SlabAllocator::<64, 16>::new();
+        let layout = Layout::new::<u64>();
+
+        let first = unsafe { slab.alloc(layout) };
+        let second = unsafe { slab.alloc(layout) };
+        unsafe {
+            slab.dealloc(first, layout);
+            slab.dealloc(second, layout);
+        }
+
+        // Last freed slot is reused first
+        assert_eq!(second, unsafe { slab.alloc(layout) });
+        assert_eq!(first, unsafe { slab.alloc(layout) });
+    }
+}
@@ -0,0 +1,175 @@
+use crate::{bumper::BumpAllocator, region::RegionAllocator};
+use std::{
+    alloc::{GlobalAlloc, Layout},
+    ptr,
+};
+
+/// Composite allocator serving allocations from a bump arena of `N` bytes first, and from the `fallback` allocator
+/// (typically a `FreeListAllocator`) once the bump arena is full.
+///
+/// Bump allocations are the fastest, but they can't be freed one by one: deallocations are routed to the allocator
+/// owning the pointer, and the ones of the bump tier are no-ops. The bump tier is reset wholesale by `reset_bump`,
+/// at a point where none of its allocations is alive anymore (between two frames or two requests for instance),
+/// while the longer lived allocations that spilled to the fallback tier are kept.
+///
+/// ## Usage
+/// ```
+/// use allocator::{free_list::FreeListAllocator, tiered::TieredAllocator};
+/// use std::alloc::{GlobalAlloc, Layout};
+///
+/// let tiered = TieredAllocator::<1024, _>::new(FreeListAllocator::<4096>::new());
+/// let layout = Layout::new::<[u64; 4]>();
+///
+/// let ptr = unsafe { tiered.alloc(layout) };
+/// unsafe { tiered.dealloc(ptr, layout) };
+/// // No bump allocation is alive anymore
+/// unsafe { tiered.reset_bump() };
+/// ```
+pub struct TieredAllocator<const N: usize, F: GlobalAlloc> {
+    bump: BumpAllocator<N>,
+    fallback: F,
+}
+
+impl<const N: usize, F: GlobalAlloc> TieredAllocator<N, F> {
+    /// Create an allocator with a new bump arena of `N` bytes, spilling to the given allocator.
+    pub fn new(fallback: F) -> Self {
+        Self::with_bump(BumpAllocator::new(), fallback)
+    }
+
+    /// Create an allocator from an existing bump allocator, spilling to the given allocator.
+    pub fn with_bump(bump: BumpAllocator<N>, fallback: F) -> Self {
+        TieredAllocator { bump, fallback }
+    }
+
+    /// Get the bump tier, to read its statistics for instance.
+    pub fn bump(&self) -> &BumpAllocator<N> {
+        &self.bump
+    }
+
+    /// Get the fallback tier, to read its statistics for instance.
+    pub fn fallback(&self) -> &F {
+        &self.fallback
+    }
+
+    /// Release every allocation of the bump tier at once, so that its arena can be allocated again.
+    /// Allocations of the fallback tier are kept.
+    ///
+    /// # Safety
+    /// No allocation served by the bump tier may be used or deallocated afterwards.
+    /// No allocation or deallocation may run concurrently, on either tier: the bump tier `RegionAllocator::reset`
+    /// contract applies, and deallocations are routed to a tier by checking the bump arena.
+    pub unsafe fn reset_bump(&self) {
+        self.bump.reset();
+    }
+}
+
+unsafe impl<const N: usize, F: GlobalAlloc> GlobalAlloc for TieredAllocator<N, F> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.bump.alloc(layout);
+        if !ptr.is_null() {
+            return ptr;
+        }
+        self.fallback.alloc(layout)
+    }
+
+    /// Deallocate the given allocation from the tier owning it. This is a no-op for the bump tier.
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        if !self.bump.owns(ptr, layout) {
+            self.fallback.dealloc(ptr, layout);
+        }
+    }
+
+    /// Resize the given allocation in its tier, moving a bump allocation to the fallback tier
+    /// if the bump arena can't hold its new size.
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        if !self.bump.owns(ptr, layout) {
+            return self.fallback.realloc(ptr, layout, new_size);
+        }
+
+        let new_ptr = self.bump.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            return new_ptr;
+        }
+        let new_ptr = self
+            .fallback
+            .alloc(Layout::from_size_align_unchecked(new_size, layout.align()));
+        if !new_ptr.is_null() {
+            ptr::copy_nonoverlapping(ptr, new_ptr, layout.size().min(new_size));
+        }
+        new_ptr
+    }
+}
+
+impl<const N: usize, F: RegionAllocator> RegionAllocator for TieredAllocator<N, F> {
+    /// Release every allocation of both tiers at once.
+    unsafe fn reset(&self) {
+        self.bump.reset();
+        self.fallback.reset();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{free_list::FreeListAllocator, tiered::*};
+
+    #[test]
+    fn spill_to_fallback_when_bump_full() {
+        let tiered = TieredAllocator::<64, _>::new(FreeListAllocator::<1024>::new());
+        let layout = Layout::new::<[u64; 4]>();
+
+        let first = unsafe { tiered.alloc(layout) };
+        let second = unsafe { tiered.alloc(layout) };
+        assert!(tiered.bump().owns(first, layout));
+        assert!(tiered.bump().owns(second, layout));
+        assert!(tiered.fallback().is_empty());
+
+        let spilled = unsafe { tiered.alloc(layout) };
+        assert!(!spilled.is_null());
+        assert!(!tiered.bump().owns(spilled, layout));
+        assert!(!tiered.fallback().is_empty());
+
+        unsafe {
+            tiered.dealloc(first, layout);
+            tiered.dealloc(spilled, layout);
+        }
+        assert!(tiered.fallback().is_empty());
+        assert_eq!(2, tiered.bump().allocation_count());
+    }
+
+    #[test]
+    fn reset_bump_keeps_fallback_allocations() {
+        let tiered = TieredAllocator::<32, _>::new(FreeListAllocator::<1024>::new());
+        let layout = Layout::new::<[u64; 4]>();
+        let bumped = unsafe { tiered.alloc(layout) };
+        let spilled = unsafe { tiered.alloc(layout) };
+        unsafe { spilled.write_bytes(7, layout.size()) };
+
+        unsafe { tiered.reset_bump() };
+        assert_eq!(bumped, unsafe { tiered.alloc(layout) });
+        assert_eq!(7, unsafe { *spilled });
+        assert!(!tiered.fallback().is_empty());
+
+        unsafe { tiered.reset() };
+        assert!(tiered.fallback().is_empty());
+    }
+
+    #[test]
+    fn realloc_moves_to_fallback_when_bump_full() {
+        let tiered = TieredAllocator::<32, _>::new(FreeListAllocator::<1024>::new());
+        let layout = Layout::new::<[u8; 16]>();
+        let ptr = unsafe { tiered.alloc(layout) };
+        unsafe { ptr.write_bytes(3, layout.size()) };
+
+        // The tail allocation grows in place in the bump arena
+        let grown = unsafe { tiered.realloc(ptr, layout, 32) };
+        assert_eq!(ptr, grown);
+
+        let moved = unsafe { tiered.realloc(grown, Layout::new::<[u8; 32]>(), 64) };
+        assert!(!tiered.bump().owns(moved, Layout::new::<[u8; 64]>()));
+        let bytes = unsafe { std::slice::from_raw_parts(moved, 16) };
+        assert!(bytes.iter().all(|byte| *byte == 3));
+
+        unsafe { tiered.dealloc(moved, Layout::new::<[u8; 64]>()) };
+        assert!(tiered.fallback().is_empty());
+    }
+}
@@ -0,0 +1,90 @@
+//! Inspection methods called on the global allocator must not allocate while holding the allocator lock:
+//! the allocation would take the lock again, and deadlock.
+#![cfg(feature = "free_list")]
+
+use allocator::free_list::{FreeListAllocator, FreeListAllocatorBuilder};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    process, slice,
+    sync::mpsc,
+    thread,
+    time::Duration,
+};
+
+// Large enough for the test harness to report failures, which symbolizes backtraces
+#[global_allocator]
+static ALLOCATOR: FreeListAllocator<{ 1 << 26 }> =
+    FreeListAllocatorBuilder::new().live_tracking(true).build();
+
+/// Run `f` on another thread, aborting the test binary if it doesn't return in time.
+/// A deadlocked thread holds the allocator lock, so neither a panic nor a captured message could be reported.
+fn without_deadlock<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> T {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || sender.send(f()).unwrap());
+    match receiver.recv_timeout(Duration::from_secs(10)) {
+        Ok(output) => output,
+        Err(_) => process::abort(),
+    }
+}
+
+#[test]
+fn free_blocks() {
+    let values: Vec<Box<u64>> = (0..64).map(Box::new).collect();
+    let blocks = without_deadlock(|| ALLOCATOR.free_blocks().collect::<Vec<_>>());
+    assert!(!blocks.is_empty());
+    assert!(blocks
+        .windows(2)
+        .all(|pair| pair[0].offset < pair[1].offset));
+    drop(values);
+}
+
+#[test]
+fn snapshot_into() {
+    let value = Box::new(42u64);
+    // The snapshot is larger than the arena: its buffer is allocated from the system allocator
+    let layout = Layout::array::<u8>(ALLOCATOR.snapshot_size()).unwrap();
+    let buffer = unsafe { System.alloc_zeroed(layout) };
+    assert!(!buffer.is_null());
+    let address = buffer as usize;
+    without_deadlock(move || {
+        let snapshot = unsafe { slice::from_raw_parts_mut(address as *mut u8, layout.size()) };
+        ALLOCATOR.snapshot_into(snapshot);
+    });
+    unsafe { System.dealloc(buffer, layout) };
+    drop(value);
+}
+
+#[test]
+fn report_live_and_heap_dump() {
+    let value = Box::new(42u64);
+    let address = &*value as *const u64 as usize;
+    let (live, dump) = without_deadlock(|| (ALLOCATOR.report_live(), ALLOCATOR.heap_dump()));
+    let offset = ALLOCATOR.offset_of(address as *const u8).unwrap();
+    assert!(live.contains(&(offset, 8)));
+    assert!(dump
+        .iter()
+        .any(|alloc| alloc.offset == offset && alloc.size == 8 && alloc.align == 8));
+    drop(value);
+}
+
+#[test]
+fn checkpoint() {
+    let checkpoint = without_deadlock(|| ALLOCATOR.checkpoint());
+    drop(checkpoint);
+}
+
+#[test]
+#[cfg(feature = "alloc_age")]
+fn allocation_ages() {
+    let value = Box::new(42u64);
+    let (oldest, old) = without_deadlock(|| {
+        (
+            ALLOCATOR.oldest_live_allocation(),
+            ALLOCATOR.live_allocations_older_than(0),
+        )
+    });
+    // Ages grew between both calls, but sequence numbers didn't change
+    let oldest = oldest.unwrap();
+    assert!(old.iter().any(|aged| aged.sequence == oldest.sequence));
+    drop(value);
+}
@@ -0,0 +1,33 @@
+//! `rewind` called on the global allocator must not allocate while holding the allocator lock (see the
+//! `global_allocator` test). It releases every allocation made since the checkpoint, including those of other
+//! threads: this binary holds a single test, so that no other test allocates in between.
+#![cfg(feature = "free_list")]
+
+use allocator::free_list::FreeListAllocator;
+use std::{process, sync::mpsc, thread, time::Duration};
+
+// Large enough for the test harness to report failures, which symbolizes backtraces
+#[global_allocator]
+static ALLOCATOR: FreeListAllocator<{ 1 << 26 }> = FreeListAllocator::new();
+
+#[test]
+fn rewind() {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let checkpoint = ALLOCATOR.checkpoint();
+        let free_bytes = ALLOCATOR.free_bytes();
+        let phase: Vec<u64> = (0..64).collect();
+        // The phase vector is released by rewinding, not by dropping it
+        std::mem::forget(phase);
+        unsafe { ALLOCATOR.rewind(&checkpoint) };
+        let rewound_free_bytes = ALLOCATOR.free_bytes();
+        drop(checkpoint);
+        sender.send((free_bytes, rewound_free_bytes)).unwrap();
+    });
+
+    // A deadlocked thread holds the allocator lock, so neither a panic nor a captured message could be reported
+    match receiver.recv_timeout(Duration::from_secs(10)) {
+        Ok((free_bytes, rewound_free_bytes)) => assert_eq!(free_bytes, rewound_free_bytes),
+        Err(_) => process::abort(),
+    }
+}